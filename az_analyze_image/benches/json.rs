@@ -0,0 +1,42 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Compares `serde_json` and `simd-json` parsing of a large Read result.
+//!
+//! Run with `cargo bench --features simd-json`.
+
+use az_analyze_image::v40::ImageAnalysisResult;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const READ: &str = include_str!("../tests/samples/v40/read.json");
+
+fn parse_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+
+    group.bench_function("serde_json", |b| {
+        b.iter(|| {
+            serde_json::from_str::<ImageAnalysisResult>(black_box(READ))
+                .unwrap()
+        })
+    });
+
+    group.bench_function("simd_json", |b| {
+        b.iter_batched_ref(
+            || READ.as_bytes().to_vec(),
+            |body| {
+                simd_json::serde::from_slice::<ImageAnalysisResult>(black_box(
+                    body,
+                ))
+                .unwrap()
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse_read);
+criterion_main!(benches);
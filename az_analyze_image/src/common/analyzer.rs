@@ -0,0 +1,63 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! The `ImageAnalyzer` trait of both API versions.
+
+/// Defines the `ImageAnalyzer` trait of the `Client` in scope, whose analysis
+/// result is `$result`, and implements it for the client. `$example` is the
+/// example section of the documentation of the trait.
+macro_rules! image_analyzer {
+    ($result:ty, $example:literal) => {
+        /// The analysis operations of a [`Client`], for code that should not
+        /// depend on a real client, e.g. to unit test it without calling
+        /// Azure.
+        ///
+        /// The returned futures are `Send`, except on `wasm32`.
+        ///
+        /// # Example
+        ///
+        #[doc = $example]
+        #[cfg_attr(not(target_arch = "wasm32"), ::async_trait::async_trait)]
+        #[cfg_attr(target_arch = "wasm32", ::async_trait::async_trait(?Send))]
+        pub trait ImageAnalyzer {
+            /// Analyze the image at `image_url`, see
+            /// [`Client::analyze_image_url`].
+            async fn analyze_image_url(
+                &self,
+                image_url: &str,
+                options: AnalyzeImageOptions<'_>,
+            ) -> Result<$result>;
+
+            /// Analyze the image `image_data`, see [`Client::analyze_image`].
+            async fn analyze_image(
+                &self,
+                image_data: &[u8],
+                options: AnalyzeImageOptions<'_>,
+            ) -> Result<$result>;
+        }
+
+        #[cfg_attr(not(target_arch = "wasm32"), ::async_trait::async_trait)]
+        #[cfg_attr(target_arch = "wasm32", ::async_trait::async_trait(?Send))]
+        impl ImageAnalyzer for Client {
+            async fn analyze_image_url(
+                &self,
+                image_url: &str,
+                options: AnalyzeImageOptions<'_>,
+            ) -> Result<$result> {
+                Client::analyze_image_url(self, image_url, options).await
+            }
+
+            async fn analyze_image(
+                &self,
+                image_data: &[u8],
+                options: AnalyzeImageOptions<'_>,
+            ) -> Result<$result> {
+                Client::analyze_image(self, image_data, options).await
+            }
+        }
+    };
+}
+
+pub(crate) use image_analyzer;
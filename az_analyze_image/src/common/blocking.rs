@@ -0,0 +1,21 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Running CPU-bound work, such as decoding images, off the async executor.
+
+/// Runs `f` on the blocking thread pool of the current Tokio runtime, so that
+/// it does not stall other tasks, and returns its result.
+///
+/// If `f` panics, the panic is resumed on the calling task.
+pub(crate) async fn run<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
@@ -0,0 +1,187 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! In-memory caching of analysis results for repeated identical requests.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+/// A least-recently-used cache of results.
+///
+/// Keys are compared in full, so distinct keys never share an entry. Lookups
+/// and insertions take logarithmic time in the number of entries.
+#[derive(Debug)]
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    /// Values and the tick of their last use.
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by the tick of their last use, from the least to the most recent.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K: Clone + Eq + Hash, V> LruCache<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Returns the value for `key`, marking it as the most recently used.
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        self.tick += 1;
+        let (value, last_used) = self.entries.get_mut(key)?;
+
+        if let Some(key) = self.order.remove(&*last_used) {
+            self.order.insert(self.tick, key);
+        }
+        *last_used = self.tick;
+
+        Some(value)
+    }
+
+    /// Inserts `value` for `key`, evicting the least recently used entry if
+    /// the cache is full.
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            self.order.remove(last_used);
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+
+        if self.entries.len() > self.capacity {
+            if let Some((_, evicted)) = self.order.pop_first() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// A 128-bit digest of the parts of a request that determine its result.
+///
+/// Keys have a fixed size regardless of the size of the image, so entries do
+/// not keep the image data. Distinct requests share a key only in the event
+/// of a digest collision, which is negligibly likely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct RequestKey(u128);
+
+impl RequestKey {
+    /// Returns the key of a request sent to `url` with `query_params`.
+    pub(crate) fn new(
+        url: &str,
+        query_params: &[(&'static str, String)],
+        image: RequestImage<'_>,
+    ) -> Self {
+        // Two 64-bit SipHash digests of the request, made distinct by a seed.
+        let digest = |seed: u8| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            url.hash(&mut hasher);
+            query_params.hash(&mut hasher);
+            image.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        RequestKey(u128::from(digest(0)) << 64 | u128::from(digest(1)))
+    }
+}
+
+/// The image of a request, by URL or by content.
+#[derive(Debug, Clone, Copy, Hash)]
+pub(crate) enum RequestImage<'a> {
+    Url(&'a str),
+    Data(&'a [u8]),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_cache_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        assert_eq!(cache.get(&1), Some(&"one"));
+
+        cache.insert(3, "three");
+
+        assert_eq!(cache.get(&1), Some(&"one"));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some(&"three"));
+    }
+
+    #[test]
+    fn test_lru_cache_replace_and_clear() {
+        let mut cache = LruCache::new(1);
+
+        cache.insert(1, "one");
+        cache.insert(1, "uno");
+        assert_eq!(cache.get(&1), Some(&"uno"));
+
+        cache.clear();
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn test_lru_cache_replace_marks_most_recently_used() {
+        let mut cache = LruCache::new(2);
+
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        cache.insert(1, "uno");
+        cache.insert(3, "three");
+
+        assert_eq!(cache.get(&1), Some(&"uno"));
+        assert_eq!(cache.get(&2), None);
+    }
+
+    #[test]
+    fn test_lru_cache_zero_capacity() {
+        let mut cache = LruCache::new(0);
+
+        cache.insert(1, "one");
+
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn test_request_key_digests_request() {
+        let params = vec![("api-version", "1".to_string())];
+        let key = |image| RequestKey::new("url", &params, image);
+        let mut cache = LruCache::new(4);
+
+        cache.insert(key(RequestImage::Data(&[1, 2])), "data");
+        cache.insert(key(RequestImage::Url("a")), "url");
+
+        assert_eq!(cache.get(&key(RequestImage::Data(&[1, 2]))), Some(&"data"));
+        assert_eq!(cache.get(&key(RequestImage::Data(&[1, 3]))), None);
+        assert_eq!(cache.get(&key(RequestImage::Url("a"))), Some(&"url"));
+        assert_eq!(cache.get(&key(RequestImage::Url("b"))), None);
+        assert_ne!(
+            key(RequestImage::Url("a")),
+            RequestKey::new("url", &[], RequestImage::Url("a"))
+        );
+        assert_ne!(
+            key(RequestImage::Url("a")),
+            RequestKey::new("other", &params, RequestImage::Url("a"))
+        );
+    }
+}
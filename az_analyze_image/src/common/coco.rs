@@ -0,0 +1,72 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Export of detections to [COCO](https://cocodataset.org/#format-data)-style
+//! annotation JSON.
+
+use super::rect::PixelRect;
+use super::{Number, PixelCount};
+use serde_json::{json, Value};
+
+/// A detection to export: its label, rectangle and, if known, confidence.
+pub(crate) struct Detection<'a> {
+    pub(crate) label: &'a str,
+    pub(crate) rect: PixelRect,
+    pub(crate) score: Option<Number>,
+}
+
+/// Returns a COCO document with the image `image_id` of size `image_size`
+/// (width, height), the distinct labels of `detections` as categories, and an
+/// annotation per detection.
+///
+/// Category and annotation IDs start at 1, in order of first appearance.
+pub(crate) fn annotations<'a>(
+    detections: impl IntoIterator<Item = Detection<'a>>,
+    image_id: u64,
+    image_size: (PixelCount, PixelCount),
+) -> Value {
+    let mut labels: Vec<&str> = Vec::new();
+    let mut annotations = Vec::new();
+
+    for (i, detection) in detections.into_iter().enumerate() {
+        let category = match labels.iter().position(|&l| l == detection.label) {
+            Some(index) => index,
+            None => {
+                labels.push(detection.label);
+                labels.len() - 1
+            }
+        };
+        let rect = detection.rect;
+
+        let mut annotation = json!({
+            "id": i + 1,
+            "image_id": image_id,
+            "category_id": category + 1,
+            "bbox": [rect.x, rect.y, rect.w, rect.h],
+            "area": rect.area(),
+            "iscrowd": 0,
+        });
+        if let Some(score) = detection.score {
+            annotation["score"] = json!(score);
+        }
+        annotations.push(annotation);
+    }
+
+    let categories: Vec<Value> = labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| json!({ "id": i + 1, "name": label }))
+        .collect();
+
+    json!({
+        "images": [{
+            "id": image_id,
+            "width": image_size.0,
+            "height": image_size.1,
+        }],
+        "categories": categories,
+        "annotations": annotations,
+    })
+}
@@ -0,0 +1,45 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! gzip compression of request bodies.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// Value of the `Content-Encoding` header for gzip-compressed bodies.
+pub(crate) const GZIP: &str = "gzip";
+
+/// Compresses `data` with gzip.
+pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+
+    // Writing to a `Vec` cannot fail.
+    encoder
+        .write_all(data)
+        .expect("in-memory gzip write failed");
+    encoder.finish().expect("in-memory gzip write failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let data = b"image data image data image data".repeat(16);
+
+        let compressed = gzip(&data);
+        assert!(compressed.len() < data.len());
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+}
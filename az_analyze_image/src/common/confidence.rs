@@ -0,0 +1,136 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+use super::Number;
+use std::cmp::Ordering;
+
+/// Formatting helpers for confidence scores, which the API reports as values
+/// ranging from 0 to 1.
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::ConfidenceExt;
+///
+/// assert_eq!(0.9966.as_percent_string(1), "99.7%");
+/// ```
+pub trait ConfidenceExt {
+    /// Returns the confidence as a percentage, e.g. `0.25` becomes `25.0`.
+    fn as_percent(&self) -> Number;
+
+    /// Returns the confidence as a percentage string rounded to `decimals`
+    /// decimal places, e.g. `0.9966` becomes `"99.7%"` for `decimals = 1`.
+    fn as_percent_string(&self, decimals: usize) -> String;
+}
+
+impl ConfidenceExt for Number {
+    fn as_percent(&self) -> Number {
+        self * 100.0
+    }
+
+    fn as_percent_string(&self, decimals: usize) -> String {
+        format!("{:.*}%", decimals, self.as_percent())
+    }
+}
+
+/// Formats `n` rounded to `sig` significant digits, e.g. `0.9966049194335938`
+/// becomes `"0.997"` for `sig = 3`, for readable logs.
+///
+/// Digits before the decimal point are never rounded away, and NaN and
+/// infinite values are formatted as is. A `sig` of 0 is treated as 1.
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::format_confidence;
+///
+/// assert_eq!(format_confidence(0.9966049194335938, 4), "0.9966");
+/// ```
+pub fn format_confidence(n: Number, sig: usize) -> String {
+    let sig = sig.max(1);
+
+    if !n.is_finite() {
+        return n.to_string();
+    }
+    if n == 0.0 {
+        return format!("{:.*}", sig - 1, n);
+    }
+
+    let magnitude = n.abs().log10().floor() as i64;
+    let decimals = (sig as i64 - 1 - magnitude).max(0) as usize;
+    let formatted = format!("{:.*}", decimals, n);
+
+    // Rounding up may add a digit, e.g. 0.9996 to "1.000" for `sig = 3`.
+    let rounded: Number = formatted.parse().unwrap_or(n);
+    if decimals > 0 && rounded.abs().log10().floor() as i64 > magnitude {
+        return format!("{:.*}", decimals - 1, n);
+    }
+
+    formatted
+}
+
+/// Compares confidences, treating NaN as lower than any other confidence (and
+/// equal to NaN), so that malformed confidences sort last in descending order.
+pub(crate) fn cmp_confidence(a: &Number, b: &Number) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.total_cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_percent() {
+        assert_eq!(0.25.as_percent(), 25.0);
+        assert_eq!(1.0.as_percent(), 100.0);
+    }
+
+    #[test]
+    fn test_format_confidence() {
+        assert_eq!(format_confidence(0.9966049, 3), "0.997");
+        assert_eq!(format_confidence(0.9966049, 4), "0.9966");
+        assert_eq!(format_confidence(0.0123456, 2), "0.012");
+        assert_eq!(format_confidence(0.5, 3), "0.500");
+        assert_eq!(format_confidence(0.0, 3), "0.00");
+    }
+
+    #[test]
+    fn test_format_confidence_zero_sig() {
+        assert_eq!(format_confidence(0.9966049, 0), "1");
+        assert_eq!(format_confidence(0.0123456, 0), "0.01");
+        assert_eq!(format_confidence(0.0, 0), "0");
+    }
+
+    #[test]
+    fn test_format_confidence_rounds_up_to_next_magnitude() {
+        assert_eq!(format_confidence(0.9996, 3), "1.00");
+        assert_eq!(format_confidence(1.0, 2), "1.0");
+        assert_eq!(format_confidence(Number::NAN, 3), "NaN");
+    }
+
+    #[test]
+    fn test_cmp_confidence_nan_lowest() {
+        let mut confidences = [0.5, Number::NAN, 0.9, 0.0];
+
+        confidences.sort_by(|a, b| cmp_confidence(b, a));
+
+        assert_eq!(&confidences[..3], &[0.9, 0.5, 0.0]);
+        assert!(confidences[3].is_nan());
+        assert_eq!(cmp_confidence(&Number::NAN, &-1.0), Ordering::Less);
+        assert_eq!(cmp_confidence(&Number::NAN, &Number::NAN), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_as_percent_string() {
+        assert_eq!(0.9966.as_percent_string(1), "99.7%");
+        assert_eq!(0.0.as_percent_string(1), "0.0%");
+        assert_eq!(0.5.as_percent_string(0), "50%");
+    }
+}
@@ -0,0 +1,137 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Decoding of base64 `data:` URIs, e.g. `data:image/png;base64,...`.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// Decodes the payload of a base64-encoded image data URI.
+///
+/// Returns a description of the problem if `uri` is not of the form
+/// `data:image/<subtype>[;<parameter>]*;base64,<payload>`, the subtype is not
+/// a format supported by the API, or the payload is empty, not valid base64,
+/// or not an image of that format. Whitespace in the payload is ignored.
+pub(crate) fn decode(uri: &str) -> Result<Vec<u8>, &'static str> {
+    let uri = uri.trim();
+
+    let rest = uri
+        .get(..5)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("data:"))
+        .map(|_| &uri[5..])
+        .ok_or("missing `data:` scheme")?;
+
+    let (metadata, payload) = rest
+        .split_once(',')
+        .ok_or("missing `,` before the payload")?;
+
+    let mut parameters = metadata.split(';');
+    let media_type = parameters.next().unwrap_or_default().trim();
+    if !media_type
+        .get(..6)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("image/"))
+    {
+        return Err("media type is not `image/*`");
+    }
+    if !parameters
+        .next_back()
+        .is_some_and(|encoding| encoding.trim().eq_ignore_ascii_case("base64"))
+    {
+        return Err("payload is not base64-encoded");
+    }
+
+    let payload: String = payload
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    if payload.is_empty() {
+        return Err("empty payload");
+    }
+
+    let data = STANDARD
+        .decode(payload)
+        .map_err(|_| "invalid base64 payload")?;
+    match has_signature(&media_type[6..].to_ascii_lowercase(), &data) {
+        Some(true) => Ok(data),
+        Some(false) => Err("payload does not match the media type"),
+        None => Err("image format is not supported"),
+    }
+}
+
+/// Returns whether `data` starts with the file signature of the
+/// `image/<subtype>` media type, or `None` if the API does not support the
+/// format.
+fn has_signature(subtype: &str, data: &[u8]) -> Option<bool> {
+    Some(match subtype {
+        "jpeg" | "jpg" | "pjpeg" | "mpo" => data.starts_with(b"\xFF\xD8\xFF"),
+        "png" => data.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "gif" => data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a"),
+        "bmp" | "x-ms-bmp" => data.starts_with(b"BM"),
+        "webp" => {
+            data.starts_with(b"RIFF") && data.get(8..12) == Some(&b"WEBP"[..])
+        }
+        "x-icon" | "vnd.microsoft.icon" => data.starts_with(b"\0\0\x01\0"),
+        "tiff" => data.starts_with(b"II*\0") || data.starts_with(b"MM\0*"),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A 1 x 1 PNG.
+    pub(crate) const PNG_DATA_URI: &str = "data:image/png;base64,\
+        iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA\
+        60e6kgAAAABJRU5ErkJggg==";
+
+    #[test]
+    fn test_decode() {
+        let data = decode(PNG_DATA_URI).unwrap();
+
+        assert!(data.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn test_decode_parameters_and_whitespace() {
+        assert_eq!(
+            decode("DATA:Image/JPEG;name=a.jpg;BASE64, /9j/\n4A==").unwrap(),
+            [0xFF, 0xD8, 0xFF, 0xE0]
+        );
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        assert_eq!(
+            decode("image/png;base64,AAAA"),
+            Err("missing `data:` scheme")
+        );
+        assert_eq!(
+            decode("data:image/png;base64"),
+            Err("missing `,` before the payload")
+        );
+        assert_eq!(
+            decode("data:text/plain;base64,AAAA"),
+            Err("media type is not `image/*`")
+        );
+        assert_eq!(
+            decode("data:image/svg+xml;base64,AAAA"),
+            Err("image format is not supported")
+        );
+        assert_eq!(
+            decode("data:image/png,AAAA"),
+            Err("payload is not base64-encoded")
+        );
+        assert_eq!(decode("data:image/png;base64,"), Err("empty payload"));
+        assert_eq!(
+            decode("data:image/png;base64,not base64!"),
+            Err("invalid base64 payload")
+        );
+        assert_eq!(
+            decode("data:image/png;base64,/9j/4A=="),
+            Err("payload does not match the media type")
+        );
+    }
+}
@@ -0,0 +1,50 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+/// Classification of error responses of the Analyze Image API, implemented
+/// for the error responses of every API version so that retry and alerting
+/// logic can be written once.
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::ApiError;
+///
+/// fn should_retry(err: &impl ApiError) -> bool {
+///     err.is_retryable()
+/// }
+/// ```
+pub trait ApiError {
+    /// Returns `true` if the request failed for a transient reason, e.g. the
+    /// service was unavailable or rate limited, and may succeed if retried.
+    ///
+    /// Both the top-level code and the codes of any inner errors are checked.
+    fn is_retryable(&self) -> bool;
+
+    /// Returns the top-level error code, e.g. `"InvalidRequest"`.
+    fn code(&self) -> &str;
+
+    /// Returns the error message.
+    fn message(&self) -> &str;
+}
+
+/// Returns `true` if `code` is the code of a transient failure, either the
+/// top-level code of an error response or that of a nested inner error.
+pub(crate) fn is_retryable_code(code: &str) -> bool {
+    matches!(
+        code,
+        "429"
+            | "InternalServerError"
+            | "ServiceUnavailable"
+            | "Timeout"
+            | "TooManyRequests"
+    )
+}
+
+/// The error returned when parsing the name of an unknown visual feature, e.g.
+/// from configuration.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown visual feature `{0}`")]
+pub struct ParseFeatureError(pub String);
@@ -0,0 +1,132 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Tolerant parsing of the bodies of error responses, so that a malformed
+//! body, e.g. from a gateway or truncated in transit, does not mask the error
+//! status with a decode error.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Maximum number of characters of a raw body used as a message.
+const MAX_RAW_MESSAGE_CHARS: usize = 512;
+
+/// The body of an error response.
+#[derive(Debug)]
+pub(crate) enum ErrorBody<E> {
+    /// A well-formed error response.
+    Parsed(E),
+
+    /// The message of a body that is not a well-formed error response: its
+    /// `message` or `error.message` string if it is JSON with one, or else the
+    /// body itself, shortened.
+    Message(String),
+}
+
+/// Reads and parses the body of the error `response`.
+///
+/// Never fails: a body that cannot be read is treated as empty.
+pub(crate) async fn from_response<E: DeserializeOwned>(
+    response: reqwest::Response,
+) -> ErrorBody<E> {
+    let body = response.bytes().await.unwrap_or_default();
+
+    parse(&body)
+}
+
+/// Parses `body` as an error response `E`, falling back to extracting a
+/// message from it.
+pub(crate) fn parse<E: DeserializeOwned>(body: &[u8]) -> ErrorBody<E> {
+    if let Ok(err) = serde_json::from_slice(body) {
+        return ErrorBody::Parsed(err);
+    }
+
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|value| find_message(&value))
+        .unwrap_or_else(|| raw_message(body));
+
+    ErrorBody::Message(message)
+}
+
+fn find_message(value: &Value) -> Option<String> {
+    let error = value.get("error");
+
+    value
+        .get("message")
+        .or_else(|| error.and_then(|error| error.get("message")))
+        .or(error)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn raw_message(body: &[u8]) -> String {
+    String::from_utf8_lossy(body)
+        .trim()
+        .chars()
+        .take(MAX_RAW_MESSAGE_CHARS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v40::ErrorResponse;
+
+    const ERROR: &str =
+        include_str!("../../tests/samples/v40/error_image_too_large.json");
+
+    fn message(body: &[u8]) -> String {
+        match parse::<ErrorResponse>(body) {
+            ErrorBody::Message(message) => message,
+            ErrorBody::Parsed(err) => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let body = parse::<ErrorResponse>(ERROR.as_bytes());
+
+        assert!(matches!(
+            body,
+            ErrorBody::Parsed(err) if err.error.code == "InvalidRequest"
+        ));
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        // Every truncation of a well-formed body yields some message.
+        for len in 0..ERROR.trim_end().len() {
+            let message = message(&ERROR.as_bytes()[..len]);
+
+            assert_eq!(message, ERROR[..len].trim());
+        }
+    }
+
+    #[test]
+    fn test_parse_wrong_shape() {
+        assert_eq!(message(br#"{"message": "Bad gateway"}"#), "Bad gateway");
+        assert_eq!(
+            message(br#"{"error": {"message": "Quota exceeded"}}"#),
+            "Quota exceeded"
+        );
+        assert_eq!(message(br#"{"error": "Unauthorized"}"#), "Unauthorized");
+        assert_eq!(message(br#"{"error": 42}"#), r#"{"error": 42}"#);
+        assert_eq!(message(b"[1, 2]"), "[1, 2]");
+    }
+
+    #[test]
+    fn test_parse_raw() {
+        assert_eq!(message(b""), "");
+        assert_eq!(
+            message(b"  <html>Bad Gateway</html>\n"),
+            "<html>Bad Gateway</html>"
+        );
+        assert_eq!(message(b"\xff\xfe"), "\u{fffd}\u{fffd}");
+
+        let long = "x".repeat(2 * MAX_RAW_MESSAGE_CHARS);
+        assert_eq!(message(long.as_bytes()).len(), MAX_RAW_MESSAGE_CHARS);
+    }
+}
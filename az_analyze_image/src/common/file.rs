@@ -0,0 +1,37 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Streaming of image files as request bodies.
+
+use std::io;
+use std::path::Path;
+
+/// An image file opened by [`open`].
+pub(crate) enum FileBody {
+    /// A body streaming the file, and its length in bytes.
+    Stream(reqwest::Body, u64),
+
+    /// The file was not opened for upload, as its size in bytes exceeds the
+    /// limit.
+    TooLarge(u64),
+}
+
+/// Opens the file at `path` as a streamed request body, unless its size
+/// exceeds `limit`.
+///
+/// The size is meant to be sent as the `Content-Length` of the request, which
+/// bounds the upload: if the file grows after its size is checked, no more
+/// than the checked size is sent.
+pub(crate) async fn open(path: &Path, limit: u64) -> io::Result<FileBody> {
+    let file = tokio::fs::File::open(path).await?;
+    let length = file.metadata().await?.len();
+    if length > limit {
+        return Ok(FileBody::TooLarge(length));
+    }
+
+    let body =
+        reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file));
+    Ok(FileBody::Stream(body, length))
+}
@@ -0,0 +1,85 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Extraction of the frames of animated GIFs.
+
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, ImageFormat};
+use std::io::Cursor;
+
+/// Decodes every `stride`-th frame of the GIF `data`, starting with the first,
+/// and re-encodes each as PNG.
+///
+/// # Panics
+///
+/// Panics if `stride` is 0.
+pub(crate) fn gif_frames_png(
+    data: &[u8],
+    stride: usize,
+) -> Result<Vec<Vec<u8>>, image::ImageError> {
+    let decoder = GifDecoder::new(Cursor::new(data))?;
+
+    decoder
+        .into_frames()
+        .step_by(stride)
+        .map(|frame| {
+            let mut png = Vec::new();
+            frame?
+                .into_buffer()
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok(png)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Frame, GenericImageView, Rgba, RgbaImage};
+
+    /// Returns a GIF with one solid-colored frame per color.
+    pub(crate) fn gif(colors: &[[u8; 4]]) -> Vec<u8> {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            encoder
+                .encode_frames(colors.iter().map(|&color| {
+                    Frame::new(RgbaImage::from_pixel(4, 4, Rgba(color)))
+                }))
+                .unwrap();
+        }
+        gif
+    }
+
+    #[test]
+    fn test_gif_frames_png() {
+        let colors = [[255, 0, 0, 255], [0, 0, 255, 255], [0, 255, 0, 255]];
+        let data = gif(&colors);
+
+        let frames = gif_frames_png(&data, 1).unwrap();
+        assert_eq!(frames.len(), 3);
+
+        for (png, color) in frames.iter().zip(colors) {
+            let image =
+                image::load_from_memory_with_format(png, ImageFormat::Png)
+                    .unwrap();
+            assert_eq!(image.dimensions(), (4, 4));
+            assert_eq!(image.get_pixel(0, 0), Rgba(color));
+        }
+    }
+
+    #[test]
+    fn test_gif_frames_png_stride() {
+        let data = gif(&[[0, 0, 0, 255]; 5]);
+
+        assert_eq!(gif_frames_png(&data, 2).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_gif_frames_png_invalid_gif() {
+        assert!(gif_frames_png(&[0; 16], 1).is_err());
+    }
+}
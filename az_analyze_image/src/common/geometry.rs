@@ -0,0 +1,61 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+use super::rect::PixelRect;
+use super::{Number, PixelCount};
+
+/// Returns `width / height`, or 0 if `height` is 0.
+pub(crate) fn aspect_ratio(width: PixelCount, height: PixelCount) -> Number {
+    if height == 0 {
+        return 0.0;
+    }
+
+    width as Number / height as Number
+}
+
+/// Sorts `items` top-to-bottom, left-to-right.
+///
+/// `position` returns the `(x, y)` coordinates of the top left point of an
+/// item. Items whose `y` lies within `row_tolerance` pixels of the first item
+/// of a row are considered to be on the same row and are ordered by `x`.
+pub(crate) fn reading_order<'a, T>(
+    items: impl IntoIterator<Item = &'a T>,
+    row_tolerance: PixelCount,
+    position: impl Fn(&T) -> (PixelCount, PixelCount),
+) -> Vec<&'a T> {
+    let mut items: Vec<&T> = items.into_iter().collect();
+    items.sort_by_key(|item| {
+        let (x, y) = position(item);
+        (y, x)
+    });
+
+    let mut ordered = Vec::with_capacity(items.len());
+    let mut row: Vec<&T> = Vec::new();
+    let mut row_y = 0;
+
+    for item in items {
+        let (_, y) = position(item);
+        if !row.is_empty() && y - row_y > row_tolerance {
+            row.sort_by_key(|item| position(item).0);
+            ordered.append(&mut row);
+        }
+        if row.is_empty() {
+            row_y = y;
+        }
+        row.push(item);
+    }
+    row.sort_by_key(|item| position(item).0);
+    ordered.append(&mut row);
+
+    ordered
+}
+
+/// Returns the smallest rectangle enclosing all `rects`, or `None` if there are
+/// none.
+pub(crate) fn union(
+    rects: impl IntoIterator<Item = PixelRect>,
+) -> Option<PixelRect> {
+    rects.into_iter().reduce(|union, rect| union.union(&rect))
+}
@@ -0,0 +1,41 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! HTTP protocol version selection for the clients.
+
+/// HTTP protocol version used to talk to the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum HttpVersion {
+    /// Negotiate the version with the server.
+    #[default]
+    Auto,
+
+    /// Use HTTP/1.1 only, e.g. behind proxies that break HTTP/2.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    Http1Only,
+
+    /// Use HTTP/2 without negotiation, for servers known to support it.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    Http2PriorKnowledge,
+}
+
+impl HttpVersion {
+    /// Configures `builder` to use this version.
+    pub(crate) fn configure(
+        self,
+        builder: reqwest::ClientBuilder,
+    ) -> reqwest::ClientBuilder {
+        // In the browser, `fetch` chooses the version.
+        #[cfg(target_arch = "wasm32")]
+        return builder;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        match self {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Images generated for unit tests.
+
+use image::{ImageFormat, RgbImage};
+use std::io::Cursor;
+
+/// Returns a black PNG of the given size.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    encode(RgbImage::new(width, height))
+}
+
+/// Returns a PNG of pseudo-random noise, which compresses poorly.
+pub fn noise_png(width: u32, height: u32) -> Vec<u8> {
+    let mut state: u32 = 0x2545_F491;
+    let image = RgbImage::from_fn(width, height, |_, _| {
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        };
+        image::Rgb([next(), next(), next()])
+    });
+
+    encode(image)
+}
+
+fn encode(image: RgbImage) -> Vec<u8> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    png
+}
@@ -3,7 +3,43 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0 OR MIT
 */
 
+#[cfg(feature = "analyzer")]
+pub(crate) mod analyzer;
+#[cfg(feature = "image")]
+pub(crate) mod blocking;
+pub(crate) mod cache;
+pub(crate) mod coco;
+#[cfg(feature = "compression")]
+pub(crate) mod compression;
+pub mod confidence;
+#[cfg(feature = "data-uri")]
+pub(crate) mod data_uri;
+pub mod error;
+pub(crate) mod error_body;
+#[cfg(feature = "fs")]
+pub(crate) mod file;
+#[cfg(feature = "image")]
+pub(crate) mod frames;
+pub(crate) mod geometry;
+pub(crate) mod http;
+pub mod rect;
+pub(crate) mod request_id;
+#[cfg(feature = "image")]
+pub(crate) mod resize;
 pub mod secret;
+pub(crate) mod success_body;
+pub mod tags;
+
+#[cfg(all(test, feature = "image"))]
+pub(crate) mod images;
+#[cfg(test)]
+pub(crate) mod round_trip;
 
 pub type PixelCount = u32;
 pub type Number = f64;
+
+/// Images must be greater than `MIN_DIMENSION` x `MIN_DIMENSION` pixels.
+pub(crate) const MIN_DIMENSION: PixelCount = 50;
+
+/// Images must be less than `MAX_DIMENSION` x `MAX_DIMENSION` pixels.
+pub(crate) const MAX_DIMENSION: PixelCount = 16_000;
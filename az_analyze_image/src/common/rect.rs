@@ -0,0 +1,307 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+use super::{Number, PixelCount};
+use serde::{Deserialize, Serialize};
+
+/// An axis-aligned rectangle, given by the coordinates of its top left point,
+/// its width and its height.
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::{NormRect, PixelRect};
+///
+/// let rect = PixelRect { x: 50, y: 25, w: 100, h: 50 };
+///
+/// assert_eq!(
+///     rect.normalize(200, 100),
+///     Some(NormRect { x: 0.25, y: 0.25, w: 0.5, h: 0.5 })
+/// );
+/// assert_eq!(rect.normalize(0, 100), None);
+/// ```
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize,
+)]
+pub struct Rect<T> {
+    /// X-coordinate of the top left point.
+    pub x: T,
+
+    /// Y-coordinate of the top left point.
+    pub y: T,
+
+    /// Width.
+    pub w: T,
+
+    /// Height.
+    pub h: T,
+}
+
+/// A rectangle in pixels, as returned by the API.
+pub type PixelRect = Rect<PixelCount>;
+
+/// A rectangle relative to the size of an image, with coordinates and
+/// dimensions between 0 and 1 for rectangles inside the image.
+pub type NormRect = Rect<f32>;
+
+impl PixelRect {
+    /// Returns the rectangle relative to an image of the given size, or `None`
+    /// if the width or height of the image is zero.
+    pub fn normalize(
+        &self,
+        width: PixelCount,
+        height: PixelCount,
+    ) -> Option<NormRect> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let (width, height) = (width as f32, height as f32);
+
+        Some(Rect {
+            x: self.x as f32 / width,
+            y: self.y as f32 / height,
+            w: self.w as f32 / width,
+            h: self.h as f32 / height,
+        })
+    }
+
+    /// Returns the area of the rectangle, in pixels.
+    pub fn area(&self) -> u64 {
+        self.w as u64 * self.h as u64
+    }
+
+    /// Returns the intersection over union (IoU) of the rectangles, between 0
+    /// (disjoint) and 1 (identical).
+    pub fn iou(&self, other: &PixelRect) -> Number {
+        let overlap = |start: PixelCount, len: PixelCount, o_start, o_len| {
+            let end =
+                (start as u64 + len as u64).min(o_start as u64 + o_len as u64);
+            end.saturating_sub(start.max(o_start) as u64)
+        };
+
+        let intersection = overlap(self.x, self.w, other.x, other.w)
+            * overlap(self.y, self.h, other.y, other.h);
+        let union = self.area() + other.area() - intersection;
+
+        if union == 0 {
+            return 0.0;
+        }
+        intersection as Number / union as Number
+    }
+
+    /// Returns the part of the rectangle within an image of the given size,
+    /// e.g. before cropping. Rectangles entirely outside the image are clamped
+    /// to zero width or height at its edge.
+    pub fn clamp_to(&self, width: PixelCount, height: PixelCount) -> PixelRect {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+
+        Rect {
+            x,
+            y,
+            w: self.x.saturating_add(self.w).min(width) - x,
+            h: self.y.saturating_add(self.h).min(height) - y,
+        }
+    }
+
+    /// Returns `true` if `other` lies entirely within the rectangle, edges
+    /// included.
+    pub fn contains(&self, other: &PixelRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x as u64 + other.w as u64 <= self.x as u64 + self.w as u64
+            && other.y as u64 + other.h as u64 <= self.y as u64 + self.h as u64
+    }
+
+    /// Returns the smallest rectangle enclosing both rectangles.
+    pub fn union(&self, other: &PixelRect) -> PixelRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x.saturating_add(self.w))
+            .max(other.x.saturating_add(other.w));
+        let bottom = (self.y.saturating_add(self.h))
+            .max(other.y.saturating_add(other.h));
+
+        Rect {
+            x,
+            y,
+            w: right - x,
+            h: bottom - y,
+        }
+    }
+}
+
+impl NormRect {
+    /// Returns the rectangle in pixels of an image of the given size, rounding
+    /// to the nearest pixel. Negative values are clamped to 0.
+    pub fn to_pixels(
+        &self,
+        width: PixelCount,
+        height: PixelCount,
+    ) -> PixelRect {
+        let (width, height) = (width as f32, height as f32);
+
+        Rect {
+            x: (self.x * width).round() as PixelCount,
+            y: (self.y * height).round() as PixelCount,
+            w: (self.w * width).round() as PixelCount,
+            h: (self.h * height).round() as PixelCount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let rect = PixelRect {
+            x: 0,
+            y: 30,
+            w: 160,
+            h: 60,
+        };
+
+        assert_eq!(
+            rect.normalize(640, 120),
+            Some(NormRect {
+                x: 0.0,
+                y: 0.25,
+                w: 0.25,
+                h: 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn test_normalize_zero_size() {
+        let rect = PixelRect {
+            x: 0,
+            y: 0,
+            w: 10,
+            h: 10,
+        };
+
+        assert_eq!(rect.normalize(0, 120), None);
+        assert_eq!(rect.normalize(640, 0), None);
+    }
+
+    #[test]
+    fn test_normalize_to_pixels_round_trip() {
+        let rect = PixelRect {
+            x: 17,
+            y: 403,
+            w: 211,
+            h: 97,
+        };
+
+        assert_eq!(
+            rect.normalize(1280, 853).unwrap().to_pixels(1280, 853),
+            rect
+        );
+    }
+
+    #[test]
+    fn test_iou() {
+        let rect = PixelRect {
+            x: 0,
+            y: 0,
+            w: 10,
+            h: 10,
+        };
+        let shifted = PixelRect { x: 5, ..rect };
+        let disjoint = PixelRect { x: 20, ..rect };
+
+        assert_eq!(rect.iou(&rect), 1.0);
+        assert_eq!(rect.iou(&shifted), 50.0 / 150.0);
+        assert_eq!(shifted.iou(&rect), rect.iou(&shifted));
+        assert_eq!(rect.iou(&disjoint), 0.0);
+        assert_eq!(PixelRect::default().iou(&PixelRect::default()), 0.0);
+    }
+
+    #[test]
+    fn test_clamp_to() {
+        let rect = PixelRect {
+            x: 900,
+            y: 600,
+            w: 200,
+            h: 150,
+        };
+
+        assert_eq!(
+            rect.clamp_to(1038, 692),
+            PixelRect {
+                x: 900,
+                y: 600,
+                w: 138,
+                h: 92,
+            }
+        );
+        assert_eq!(rect.clamp_to(2000, 2000), rect);
+        assert_eq!(
+            rect.clamp_to(800, 500),
+            PixelRect {
+                x: 800,
+                y: 500,
+                w: 0,
+                h: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let rect = PixelRect {
+            x: 10,
+            y: 20,
+            w: 10,
+            h: 10,
+        };
+        let other = PixelRect {
+            x: 0,
+            y: 25,
+            w: 5,
+            h: 30,
+        };
+        let expected = PixelRect {
+            x: 0,
+            y: 20,
+            w: 20,
+            h: 35,
+        };
+
+        assert_eq!(rect.union(&other), expected);
+        assert_eq!(other.union(&rect), expected);
+        assert_eq!(rect.union(&rect), rect);
+    }
+
+    #[test]
+    fn test_contains() {
+        let rect = PixelRect {
+            x: 10,
+            y: 20,
+            w: 10,
+            h: 10,
+        };
+        let inside = PixelRect {
+            x: 12,
+            y: 20,
+            w: 8,
+            h: 5,
+        };
+        let overlapping = PixelRect {
+            x: 15,
+            y: 25,
+            w: 10,
+            h: 5,
+        };
+
+        assert!(rect.contains(&rect));
+        assert!(rect.contains(&inside));
+        assert!(!inside.contains(&rect));
+        assert!(!rect.contains(&overlapping));
+    }
+}
@@ -0,0 +1,59 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Client request IDs, for correlating requests with the logs of the API.
+
+use reqwest::header::HeaderMap;
+
+/// Name of the header carrying the client request ID.
+pub(crate) const HEADER: &str = "x-ms-client-request-id";
+
+/// Returns the generator of the client request IDs of a new client: random
+/// UUIDs (v4) with the `uuid` feature, or else none.
+pub(crate) fn default_generator() -> Option<fn() -> String> {
+    #[cfg(feature = "uuid")]
+    return Some(|| uuid::Uuid::new_v4().to_string());
+
+    #[cfg(not(feature = "uuid"))]
+    None
+}
+
+/// Returns the client request ID echoed by the API in `headers`, or else the
+/// `sent` one, if any.
+pub(crate) fn echoed(
+    headers: &HeaderMap,
+    sent: Option<&str>,
+) -> Option<String> {
+    headers
+        .get(HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or(sent)
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_echoed() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(echoed(&headers, None), None);
+        assert_eq!(echoed(&headers, Some("sent")).as_deref(), Some("sent"));
+
+        headers.insert(HEADER, HeaderValue::from_static("echoed"));
+        assert_eq!(echoed(&headers, Some("sent")).as_deref(), Some("echoed"));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_default_generator() {
+        let generate = default_generator().unwrap();
+
+        assert!(uuid::Uuid::parse_str(&generate()).is_ok());
+        assert_ne!(generate(), generate());
+    }
+}
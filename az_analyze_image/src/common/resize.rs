@@ -0,0 +1,155 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Downscaling of images that exceed the maximum input size of the API.
+
+use super::MIN_DIMENSION;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageReader};
+use std::borrow::Cow;
+use std::io::Cursor;
+
+/// JPEG quality used when re-encoding.
+const JPEG_QUALITY: u8 = 85;
+
+/// Returns the `(width, height)` of the image, reading only as much of `data`
+/// as needed.
+pub(crate) fn dimensions(data: &[u8]) -> Result<(u32, u32), image::ImageError> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()?
+        .into_dimensions()
+}
+
+/// Returns `data` unchanged if it is at most `max_size` bytes. Otherwise,
+/// re-encodes the image as JPEG, downscaling it while preserving its aspect
+/// ratio, until it fits.
+///
+/// Returns `Ok(None)` if the image cannot fit without its width or height
+/// dropping to 50 pixels or below.
+pub(crate) fn fit_to_size(
+    data: &[u8],
+    max_size: usize,
+) -> Result<Option<Cow<'_, [u8]>>, image::ImageError> {
+    if data.len() <= max_size {
+        return Ok(Some(Cow::Borrowed(data)));
+    }
+
+    let original = image::load_from_memory(data)?;
+    let (original_width, original_height) = original.dimensions();
+
+    let mut scale = 1.0;
+    loop {
+        let width = (original_width as f64 * scale) as u32;
+        let height = (original_height as f64 * scale) as u32;
+
+        if width <= MIN_DIMENSION || height <= MIN_DIMENSION {
+            return Ok(None);
+        }
+
+        let encoded = encode_jpeg(&original.resize_exact(
+            width,
+            height,
+            FilterType::Triangle,
+        ))?;
+
+        if encoded.len() <= max_size {
+            return Ok(Some(Cow::Owned(encoded)));
+        }
+
+        // The encoded size is roughly proportional to the pixel count.
+        let ratio = (max_size as f64 / encoded.len() as f64).sqrt();
+        scale *= (ratio * 0.9).min(0.9);
+    }
+}
+
+/// Like [`fit_to_size`], but decodes, downscales and re-encodes an image that
+/// does not fit on the blocking thread pool, so as not to stall the executor.
+pub(crate) async fn fit_to_size_blocking(
+    data: &[u8],
+    max_size: usize,
+) -> Result<Option<Cow<'_, [u8]>>, image::ImageError> {
+    if data.len() <= max_size {
+        return Ok(Some(Cow::Borrowed(data)));
+    }
+
+    let data = data.to_vec();
+    let fitted = super::blocking::run(move || {
+        fit_to_size(&data, max_size)
+            .map(|fitted| fitted.map(|fitted| fitted.into_owned()))
+    })
+    .await?;
+
+    Ok(fitted.map(Cow::Owned))
+}
+
+fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let mut encoded = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY);
+
+    // JPEG has no alpha channel.
+    DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::images::noise_png;
+
+    #[test]
+    fn test_fit_to_size_small_image_unchanged() {
+        let png = noise_png(60, 60);
+
+        let fitted = fit_to_size(&png, png.len()).unwrap().unwrap();
+
+        assert!(matches!(fitted, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_fit_to_size_oversized_image() {
+        let png = noise_png(400, 200);
+        let max_size = png.len() / 8;
+
+        let fitted = fit_to_size(&png, max_size).unwrap().unwrap();
+        assert!(fitted.len() <= max_size);
+
+        let (width, height) =
+            image::load_from_memory(&fitted).unwrap().dimensions();
+        assert!(width > MIN_DIMENSION && height > MIN_DIMENSION);
+        assert!(width < 400);
+        assert!((width as f64 / height as f64 - 2.0).abs() < 0.05);
+    }
+
+    #[tokio::test]
+    async fn test_fit_to_size_blocking() {
+        let png = noise_png(400, 200);
+
+        let unchanged = fit_to_size_blocking(&png, png.len()).await.unwrap();
+        assert!(matches!(unchanged, Some(Cow::Borrowed(_))));
+
+        let fitted = fit_to_size_blocking(&png, png.len() / 8).await.unwrap();
+        assert!(fitted.unwrap().len() <= png.len() / 8);
+    }
+
+    #[test]
+    fn test_fit_to_size_cannot_fit() {
+        let png = noise_png(200, 200);
+
+        assert!(fit_to_size(&png, 100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_dimensions() {
+        assert_eq!(dimensions(&noise_png(64, 51)).unwrap(), (64, 51));
+        assert!(dimensions(&[0; 16]).is_err());
+    }
+
+    #[test]
+    fn test_fit_to_size_invalid_image() {
+        assert!(fit_to_size(&[0; 16], 8).is_err());
+    }
+}
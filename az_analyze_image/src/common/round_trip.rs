@@ -0,0 +1,135 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Serde round-trip checks of the API types against the committed samples.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Asserts that deserializing `json` as `T` and serializing it back is
+/// lossless for the modeled fields.
+///
+/// Every field of the re-serialized value must be present in `json` with an
+/// equal value. `null`s (unset `Option`s) are ignored, and numbers are compared
+/// as `f64`, so that e.g. `1` and `1.0` are equal. Fields of `json` that are
+/// not modeled by `T` are not checked.
+pub fn assert_round_trip<T: DeserializeOwned + Serialize>(json: &str) {
+    let original: Value = serde_json::from_str(json).unwrap();
+    let value: T = serde_json::from_str(json).unwrap();
+    let round_tripped = serde_json::to_value(&value).unwrap();
+
+    assert_subset(&round_tripped, &original, "$");
+}
+
+/// Returns the committed samples of successful responses of API `version`,
+/// e.g. `"v40"`.
+pub fn samples(version: &str) -> Vec<String> {
+    let dir =
+        format!("{}/tests/samples/{}", env!("CARGO_MANIFEST_DIR"), version);
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            !path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("error_")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect()
+}
+
+/// Asserts that `simd-json` deserializes `json` as `T` like `serde_json` does.
+///
+/// Numbers may differ in their last bit, since `serde_json` does not always
+/// round floats correctly by default, while `simd-json` does.
+#[cfg(feature = "simd-json")]
+pub fn assert_simd_json_identical<T: DeserializeOwned + Serialize>(json: &str) {
+    let expected: T = serde_json::from_str(json).unwrap();
+    let value: T =
+        simd_json::serde::from_slice(&mut json.as_bytes().to_vec()).unwrap();
+
+    assert_equivalent(
+        &serde_json::to_value(&value).unwrap(),
+        &serde_json::to_value(&expected).unwrap(),
+        "$",
+    );
+}
+
+#[cfg(feature = "simd-json")]
+fn assert_equivalent(value: &Value, expected: &Value, path: &str) {
+    match (value, expected) {
+        (Value::Object(fields), Value::Object(expected_fields)) => {
+            assert_eq!(fields.len(), expected_fields.len(), "{}", path);
+            for (key, field) in fields {
+                let path = format!("{}.{}", path, key);
+                let expected_field = expected_fields
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} is not expected", path));
+                assert_equivalent(field, expected_field, &path);
+            }
+        }
+        (Value::Array(items), Value::Array(expected_items)) => {
+            assert_eq!(items.len(), expected_items.len(), "{}", path);
+            for (i, (item, expected_item)) in
+                items.iter().zip(expected_items).enumerate()
+            {
+                assert_equivalent(
+                    item,
+                    expected_item,
+                    &format!("{}[{}]", path, i),
+                );
+            }
+        }
+        (Value::Number(n), Value::Number(expected_n)) => {
+            let (n, expected_n) =
+                (n.as_f64().unwrap(), expected_n.as_f64().unwrap());
+            assert!(
+                (n - expected_n).abs() <= expected_n.abs() * f64::EPSILON,
+                "{}: {} != {}",
+                path,
+                n,
+                expected_n
+            );
+        }
+        _ => assert_eq!(value, expected, "{}", path),
+    }
+}
+
+fn assert_subset(value: &Value, original: &Value, path: &str) {
+    match (value, original) {
+        (Value::Object(fields), Value::Object(original_fields)) => {
+            for (key, field) in fields {
+                if field.is_null() {
+                    continue;
+                }
+                let path = format!("{}.{}", path, key);
+                let original_field = original_fields
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} is not in the input", path));
+                assert_subset(field, original_field, &path);
+            }
+        }
+        (Value::Array(items), Value::Array(original_items)) => {
+            assert_eq!(items.len(), original_items.len(), "{}", path);
+            for (i, (item, original_item)) in
+                items.iter().zip(original_items).enumerate()
+            {
+                assert_subset(item, original_item, &format!("{}[{}]", path, i));
+            }
+        }
+        (Value::Number(n), Value::Number(original_n)) => {
+            assert_eq!(n.as_f64(), original_n.as_f64(), "{}", path);
+        }
+        _ => assert_eq!(value, original, "{}", path),
+    }
+}
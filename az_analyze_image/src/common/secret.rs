@@ -30,10 +30,21 @@ impl Secret {
         Self { value }
     }
 
+    /// Returns the secret value.
+    ///
+    /// Prefer [`Secret::expose_for`], which makes it harder to retain copies
+    /// of the value that are not zeroized.
+    #[deprecated(note = "use `Secret::expose_for` instead")]
     #[allow(dead_code)]
     pub fn value(&self) -> &str {
         &self.value
     }
+
+    /// Calls `f` with the secret value and returns its result, exposing the
+    /// value only for the duration of the call.
+    pub fn expose_for<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+        f(&self.value)
+    }
 }
 
 impl fmt::Debug for Secret {
@@ -72,11 +83,24 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_secret_get_value() {
         let secret = Secret::new(TEST_VALUE.to_string());
         assert_eq!(secret.value(), TEST_VALUE);
     }
 
+    #[test]
+    fn test_secret_expose_for() {
+        let secret = Secret::new(TEST_VALUE.to_string());
+
+        let len = secret.expose_for(|value| {
+            assert_eq!(value, TEST_VALUE);
+            value.len()
+        });
+
+        assert_eq!(len, TEST_VALUE.len());
+    }
+
     #[test]
     fn test_secret_debug() {
         let secret = Secret::new(TEST_VALUE.to_string());
@@ -0,0 +1,101 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Deserialization of success responses that may carry an error body.
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// The body of a response with a success status: normally the expected result
+/// `T`, but occasionally, for some partial failures, an error response `E`,
+/// identified by an `error` key anywhere in the body.
+///
+/// The body is buffered as a JSON object, so the `error` key is found
+/// regardless of its position, before it is deserialized as `T` or `E`.
+#[derive(Debug)]
+pub(crate) enum SuccessBody<T, E> {
+    Result(T),
+    Error(E),
+}
+
+impl<'de, T, E> Deserialize<'de> for SuccessBody<T, E>
+where
+    T: DeserializeOwned,
+    E: DeserializeOwned,
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let body = Map::<String, Value>::deserialize(deserializer)?;
+        let is_error = body.contains_key("error");
+        let body = Value::Object(body);
+
+        if is_error {
+            E::deserialize(body).map(SuccessBody::Error)
+        } else {
+            T::deserialize(body).map(SuccessBody::Result)
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Analysis {
+        a: u32,
+        b: Vec<u32>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct ErrorBody {
+        error: String,
+    }
+
+    type Body = SuccessBody<Analysis, ErrorBody>;
+
+    #[test]
+    fn test_success_body_result() {
+        let body: Body = serde_json::from_str(r#"{"a": 1, "b": [2]}"#).unwrap();
+
+        assert!(matches!(
+            body,
+            SuccessBody::Result(Analysis { a: 1, ref b }) if b == &[2]
+        ));
+    }
+
+    #[test]
+    fn test_success_body_error() {
+        let body: Body = serde_json::from_str(r#"{"error": "oops"}"#).unwrap();
+
+        assert!(matches!(
+            body,
+            SuccessBody::Error(ErrorBody { ref error }) if error == "oops"
+        ));
+    }
+
+    #[test]
+    fn test_success_body_error_after_other_keys() {
+        let body: Body =
+            serde_json::from_str(r#"{"a": 1, "error": "oops"}"#).unwrap();
+
+        assert!(matches!(
+            body,
+            SuccessBody::Error(ErrorBody { ref error }) if error == "oops"
+        ));
+    }
+
+    #[test]
+    fn test_success_body_invalid_result() {
+        let err = serde_json::from_str::<Body>(r#"{"a": 1}"#).unwrap_err();
+
+        assert!(err.to_string().contains("missing field `b`"));
+        assert!(serde_json::from_str::<Body>("{}").is_err());
+        assert!(serde_json::from_str::<Body>("[]").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+use super::confidence::cmp_confidence;
+use super::Number;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A tag in a form common to both API versions, for code that consumes tags
+/// regardless of the version that produced them.
+///
+/// Converts from v3.2 [`ImageTag`](crate::v32::ImageTag)s and v4.0
+/// [`Tag`](crate::v40::Tag)s with [`From`]. Only v3.2 tags carry a `hint`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CommonTag {
+    /// Name of the entity.
+    pub name: String,
+
+    /// The level of confidence that the entity was observed.
+    pub confidence: Number,
+
+    /// Optional hint/details for this tag.
+    pub hint: Option<String>,
+}
+
+/// How confidences of the same tag are combined across multiple images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagAggregation {
+    /// The highest confidence the tag received in any image.
+    #[default]
+    Max,
+
+    /// The mean confidence over all images, where images without the tag
+    /// contribute a confidence of 0.
+    Mean,
+}
+
+/// Combines `(name, confidence)` tag sets, one per image, by tag name.
+///
+/// The result is sorted by combined confidence (descending), then by name.
+pub(crate) fn aggregate<'a, I, T>(
+    tag_sets: I,
+    aggregation: TagAggregation,
+) -> Vec<(String, Number)>
+where
+    I: IntoIterator<Item = T>,
+    T: IntoIterator<Item = (&'a str, Number)>,
+{
+    let mut combined: HashMap<&str, Number> = HashMap::new();
+    let mut count = 0;
+
+    for tags in tag_sets {
+        count += 1;
+
+        // A tag reported more than once for the same image counts once.
+        let mut per_image: HashMap<&str, Number> = HashMap::new();
+        for (name, confidence) in tags {
+            let entry = per_image.entry(name).or_insert(confidence);
+            *entry = entry.max(confidence);
+        }
+
+        for (name, confidence) in per_image {
+            let entry = combined.entry(name).or_insert(0.0);
+            *entry = match aggregation {
+                TagAggregation::Max => entry.max(confidence),
+                TagAggregation::Mean => *entry + confidence,
+            };
+        }
+    }
+
+    let mut aggregated: Vec<(String, Number)> = combined
+        .into_iter()
+        .map(|(name, confidence)| {
+            let confidence = match aggregation {
+                TagAggregation::Max => confidence,
+                TagAggregation::Mean => confidence / count as Number,
+            };
+            (name.to_string(), confidence)
+        })
+        .collect();
+
+    aggregated
+        .sort_by(|a, b| cmp_confidence(&b.1, &a.1).then_with(|| a.0.cmp(&b.0)));
+
+    aggregated
+}
+
+/// Counts `confidences` per bucket of width `bucket_size`, keyed by bucket
+/// index, i.e. `confidence / bucket_size` floored.
+///
+/// A confidence of exactly 1 is counted in the last bucket below 1 rather than
+/// in a bucket of its own. Returns `None` if `bucket_size` is not positive.
+pub(crate) fn confidence_buckets(
+    confidences: impl IntoIterator<Item = Number>,
+    bucket_size: Number,
+) -> Option<BTreeMap<u32, usize>> {
+    if bucket_size.is_nan() || bucket_size <= 0.0 {
+        return None;
+    }
+
+    // Multiplying by the reciprocal avoids e.g. 0.3 / 0.1 flooring to 2.
+    let buckets_per_unit = 1.0 / bucket_size;
+    let last_bucket = (buckets_per_unit.ceil() as u32).saturating_sub(1);
+
+    let mut buckets = BTreeMap::new();
+    for confidence in confidences {
+        let bucket = ((confidence * buckets_per_unit).floor().max(0.0) as u32)
+            .min(last_bucket);
+        *buckets.entry(bucket).or_insert(0) += 1;
+    }
+
+    Some(buckets)
+}
@@ -4,8 +4,12 @@ SPDX-License-Identifier: Apache-2.0 OR MIT
 */
 
 #![doc = include_str!("../README.md")]
+#![allow(clippy::doc_overindented_list_items)]
+#![allow(clippy::result_large_err)]
 
 pub mod v32;
 pub mod v40;
 
 mod common;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
@@ -0,0 +1,226 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! A minimal HTTP server that answers requests with canned responses and
+//! records them, e.g. for a client created with `Client::with_http_client`.
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Extra `(name, value)` headers to include in a response.
+type Headers = &'static [(&'static str, &'static str)];
+
+/// The request received by the mock server.
+#[derive(Debug)]
+pub struct RecordedRequest {
+    /// Request line and headers.
+    pub head: String,
+
+    /// Request body.
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// Returns the value of the header with the given (case-insensitive) name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then_some(value.trim())
+        })
+    }
+}
+
+/// Starts a server that answers exactly one request with `status` and the JSON
+/// `body`.
+///
+/// Returns an endpoint suitable for `Client::new` and a handle resolving to the
+/// recorded request.
+pub async fn serve_once(
+    status: u16,
+    body: &'static str,
+) -> (String, JoinHandle<RecordedRequest>) {
+    let (endpoint, handle) = serve(vec![(status, body)]).await;
+
+    let handle =
+        tokio::spawn(async move { handle.await.unwrap().pop().unwrap() });
+
+    (endpoint, handle)
+}
+
+/// Starts a server that answers exactly one request with `status`, the extra
+/// response `headers` and the JSON `body`.
+///
+/// Returns an endpoint suitable for `Client::new` and a handle resolving to the
+/// recorded request.
+pub async fn serve_once_with_headers(
+    status: u16,
+    headers: Headers,
+    body: &'static str,
+) -> (String, JoinHandle<RecordedRequest>) {
+    serve_once_bytes(status, headers, body.as_bytes().to_vec()).await
+}
+
+/// Starts a server that answers exactly one request with `status`, the extra
+/// response `headers` and the raw `body`, e.g. a gzip-compressed one.
+///
+/// Returns an endpoint suitable for `Client::new` and a handle resolving to the
+/// recorded request.
+pub async fn serve_once_bytes(
+    status: u16,
+    headers: Headers,
+    body: Vec<u8>,
+) -> (String, JoinHandle<RecordedRequest>) {
+    let (endpoint, handle) =
+        serve_responses(vec![(status, headers, body)]).await;
+
+    let handle =
+        tokio::spawn(async move { handle.await.unwrap().pop().unwrap() });
+
+    (endpoint, handle)
+}
+
+/// Starts a server that answers exactly one request with `status` and the JSON
+/// `body`, but only after `delay`, e.g. to measure latency.
+///
+/// Returns an endpoint suitable for `Client::new` and a handle resolving to the
+/// recorded request.
+pub async fn serve_once_delayed(
+    status: u16,
+    body: &'static str,
+    delay: std::time::Duration,
+) -> (String, JoinHandle<RecordedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(delay).await;
+        respond(stream, status, &[], body.as_bytes()).await
+    });
+
+    (endpoint, handle)
+}
+
+/// Starts a server that closes the first connection as soon as it has read the
+/// request head, without responding, then answers the next request with
+/// `status` and the JSON `body`, e.g. to simulate a connection reset.
+///
+/// Returns an endpoint suitable for `Client::new` and a handle resolving to the
+/// recorded request.
+#[cfg(feature = "retry")]
+pub async fn serve_once_after_close(
+    status: u16,
+    body: &'static str,
+) -> (String, JoinHandle<RecordedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut chunk = [0u8; 4096];
+        let _ = stream.read(&mut chunk).await;
+        drop(stream);
+
+        let (stream, _) = listener.accept().await.unwrap();
+        respond(stream, status, &[], body.as_bytes()).await
+    });
+
+    (endpoint, handle)
+}
+
+/// Starts a server that answers one request per `(status, body)` pair in
+/// `responses`, in order.
+///
+/// Returns an endpoint suitable for `Client::new` and a handle resolving to the
+/// recorded requests.
+pub async fn serve(
+    responses: Vec<(u16, &'static str)>,
+) -> (String, JoinHandle<Vec<RecordedRequest>>) {
+    serve_responses(
+        responses
+            .into_iter()
+            .map(|(status, body)| (status, &[][..], body.as_bytes().to_vec()))
+            .collect(),
+    )
+    .await
+}
+
+async fn serve_responses(
+    responses: Vec<(u16, Headers, Vec<u8>)>,
+) -> (String, JoinHandle<Vec<RecordedRequest>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::with_capacity(responses.len());
+        for (status, headers, body) in responses {
+            let (stream, _) = listener.accept().await.unwrap();
+            requests.push(respond(stream, status, headers, &body).await);
+        }
+        requests
+    });
+
+    (endpoint, handle)
+}
+
+async fn respond(
+    mut stream: TcpStream,
+    status: u16,
+    headers: Headers,
+    body: &[u8],
+) -> RecordedRequest {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let head_end = loop {
+        let n = stream.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        assert!(n != 0, "connection closed before end of headers");
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut request = RecordedRequest {
+        head,
+        body: Vec::new(),
+    };
+
+    let content_length: usize = request
+        .header("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+
+    while buf.len() < head_end + content_length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        assert!(n != 0, "connection closed before end of body");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    request.body = buf[head_end..head_end + content_length].to_vec();
+
+    let extra_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    let response_head = format!(
+        "HTTP/1.1 {} Mock\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         {}\
+         Connection: close\r\n\r\n",
+        status,
+        body.len(),
+        extra_headers,
+    );
+    stream.write_all(response_head.as_bytes()).await.unwrap();
+    stream.write_all(body).await.unwrap();
+    stream.shutdown().await.unwrap();
+
+    request
+}
@@ -0,0 +1,10 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Helpers for testing code that uses the clients without reaching Azure.
+//!
+//! Requires the `test-util` feature.
+
+pub mod mock;
@@ -0,0 +1,63 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! The [`ImageAnalyzer`] trait of the v3.2 [`Client`].
+
+use super::client::{AnalyzeImageOptions, Client, Result};
+use super::ImageAnalysis;
+
+crate::common::analyzer::image_analyzer!(
+    ImageAnalysis,
+    r#"
+```
+use az_analyze_image::v32::analyzer::ImageAnalyzer;
+use az_analyze_image::v32::client::{AnalyzeImageOptions, Result};
+use az_analyze_image::v32::VisualFeatureTypes;
+
+async fn caption(
+    analyzer: &impl ImageAnalyzer,
+    image_url: &str,
+) -> Result<Option<String>> {
+    let features = [VisualFeatureTypes::Description];
+    let options = AnalyzeImageOptions {
+        visual_features: Some(&features),
+        ..Default::default()
+    };
+
+    let analysis = analyzer.analyze_image_url(image_url, options).await?;
+    Ok(analysis.description.and_then(|description| {
+        description.best_caption().map(|caption| caption.text.clone())
+    }))
+}
+```
+"#
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mock;
+    use crate::v32::testing::{mock_client, options, TAGS};
+    use crate::v32::VisualFeatureTypes;
+
+    async fn tag_count(analyzer: &impl ImageAnalyzer) -> Result<usize> {
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let analysis = analyzer
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await?;
+        Ok(analysis.require_tags()?.len())
+    }
+
+    #[tokio::test]
+    async fn test_client_analyzer() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+
+        let count = tag_count(&mock_client(&endpoint)).await;
+        server.await.unwrap();
+
+        assert!(count.unwrap() > 0);
+    }
+}
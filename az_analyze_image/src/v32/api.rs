@@ -3,8 +3,15 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0 OR MIT
 */
 
-use super::{Number, PixelCount};
+use super::client::{Error, Result};
+use super::confidence::cmp_confidence;
+use super::{
+    coco, geometry, tags, ApiError, CommonTag, Number, ParseFeatureError,
+    PixelCount, PixelRect, TagAggregation,
+};
+use crate::common::error::is_retryable_code;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// An object describing whether the image contains adult-oriented content
 /// and/or is racy.
@@ -52,6 +59,37 @@ pub struct BoundingRect {
     pub y: PixelCount,
 }
 
+impl From<&BoundingRect> for PixelRect {
+    fn from(rect: &BoundingRect) -> Self {
+        PixelRect {
+            x: rect.x,
+            y: rect.y,
+            w: rect.w,
+            h: rect.h,
+        }
+    }
+}
+
+impl BoundingRect {
+    /// Returns the part of the rectangle within an image of the given size,
+    /// e.g. the size in [`ImageAnalysis::metadata`], as the API occasionally
+    /// returns rectangles extending slightly beyond the image.
+    pub fn clamp_to(
+        &self,
+        width: PixelCount,
+        height: PixelCount,
+    ) -> BoundingRect {
+        let rect = PixelRect::from(self).clamp_to(width, height);
+
+        BoundingRect {
+            h: rect.h,
+            w: rect.w,
+            x: rect.x,
+            y: rect.y,
+        }
+    }
+}
+
 /// An object describing identified category.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Category {
@@ -65,6 +103,40 @@ pub struct Category {
     pub score: Number,
 }
 
+impl Category {
+    /// Returns the top-level category of the taxonomy that the category
+    /// belongs to, i.e. the part of the name before the first `_`, e.g.
+    /// `"people"` for `"people_portrait"` and `"others"` for `"others_"`.
+    pub fn top_level(&self) -> &str {
+        self.name
+            .split_once('_')
+            .map_or(self.name.as_str(), |(top_level, _)| top_level)
+    }
+
+    /// Returns `true` if the category is a subcategory, e.g.
+    /// `"people_portrait"`, rather than a top-level category, whose name has
+    /// a trailing `_`, e.g. `"others_"`.
+    pub fn is_leaf(&self) -> bool {
+        self.name
+            .split_once('_')
+            .is_some_and(|(_, child)| !child.is_empty())
+    }
+
+    /// Returns the numerator `n` of the score if it is exactly `n / 256`,
+    /// the quantization of the scores returned by the service, e.g. `235` for
+    /// `0.91796875`.
+    ///
+    /// Returns `None` if the score is not a multiple of `1 / 256` or is
+    /// outside `0..=255 / 256`, e.g. for `1.0`.
+    pub fn score_256(&self) -> Option<u8> {
+        // Scaling by a power of two is exact.
+        let scaled = self.score * 256.0;
+
+        (scaled.fract() == 0.0 && (0.0..=255.0).contains(&scaled))
+            .then_some(scaled as u8)
+    }
+}
+
 /// An object describing additional category details.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CategoryDetail {
@@ -115,6 +187,32 @@ pub struct ColorInfo {
     pub is_bw_img: bool,
 }
 
+impl ColorInfo {
+    /// Returns [`dominant_color_background`] as a [`DominantColor`].
+    ///
+    /// [`dominant_color_background`]: Self::dominant_color_background
+    pub fn dominant_color_background_typed(&self) -> DominantColor {
+        DominantColor::from(self.dominant_color_background.as_str())
+    }
+
+    /// Returns [`dominant_color_foreground`] as a [`DominantColor`].
+    ///
+    /// [`dominant_color_foreground`]: Self::dominant_color_foreground
+    pub fn dominant_color_foreground_typed(&self) -> DominantColor {
+        DominantColor::from(self.dominant_color_foreground.as_str())
+    }
+
+    /// Returns [`dominant_colors`] as [`DominantColor`]s.
+    ///
+    /// [`dominant_colors`]: Self::dominant_colors
+    pub fn dominant_colors_typed(&self) -> Vec<DominantColor> {
+        self.dominant_colors
+            .iter()
+            .map(|color| DominantColor::from(color.as_str()))
+            .collect()
+    }
+}
+
 /// The API request error.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ComputerVisionError {
@@ -130,6 +228,7 @@ pub struct ComputerVisionError {
 
 /// The error code.
 #[derive(Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub enum ComputerVisionErrorCodes {
     InternalServerError,
     InvalidArgument,
@@ -150,12 +249,58 @@ impl std::fmt::Display for ComputerVisionErrorCodes {
     }
 }
 
+impl ComputerVisionErrorCodes {
+    /// Returns the error code as returned by the API, e.g. `"InvalidRequest"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InternalServerError => "InternalServerError",
+            Self::InvalidArgument => "InvalidArgument",
+            Self::InvalidRequest => "InvalidRequest",
+            Self::ServiceUnavailable => "ServiceUnavailable",
+        }
+    }
+}
+
 /// The API error response.
-#[derive(Debug, Deserialize, Serialize, thiserror::Error)]
-#[error("{:?}", error)] // TODO.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ComputerVisionErrorResponse {
     /// Error contents.
     pub error: ComputerVisionError,
+
+    /// The client request ID of the failed request, as echoed by the API or
+    /// else as sent, if any. Not part of the body.
+    #[serde(skip)]
+    pub client_request_id: Option<String>,
+}
+
+impl std::fmt::Display for ComputerVisionErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.error.code.as_str(), self.error.message)
+    }
+}
+
+/// The [`source`](std::error::Error::source) of a
+/// `ComputerVisionErrorResponse` is its inner error, so that walking the error
+/// chain reveals the more specific code and message.
+impl std::error::Error for ComputerVisionErrorResponse {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error.innererror)
+    }
+}
+
+impl ApiError for ComputerVisionErrorResponse {
+    fn is_retryable(&self) -> bool {
+        is_retryable_code(self.error.code.as_str())
+            || is_retryable_code(self.error.innererror.code.as_str())
+    }
+
+    fn code(&self) -> &str {
+        self.error.code.as_str()
+    }
+
+    fn message(&self) -> &str {
+        &self.error.message
+    }
 }
 
 /// Details about the API request error.
@@ -168,8 +313,17 @@ pub struct ComputerVisionInnerError {
     pub message: String,
 }
 
+impl std::fmt::Display for ComputerVisionInnerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ComputerVisionInnerError {}
+
 /// The error code.
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[non_exhaustive]
 pub enum ComputerVisionInnerErrorCodeValue {
     BadArgument,
     CancelledRequest,
@@ -194,8 +348,36 @@ pub enum ComputerVisionInnerErrorCodeValue {
     UnsupportedMediaType,
 }
 
+impl ComputerVisionInnerErrorCodeValue {
+    /// Returns the error code as returned by the API, e.g. `"Timeout"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BadArgument => "BadArgument",
+            Self::CancelledRequest => "CancelledRequest",
+            Self::DetectFaceError => "DetectFaceError",
+            Self::FailedToProcess => "FailedToProcess",
+            Self::InternalServerError => "InternalServerError",
+            Self::InvalidDetails => "InvalidDetails",
+            Self::InvalidImageFormat => "InvalidImageFormat",
+            Self::InvalidImageSize => "InvalidImageSize",
+            Self::InvalidImageUrl => "InvalidImageUrl",
+            Self::InvalidModel => "InvalidModel",
+            Self::InvalidThumbnailSize => "InvalidThumbnailSize",
+            Self::NotSupportedFeature => "UnsupportedFeature",
+            Self::NotSupportedImage => "NotSupportedImage",
+            Self::NotSupportedLanguage => "NotSupportedLanguage",
+            Self::NotSupportedVisualFeature => "NotSupportedVisualFeature",
+            Self::StorageException => "StorageException",
+            Self::Timeout => "Timeout",
+            Self::Unspecified => "Unspecified",
+            Self::UnsupportedMediaType => "UnsupportedMediaType",
+        }
+    }
+}
+
 /// Turn off specified domain models when generating the description.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[non_exhaustive]
 pub enum DescriptionExclude {
     Celebrities,
     Landmarks,
@@ -210,11 +392,75 @@ pub enum DescriptionExclude {
 /// - `Celebrities`: identifies celebrities if detected in the image.
 /// - `Landmarks`: identifies notable landmarks in the image.
 #[derive(Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub enum Details {
     Celebrities,
     Landmarks,
 }
 
+/// A dominant color name, from the fixed palette of 12 colors used by the API.
+///
+/// Names outside the palette are preserved in [`DominantColor::Other`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DominantColor {
+    Black,
+    Blue,
+    Brown,
+    Green,
+    Grey,
+    Orange,
+    Pink,
+    Purple,
+    Red,
+    Teal,
+    White,
+    Yellow,
+    Other(String),
+}
+
+impl From<&str> for DominantColor {
+    fn from(name: &str) -> Self {
+        match name {
+            "Black" => Self::Black,
+            "Blue" => Self::Blue,
+            "Brown" => Self::Brown,
+            "Green" => Self::Green,
+            "Grey" | "Gray" => Self::Grey,
+            "Orange" => Self::Orange,
+            "Pink" => Self::Pink,
+            "Purple" => Self::Purple,
+            "Red" => Self::Red,
+            "Teal" => Self::Teal,
+            "White" => Self::White,
+            "Yellow" => Self::Yellow,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for DominantColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Black => "Black",
+            Self::Blue => "Blue",
+            Self::Brown => "Brown",
+            Self::Green => "Green",
+            Self::Grey => "Grey",
+            Self::Orange => "Orange",
+            Self::Pink => "Pink",
+            Self::Purple => "Purple",
+            Self::Red => "Red",
+            Self::Teal => "Teal",
+            Self::White => "White",
+            Self::Yellow => "Yellow",
+            Self::Other(name) => name,
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
 /// A brand detected in an image.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DetectedBrand {
@@ -278,8 +524,55 @@ pub struct FaceRectangle {
     pub width: PixelCount,
 }
 
+impl From<&FaceRectangle> for PixelRect {
+    fn from(rect: &FaceRectangle) -> Self {
+        PixelRect {
+            x: rect.left,
+            y: rect.top,
+            w: rect.width,
+            h: rect.height,
+        }
+    }
+}
+
+impl FaceRectangle {
+    /// Returns the part of the face rectangle within an image of the given
+    /// size, e.g. the size in [`ImageAnalysis::metadata`].
+    pub fn clamp_to(
+        &self,
+        width: PixelCount,
+        height: PixelCount,
+    ) -> FaceRectangle {
+        let rect = PixelRect::from(self).clamp_to(width, height);
+
+        FaceRectangle {
+            height: rect.h,
+            left: rect.x,
+            top: rect.y,
+            width: rect.w,
+        }
+    }
+
+    /// Returns the `(x, y)` center point of the face, in pixels.
+    ///
+    /// Half the width and height are rounded down.
+    pub fn center(&self) -> (PixelCount, PixelCount) {
+        (self.left + self.width / 2, self.top + self.height / 2)
+    }
+
+    /// Returns the radius of a circle centered at [`FaceRectangle::center`]
+    /// spanning the larger dimension of the face, in pixels.
+    ///
+    /// Half the larger dimension is rounded up, so that the circle is never
+    /// narrower than the face.
+    pub fn radius(&self) -> PixelCount {
+        self.width.max(self.height).div_ceil(2)
+    }
+}
+
 /// Possible gender of the face.
 #[derive(Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub enum Gender {
     Female,
     Male,
@@ -330,6 +623,148 @@ pub struct ImageAnalysis {
     pub tags: Option<Vec<ImageTag>>,
 }
 
+impl ImageAnalysis {
+    /// Serializes the result to pretty-printed JSON, with the field names of
+    /// the API, e.g. for logging or caching.
+    ///
+    /// The JSON can be deserialized back into an `ImageAnalysis`.
+    pub fn to_pretty_json(
+        &self,
+    ) -> std::result::Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Returns the `adult` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_adult(&self) -> Result<&AdultInfo> {
+        self.adult
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Adult))
+    }
+
+    /// Returns the `brands` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_brands(&self) -> Result<&[DetectedBrand]> {
+        self.brands
+            .as_deref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Brands))
+    }
+
+    /// Returns the `categories` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_categories(&self) -> Result<&[Category]> {
+        self.categories
+            .as_deref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Categories))
+    }
+
+    /// Returns the `color` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_color(&self) -> Result<&ColorInfo> {
+        self.color
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Color))
+    }
+
+    /// Returns the `description` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_description(&self) -> Result<&ImageDescriptionDetails> {
+        self.description
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Description))
+    }
+
+    /// Returns the `faces` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_faces(&self) -> Result<&[FaceDescription]> {
+        self.faces
+            .as_deref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Faces))
+    }
+
+    /// Returns the `imageType` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_image_type(&self) -> Result<&ImageType> {
+        self.image_type
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::ImageType))
+    }
+
+    /// Returns the `objects` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_objects(&self) -> Result<&[DetectedObject]> {
+        self.objects
+            .as_deref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Objects))
+    }
+
+    /// Returns the `tags` result, or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_tags(&self) -> Result<&[ImageTag]> {
+        self.tags
+            .as_deref()
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Tags))
+    }
+
+    /// Returns `true` if any face was detected. `false` if the `Faces` feature
+    /// was not requested.
+    pub fn has_faces(&self) -> bool {
+        self.faces.as_ref().is_some_and(|faces| !faces.is_empty())
+    }
+
+    /// Returns whether the image is black and white, or `None` if the `Color`
+    /// feature was not requested.
+    pub fn is_black_and_white(&self) -> Option<bool> {
+        self.color.as_ref().map(|color| color.is_bw_img)
+    }
+
+    /// Returns the detected objects sorted top-to-bottom, left-to-right, or an
+    /// empty vector if the `Objects` feature was not requested.
+    ///
+    /// Objects whose top edges lie within `row_tolerance` pixels of each other
+    /// are treated as being on the same row and are ordered by their left
+    /// edge.
+    pub fn objects_in_reading_order(
+        &self,
+        row_tolerance: PixelCount,
+    ) -> Vec<&DetectedObject> {
+        geometry::reading_order(
+            self.objects.iter().flatten(),
+            row_tolerance,
+            |object| (object.rectangle.x, object.rectangle.y),
+        )
+    }
+
+    /// Returns the names of the tags with a confidence of at least `min`, in
+    /// the order returned by the API, or an empty vector if the `Tags` feature
+    /// was not requested.
+    ///
+    /// Unlike [`ImageDescriptionDetails::tags`], these tags have confidences.
+    pub fn high_confidence_tags(&self, min: Number) -> Vec<&str> {
+        self.tags
+            .iter()
+            .flatten()
+            .filter(|tag| tag.confidence >= min)
+            .map(|tag| tag.name.as_str())
+            .collect()
+    }
+
+    /// Counts the tags per confidence range of width `bucket_size`, keyed by
+    /// the index of the range, e.g. key `9` for 0.9 to 1.0 with a
+    /// `bucket_size` of `0.1`. Empty if the `Tags` feature was not requested.
+    ///
+    /// Returns `None` if `bucket_size` is not positive.
+    pub fn tag_confidence_buckets(
+        &self,
+        bucket_size: Number,
+    ) -> Option<BTreeMap<u32, usize>> {
+        tags::confidence_buckets(
+            self.tags.iter().flatten().map(|tag| tag.confidence),
+            bucket_size,
+        )
+    }
+}
+
 /// An image caption, i.e. a brief description of what the image depicts.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ImageCaption {
@@ -351,6 +786,15 @@ pub struct ImageDescriptionDetails {
     pub tags: Vec<String>,
 }
 
+impl ImageDescriptionDetails {
+    /// Returns the caption with the highest confidence, if any.
+    pub fn best_caption(&self) -> Option<&ImageCaption> {
+        self.captions
+            .iter()
+            .max_by(|a, b| cmp_confidence(&a.confidence, &b.confidence))
+    }
+}
+
 /// Image metadata.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ImageMetadata {
@@ -364,6 +808,24 @@ pub struct ImageMetadata {
     pub width: PixelCount,
 }
 
+impl ImageMetadata {
+    /// Returns the aspect ratio of the image, i.e. `width / height`, or `0.0`
+    /// if the height is 0.
+    pub fn aspect_ratio(&self) -> Number {
+        geometry::aspect_ratio(self.width, self.height)
+    }
+
+    /// Returns `true` if the image is taller than it is wide.
+    pub fn is_portrait(&self) -> bool {
+        self.height > self.width
+    }
+
+    /// Returns `true` if the image is wider than it is tall.
+    pub fn is_landscape(&self) -> bool {
+        self.width > self.height
+    }
+}
+
 /// An entity observation in the image, along with the confidence score.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ImageTag {
@@ -377,6 +839,108 @@ pub struct ImageTag {
     pub name: String,
 }
 
+impl From<&ImageTag> for CommonTag {
+    fn from(tag: &ImageTag) -> Self {
+        CommonTag {
+            name: tag.name.clone(),
+            confidence: tag.confidence,
+            hint: tag.hint.clone(),
+        }
+    }
+}
+
+/// Returns the intersection over union (IoU) of two rectangles, between 0
+/// (disjoint) and 1 (identical), e.g. for custom overlap filters.
+///
+/// Returns 0 if both rectangles have zero area.
+pub fn iou_rect(a: &BoundingRect, b: &BoundingRect) -> Number {
+    PixelRect::from(a).iou(&PixelRect::from(b))
+}
+
+/// Matches each face to the detected person containing it, e.g. to tell which
+/// person a face belongs to.
+///
+/// An object is a person if it or any of its parents is labeled `person`. A
+/// face is matched to the smallest person whose rectangle contains its
+/// rectangle entirely, or to `None` if there is no such person. Faces are
+/// returned in their original order.
+pub fn correlate_faces_objects<'a, 'b>(
+    faces: &'a [FaceDescription],
+    objects: &'b [DetectedObject],
+) -> Vec<(&'a FaceDescription, Option<&'b DetectedObject>)> {
+    let is_person = |object: &DetectedObject| {
+        object.object == "person"
+            || object.parent.as_ref().is_some_and(|parent| {
+                parent.iter_chain().any(|parent| parent.object == "person")
+            })
+    };
+    let people: Vec<(&DetectedObject, PixelRect)> = objects
+        .iter()
+        .filter(|object| is_person(object))
+        .map(|object| (object, PixelRect::from(&object.rectangle)))
+        .collect();
+
+    faces
+        .iter()
+        .map(|face| {
+            let face_rect = PixelRect::from(&face.face_rectangle);
+            let person = people
+                .iter()
+                .filter(|(_, rect)| rect.contains(&face_rect))
+                .min_by_key(|(_, rect)| rect.area())
+                .map(|(object, _)| *object);
+
+            (face, person)
+        })
+        .collect()
+}
+
+/// Converts detected objects and faces to a
+/// [COCO](https://cocodataset.org/#format-data) annotation document, e.g. to
+/// use them as training data.
+///
+/// The document lists the image `image_id` of size `image_size` (width,
+/// height) under `images`, each distinct object label, and `face` for faces,
+/// under `categories`, and an annotation per object, then per face, with its
+/// `bbox` as `[x, y, w, h]` in pixels. Object annotations have a `score`;
+/// face annotations do not, as the API returns no confidence for faces. IDs of
+/// categories and annotations start at 1.
+pub fn to_coco_annotations(
+    objects: &[DetectedObject],
+    faces: &[FaceDescription],
+    image_id: u64,
+    image_size: (PixelCount, PixelCount),
+) -> serde_json::Value {
+    let objects = objects.iter().map(|object| coco::Detection {
+        label: &object.object,
+        rect: PixelRect::from(&object.rectangle),
+        score: Some(object.confidence),
+    });
+    let faces = faces.iter().map(|face| coco::Detection {
+        label: "face",
+        rect: PixelRect::from(&face.face_rectangle),
+        score: None,
+    });
+
+    coco::annotations(objects.chain(faces), image_id, image_size)
+}
+
+/// Combines the tags of multiple images by tag name, e.g. to tag an album.
+///
+/// Returns `(name, confidence)` pairs sorted by combined confidence
+/// (descending), then by name.
+pub fn aggregate_tags(
+    results: &[&[ImageTag]],
+    aggregation: TagAggregation,
+) -> Vec<(String, Number)> {
+    tags::aggregate(
+        results.iter().map(|tags| {
+            tags.iter().map(|tag| (tag.name.as_str(), tag.confidence))
+        }),
+        aggregation,
+    )
+}
+
 /// An object providing possible image types and matching confidence levels.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ImageType {
@@ -398,6 +962,46 @@ pub struct ImageType {
     pub line_drawing_type: u8,
 }
 
+impl ImageType {
+    /// Returns `true` if the image is neither clip art nor a line drawing,
+    /// i.e. both levels are `0`.
+    pub fn is_likely_photo(&self) -> bool {
+        self.clipart_type == 0 && self.line_drawing_type == 0
+    }
+
+    /// Classifies the image by its clip art and line drawing levels.
+    ///
+    /// The image is [`ImageKind::ClipArt`] if it is normal or good clip art
+    /// (level `2` or `3`) and not a line drawing, and
+    /// [`ImageKind::LineDrawing`] if it is a line drawing and not clip art.
+    /// Ambiguous clip art (level `1`), or an image that is both, is
+    /// [`ImageKind::Ambiguous`].
+    pub fn classification(&self) -> ImageKind {
+        match (self.clipart_type, self.line_drawing_type) {
+            (0, 0) => ImageKind::Photo,
+            (2.., 0) => ImageKind::ClipArt,
+            (0, 1..) => ImageKind::LineDrawing,
+            _ => ImageKind::Ambiguous,
+        }
+    }
+}
+
+/// The kind of an image, as classified by [`ImageType::classification`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    /// Neither clip art nor a line drawing.
+    Photo,
+
+    /// Normal or good clip art.
+    ClipArt,
+
+    /// A line drawing.
+    LineDrawing,
+
+    /// Ambiguous clip art, or both clip art and a line drawing.
+    Ambiguous,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ImageUrl {
     /// Publicly reachable URL of an image.
@@ -431,6 +1035,14 @@ pub struct ObjectHierarchy {
     pub parent: Option<Box<ObjectHierarchy>>, // m.b.o
 }
 
+impl ObjectHierarchy {
+    /// Returns an iterator yielding this object followed by each of its
+    /// parents, i.e. from the most to the least specific.
+    pub fn iter_chain(&self) -> impl Iterator<Item = &ObjectHierarchy> {
+        std::iter::successors(Some(self), |object| object.parent.as_deref())
+    }
+}
+
 /// A string indicating what visual feature types to return.
 ///
 /// Multiple values should be comma-separated.
@@ -455,7 +1067,8 @@ pub struct ObjectHierarchy {
 ///   approximate location. The `Objects` argument is only available in English.
 /// - `Brands`: Detects various brands within an image, including the
 ///   approximate location. The `Brands` argument is only available in English.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum VisualFeatureTypes {
     Adult,
     Brands,
@@ -467,3 +1080,818 @@ pub enum VisualFeatureTypes {
     Objects,
     Tags,
 }
+
+impl VisualFeatureTypes {
+    /// Every visual feature type.
+    pub const ALL: &'static [VisualFeatureTypes] = &[
+        VisualFeatureTypes::Adult,
+        VisualFeatureTypes::Brands,
+        VisualFeatureTypes::Categories,
+        VisualFeatureTypes::Color,
+        VisualFeatureTypes::Description,
+        VisualFeatureTypes::Faces,
+        VisualFeatureTypes::ImageType,
+        VisualFeatureTypes::Objects,
+        VisualFeatureTypes::Tags,
+    ];
+
+    /// Parses each of `names` as a [`VisualFeatureTypes`], e.g. to select
+    /// features from configuration, failing on the first unknown name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v32::VisualFeatureTypes;
+    ///
+    /// let features = VisualFeatureTypes::from_strings(&["Faces", "Tags"]);
+    ///
+    /// assert_eq!(
+    ///     features.unwrap(),
+    ///     [VisualFeatureTypes::Faces, VisualFeatureTypes::Tags]
+    /// );
+    /// ```
+    pub fn from_strings(
+        names: &[&str],
+    ) -> std::result::Result<Vec<Self>, ParseFeatureError> {
+        names.iter().map(|name| name.parse()).collect()
+    }
+}
+
+/// Formats the feature type as the name of its result in the response, e.g.
+/// `imageType`.
+impl std::fmt::Display for VisualFeatureTypes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let feature_str = match self {
+            VisualFeatureTypes::Adult => "adult",
+            VisualFeatureTypes::Brands => "brands",
+            VisualFeatureTypes::Categories => "categories",
+            VisualFeatureTypes::Color => "color",
+            VisualFeatureTypes::Description => "description",
+            VisualFeatureTypes::Faces => "faces",
+            VisualFeatureTypes::ImageType => "imageType",
+            VisualFeatureTypes::Objects => "objects",
+            VisualFeatureTypes::Tags => "tags",
+        };
+        write!(f, "{}", feature_str)
+    }
+}
+
+/// Parses the name of a feature type as used by the API, e.g. `"ImageType"`,
+/// ignoring ASCII case.
+impl std::str::FromStr for VisualFeatureTypes {
+    type Err = ParseFeatureError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|feature| format!("{:?}", feature).eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| ParseFeatureError(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::round_trip;
+    use crate::v32::NormRect;
+
+    fn detected_object(
+        name: &str,
+        x: PixelCount,
+        y: PixelCount,
+    ) -> DetectedObject {
+        DetectedObject {
+            confidence: 1.0,
+            object: name.to_string(),
+            parent: None,
+            rectangle: BoundingRect { h: 10, w: 10, x, y },
+        }
+    }
+
+    fn image_analysis(objects: Option<Vec<DetectedObject>>) -> ImageAnalysis {
+        ImageAnalysis {
+            adult: None,
+            brands: None,
+            categories: None,
+            color: None,
+            description: None,
+            faces: None,
+            image_type: None,
+            metadata: ImageMetadata {
+                format: "Jpeg".to_string(),
+                height: 500,
+                width: 500,
+            },
+            model_version: "2021-05-01".to_string(),
+            objects,
+            request_id: "00000000-0000-0000-0000-000000000000".to_string(),
+            tags: None,
+        }
+    }
+
+    #[test]
+    fn test_require_present() {
+        let analysis =
+            image_analysis(Some(vec![detected_object("chair", 0, 0)]));
+
+        assert_eq!(analysis.require_objects().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_require_absent() {
+        let analysis = image_analysis(None);
+
+        assert!(matches!(
+            analysis.require_objects(),
+            Err(Error::FeatureNotReturned(VisualFeatureTypes::Objects))
+        ));
+        assert!(matches!(
+            analysis.require_image_type(),
+            Err(Error::FeatureNotReturned(VisualFeatureTypes::ImageType))
+        ));
+    }
+
+    #[test]
+    fn test_image_type_classification() {
+        let image_type = |clipart_type, line_drawing_type| ImageType {
+            clipart_type,
+            line_drawing_type,
+        };
+
+        assert_eq!(image_type(0, 0).classification(), ImageKind::Photo);
+        assert_eq!(image_type(3, 0).classification(), ImageKind::ClipArt);
+        assert_eq!(image_type(2, 0).classification(), ImageKind::ClipArt);
+        assert_eq!(image_type(0, 1).classification(), ImageKind::LineDrawing);
+        assert_eq!(image_type(1, 0).classification(), ImageKind::Ambiguous);
+        assert_eq!(image_type(3, 1).classification(), ImageKind::Ambiguous);
+
+        assert!(image_type(0, 0).is_likely_photo());
+        assert!(!image_type(1, 0).is_likely_photo());
+        assert!(!image_type(0, 1).is_likely_photo());
+    }
+
+    #[test]
+    fn test_to_coco_annotations() {
+        let objects: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/objects.json"
+        ))
+        .unwrap();
+        let faces: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/faces.json"
+        ))
+        .unwrap();
+        let faces = faces.require_faces().unwrap();
+
+        let coco = to_coco_annotations(
+            objects.require_objects().unwrap(),
+            faces,
+            1,
+            (1280, 853),
+        );
+
+        let categories = coco["categories"].as_array().unwrap();
+        assert_eq!(categories.len(), 3);
+        assert_eq!(categories[2]["name"], "face");
+
+        let annotations = coco["annotations"].as_array().unwrap();
+        assert_eq!(annotations.len(), 2 + faces.len());
+        assert_eq!(annotations[1]["category_id"], 2);
+        assert_eq!(
+            annotations[1]["bbox"],
+            serde_json::json!([35, 52, 918, 795])
+        );
+        assert_eq!(annotations[1]["score"], 0.908);
+        assert_eq!(annotations[2]["category_id"], 3);
+        assert!(annotations[2].get("score").is_none());
+    }
+
+    #[test]
+    fn test_correlate_faces_objects() {
+        let face = |left, top| FaceDescription {
+            age: Some(30),
+            face_rectangle: FaceRectangle {
+                left,
+                top,
+                width: 20,
+                height: 20,
+            },
+            gender: None,
+        };
+        let object = |object: &str, parent: Option<&str>, x| DetectedObject {
+            confidence: 0.9,
+            object: object.to_string(),
+            parent: parent.map(|parent| ObjectHierarchy {
+                confidence: 0.9,
+                object: parent.to_string(),
+                parent: None,
+            }),
+            rectangle: BoundingRect {
+                x,
+                y: 0,
+                w: 100,
+                h: 200,
+            },
+        };
+        let faces = [face(40, 10), face(240, 10)];
+        let objects =
+            [object("hat", None, 0), object("man", Some("person"), 0)];
+
+        let correlated = correlate_faces_objects(&faces, &objects);
+
+        assert_eq!(correlated.len(), 2);
+        assert_eq!(correlated[0].0.face_rectangle.left, 40);
+        assert_eq!(correlated[0].1.map(|o| o.object.as_str()), Some("man"));
+        assert!(correlated[1].1.is_none());
+    }
+
+    #[test]
+    fn test_iou_rect() {
+        let a = BoundingRect {
+            x: 0,
+            y: 0,
+            w: 10,
+            h: 10,
+        };
+        let b = BoundingRect {
+            x: 0,
+            y: 5,
+            w: 10,
+            h: 10,
+        };
+        let c = BoundingRect {
+            x: 10,
+            y: 0,
+            w: 10,
+            h: 10,
+        };
+        let empty = BoundingRect {
+            x: 3,
+            y: 3,
+            w: 0,
+            h: 0,
+        };
+
+        assert_eq!(iou_rect(&a, &a), 1.0);
+        // Touching edges do not overlap.
+        assert_eq!(iou_rect(&a, &c), 0.0);
+        // Intersection 50, union 150.
+        assert!((iou_rect(&a, &b) - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(iou_rect(&empty, &empty), 0.0);
+        assert_eq!(iou_rect(&a, &empty), 0.0);
+    }
+
+    #[test]
+    fn test_objects_in_reading_order() {
+        let analysis = image_analysis(Some(vec![
+            detected_object("bottom", 5, 100),
+            detected_object("top-right", 200, 8),
+            detected_object("top-left", 10, 12),
+        ]));
+
+        let names: Vec<&str> = analysis
+            .objects_in_reading_order(5)
+            .iter()
+            .map(|object| object.object.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["top-left", "top-right", "bottom"]);
+    }
+
+    #[test]
+    fn test_objects_in_reading_order_no_objects() {
+        let analysis = image_analysis(None);
+
+        assert!(analysis.objects_in_reading_order(5).is_empty());
+    }
+
+    #[test]
+    fn test_dominant_color_from_str() {
+        assert_eq!(DominantColor::from("White"), DominantColor::White);
+        assert_eq!(DominantColor::from("Grey"), DominantColor::Grey);
+        assert_eq!(DominantColor::from("Teal"), DominantColor::Teal);
+        assert_eq!(
+            DominantColor::from("Magenta"),
+            DominantColor::Other("Magenta".to_string())
+        );
+    }
+
+    #[test]
+    fn test_color_info_typed_accessors() {
+        let color = ColorInfo {
+            accent_color: "666666".to_string(),
+            dominant_color_background: "Black".to_string(),
+            dominant_color_foreground: "Grey".to_string(),
+            dominant_colors: vec!["Black".to_string(), "Grey".to_string()],
+            is_bw_img: true,
+        };
+
+        assert_eq!(
+            color.dominant_color_background_typed(),
+            DominantColor::Black
+        );
+        assert_eq!(
+            color.dominant_color_foreground_typed(),
+            DominantColor::Grey
+        );
+        assert_eq!(
+            color.dominant_colors_typed(),
+            vec![DominantColor::Black, DominantColor::Grey]
+        );
+    }
+
+    fn image_tags(tags: &[(&str, Number)]) -> Vec<ImageTag> {
+        tags.iter()
+            .map(|&(name, confidence)| ImageTag {
+                confidence,
+                hint: None,
+                name: name.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_tags() {
+        let a = image_tags(&[("dog", 0.9), ("grass", 0.6)]);
+        let b = image_tags(&[("dog", 0.7), ("ball", 0.8)]);
+
+        assert_eq!(
+            aggregate_tags(&[&a, &b], TagAggregation::Max),
+            vec![
+                ("dog".to_string(), 0.9),
+                ("ball".to_string(), 0.8),
+                ("grass".to_string(), 0.6),
+            ]
+        );
+
+        let mean = aggregate_tags(&[&a, &b], TagAggregation::Mean);
+        let names: Vec<&str> = mean.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["dog", "ball", "grass"]);
+        assert!((mean[0].1 - 0.8).abs() < 1e-9);
+        assert!((mean[1].1 - 0.4).abs() < 1e-9);
+        assert!((mean[2].1 - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_visual_feature_types_from_strings() {
+        let features =
+            VisualFeatureTypes::from_strings(&["Adult", "imageType", "TAGS"]);
+
+        assert_eq!(
+            features.unwrap(),
+            [
+                VisualFeatureTypes::Adult,
+                VisualFeatureTypes::ImageType,
+                VisualFeatureTypes::Tags
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visual_feature_types_from_strings_unknown() {
+        let result = VisualFeatureTypes::from_strings(&["Adult", "People"]);
+
+        assert_eq!(result, Err(ParseFeatureError("People".to_string())));
+    }
+
+    fn category(name: &str) -> Category {
+        Category {
+            detail: None,
+            name: name.to_string(),
+            score: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_category_top_level() {
+        assert_eq!(category("people_portrait").top_level(), "people");
+        assert_eq!(category("others_").top_level(), "others");
+        assert_eq!(category("people").top_level(), "people");
+        assert_eq!(category("").top_level(), "");
+    }
+
+    #[test]
+    fn test_category_is_leaf() {
+        assert!(category("people_portrait").is_leaf());
+        assert!(category("building_pillar").is_leaf());
+        assert!(!category("others_").is_leaf());
+        assert!(!category("abstract_").is_leaf());
+        assert!(!category("people").is_leaf());
+    }
+
+    #[test]
+    fn test_category_score_256() {
+        let sample: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/categories_1.json"
+        ))
+        .unwrap();
+        let scores: Vec<_> = sample
+            .categories
+            .unwrap()
+            .iter()
+            .map(Category::score_256)
+            .collect();
+        assert_eq!(scores, [Some(1), Some(5), Some(100)]);
+
+        let mut category = category("people_portrait");
+        category.score = 0.91796875;
+        assert_eq!(category.score_256(), Some(235));
+
+        category.score = 0.0;
+        assert_eq!(category.score_256(), Some(0));
+
+        category.score = 0.9;
+        assert_eq!(category.score_256(), None);
+
+        category.score = 1.0;
+        assert_eq!(category.score_256(), None);
+
+        category.score = -0.5;
+        assert_eq!(category.score_256(), None);
+
+        category.score = Number::NAN;
+        assert_eq!(category.score_256(), None);
+    }
+
+    #[test]
+    fn test_has_faces() {
+        let analysis: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/faces.json"
+        ))
+        .unwrap();
+
+        assert!(analysis.has_faces());
+    }
+
+    #[test]
+    fn test_has_faces_none() {
+        let mut analysis = image_analysis(None);
+        assert!(!analysis.has_faces());
+
+        analysis.faces = Some(vec![]);
+        assert!(!analysis.has_faces());
+    }
+
+    #[test]
+    fn test_is_black_and_white() {
+        let analysis: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/color.json"
+        ))
+        .unwrap();
+
+        assert_eq!(analysis.is_black_and_white(), Some(true));
+    }
+
+    #[test]
+    fn test_is_black_and_white_no_color() {
+        let analysis = image_analysis(None);
+
+        assert_eq!(analysis.is_black_and_white(), None);
+    }
+
+    #[test]
+    fn test_high_confidence_tags() {
+        let analysis: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/tags.json"
+        ))
+        .unwrap();
+
+        let tags = analysis.high_confidence_tags(0.9);
+        assert_eq!(tags.len(), 13);
+        assert_eq!(tags[0], "black and white");
+        assert_eq!(tags[12], "man");
+
+        assert_eq!(
+            analysis.high_confidence_tags(0.95),
+            vec![
+                "black and white",
+                "headdress",
+                "human face",
+                "person",
+                "clothing",
+                "outdoor",
+                "hat",
+                "sun hat",
+                "building",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_confidence_buckets() {
+        let analysis: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/tags.json"
+        ))
+        .unwrap();
+
+        let buckets = analysis.tag_confidence_buckets(0.1).unwrap();
+
+        assert_eq!(
+            buckets.into_iter().collect::<Vec<_>>(),
+            vec![(6, 1), (7, 1), (8, 3), (9, 13)]
+        );
+        assert!(image_analysis(None)
+            .tag_confidence_buckets(0.1)
+            .unwrap()
+            .is_empty());
+        assert_eq!(analysis.tag_confidence_buckets(0.0), None);
+    }
+
+    #[test]
+    fn test_image_tag_to_common_tag() {
+        let tag = ImageTag {
+            confidence: 0.9,
+            hint: Some("headwear".to_string()),
+            name: "hat".to_string(),
+        };
+
+        assert_eq!(
+            CommonTag::from(&tag),
+            CommonTag {
+                name: "hat".to_string(),
+                confidence: 0.9,
+                hint: Some("headwear".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_high_confidence_tags_no_tags() {
+        let analysis = image_analysis(None);
+
+        assert!(analysis.high_confidence_tags(0.0).is_empty());
+    }
+
+    #[test]
+    fn test_clamp_to() {
+        let rect = BoundingRect {
+            h: 100,
+            w: 100,
+            x: 1000,
+            y: 650,
+        };
+        let face = FaceRectangle {
+            height: 100,
+            left: 1000,
+            top: 650,
+            width: 100,
+        };
+
+        let rect = rect.clamp_to(1038, 692);
+        let face = face.clamp_to(1038, 692);
+
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (1000, 650, 38, 42));
+        assert_eq!(
+            (face.left, face.top, face.width, face.height),
+            (1000, 650, 38, 42)
+        );
+    }
+
+    #[test]
+    fn test_face_rectangle_center_and_radius() {
+        let face = FaceRectangle {
+            height: 229,
+            left: 352,
+            top: 285,
+            width: 229,
+        };
+
+        assert_eq!(face.center(), (466, 399));
+        assert_eq!(face.radius(), 115);
+    }
+
+    #[test]
+    fn test_face_rectangle_radius_uses_larger_dimension() {
+        let face = FaceRectangle {
+            height: 40,
+            left: 0,
+            top: 0,
+            width: 30,
+        };
+
+        assert_eq!(face.center(), (15, 20));
+        assert_eq!(face.radius(), 20);
+    }
+
+    #[test]
+    fn test_object_hierarchy_iter_chain() {
+        let hierarchy = ObjectHierarchy {
+            confidence: 0.9,
+            object: "bulldog".to_string(),
+            parent: Some(Box::new(ObjectHierarchy {
+                confidence: 0.95,
+                object: "dog".to_string(),
+                parent: Some(Box::new(ObjectHierarchy {
+                    confidence: 0.99,
+                    object: "mammal".to_string(),
+                    parent: None,
+                })),
+            })),
+        };
+
+        let chain: Vec<(&str, Number)> = hierarchy
+            .iter_chain()
+            .map(|object| (object.object.as_str(), object.confidence))
+            .collect();
+
+        assert_eq!(
+            chain,
+            vec![("bulldog", 0.9), ("dog", 0.95), ("mammal", 0.99)]
+        );
+    }
+
+    #[test]
+    fn test_best_caption() {
+        let caption = |text: &str, confidence| ImageCaption {
+            confidence,
+            text: text.to_string(),
+        };
+        let description = ImageDescriptionDetails {
+            captions: vec![
+                caption("a dog", 0.4),
+                caption("a dog on grass", 0.7),
+                caption("malformed", Number::NAN),
+            ],
+            tags: vec![],
+        };
+
+        assert_eq!(description.best_caption().unwrap().text, "a dog on grass");
+    }
+
+    #[test]
+    fn test_best_caption_no_captions() {
+        let description = ImageDescriptionDetails {
+            captions: vec![],
+            tags: vec![],
+        };
+
+        assert!(description.best_caption().is_none());
+    }
+
+    #[test]
+    fn test_rects_to_norm_rect() {
+        let rect = BoundingRect {
+            h: 50,
+            w: 100,
+            x: 50,
+            y: 25,
+        };
+        let face = FaceRectangle {
+            height: 50,
+            left: 50,
+            top: 25,
+            width: 100,
+        };
+        let expected = NormRect {
+            x: 0.25,
+            y: 0.25,
+            w: 0.5,
+            h: 0.5,
+        };
+
+        assert_eq!(PixelRect::from(&rect).normalize(200, 100), Some(expected));
+        assert_eq!(PixelRect::from(&face).normalize(200, 100), Some(expected));
+    }
+
+    #[test]
+    fn test_image_metadata_landscape() {
+        let analysis: ImageAnalysis = serde_json::from_str(include_str!(
+            "../../tests/samples/v32/all_features.json"
+        ))
+        .unwrap();
+        let metadata = analysis.metadata;
+
+        assert_eq!((metadata.width, metadata.height), (1038, 692));
+        assert_eq!(metadata.aspect_ratio(), 1.5);
+        assert!(metadata.is_landscape());
+        assert!(!metadata.is_portrait());
+    }
+
+    #[test]
+    fn test_image_metadata_portrait() {
+        let metadata = ImageMetadata {
+            format: "Jpeg".to_string(),
+            height: 1280,
+            width: 853,
+        };
+
+        assert!((metadata.aspect_ratio() - 0.6664).abs() < 1e-4);
+        assert!(metadata.is_portrait());
+        assert!(!metadata.is_landscape());
+    }
+
+    #[test]
+    fn test_image_url_query_string_round_trip() {
+        let url = "https://account.blob.core.windows.net/images/a%20b.jpg\
+                   ?sv=2022-11-02&sp=r&sig=abc%2Bdef%2F%3D&name=caf\u{e9}";
+        let image_url = ImageUrl {
+            url: url.to_string(),
+        };
+
+        let json = serde_json::to_string(&image_url).unwrap();
+        let round_tripped: ImageUrl = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains("?sv=2022-11-02&sp=r&sig=abc%2Bdef%2F%3D"));
+        assert_eq!(round_tripped.url, url);
+    }
+
+    fn should_retry(err: &impl ApiError) -> bool {
+        err.is_retryable()
+    }
+
+    fn error_response(
+        code: &str,
+        inner_code: &str,
+    ) -> ComputerVisionErrorResponse {
+        serde_json::from_str(&format!(
+            r#"{{
+                "error": {{
+                    "code": "{}",
+                    "innererror": {{"code": "{}", "message": "message"}},
+                    "message": "message"
+                }}
+            }}"#,
+            code, inner_code
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_api_error_service_unavailable() {
+        let err = error_response("ServiceUnavailable", "Unspecified");
+
+        assert!(should_retry(&err));
+        assert_eq!(err.code(), "ServiceUnavailable");
+        assert_eq!(err.message(), "message");
+    }
+
+    #[test]
+    fn test_api_error_invalid_request() {
+        let err = error_response("InvalidRequest", "InvalidImageSize");
+
+        assert!(!should_retry(&err));
+        assert_eq!(err.code(), "InvalidRequest");
+        assert!(should_retry(&error_response("InvalidRequest", "Timeout")));
+        assert!(should_retry(&error_response(
+            "InvalidRequest",
+            "InternalServerError"
+        )));
+    }
+
+    #[test]
+    fn test_error_response_source_chain() {
+        let err = error_response("InvalidRequest", "InvalidImageSize");
+
+        let source = std::error::Error::source(&err).unwrap();
+
+        assert_eq!(err.to_string(), "InvalidRequest: message");
+        assert_eq!(source.to_string(), "InvalidImageSize: message");
+        assert!(source.source().is_none());
+    }
+
+    #[test]
+    fn test_to_pretty_json() {
+        for sample in round_trip::samples("v32") {
+            let analysis: ImageAnalysis =
+                serde_json::from_str(&sample).unwrap();
+
+            let json = analysis.to_pretty_json().unwrap();
+            let reparsed: ImageAnalysis = serde_json::from_str(&json).unwrap();
+
+            assert!(json.contains("\n  \"requestId\": "));
+            assert_eq!(
+                serde_json::to_value(&reparsed).unwrap(),
+                serde_json::to_value(&analysis).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_samples_round_trip() {
+        for sample in [
+            include_str!("../../tests/samples/v32/adult.json"),
+            include_str!("../../tests/samples/v32/all_features.json"),
+            include_str!("../../tests/samples/v32/brands.json"),
+            include_str!("../../tests/samples/v32/categories_1.json"),
+            include_str!("../../tests/samples/v32/categories_2.json"),
+            include_str!("../../tests/samples/v32/categories_3.json"),
+            include_str!("../../tests/samples/v32/color.json"),
+            include_str!("../../tests/samples/v32/description.json"),
+            include_str!("../../tests/samples/v32/faces.json"),
+            include_str!("../../tests/samples/v32/image_type.json"),
+            include_str!("../../tests/samples/v32/no_query_parameters.json"),
+            include_str!("../../tests/samples/v32/objects.json"),
+            include_str!("../../tests/samples/v32/tags.json"),
+        ] {
+            round_trip::assert_round_trip::<ImageAnalysis>(sample);
+        }
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn test_samples_simd_json() {
+        for sample in round_trip::samples("v32") {
+            round_trip::assert_simd_json_identical::<ImageAnalysis>(&sample);
+        }
+    }
+}
@@ -11,13 +11,37 @@ SPDX-License-Identifier: Apache-2.0 OR MIT
 //! - Dimensions must be greater than 50 x 50 pixels and less than
 //!     16,000 x 16,000 pixels.
 
+use crate::common::error_body::{self, ErrorBody};
+#[cfg(feature = "fs")]
+use crate::common::file::{self, FileBody};
+use crate::common::http::HttpVersion;
+use crate::common::request_id;
 use crate::common::secret::Secret;
+use crate::common::success_body::SuccessBody;
 use crate::v32::*;
 use serde::Serialize;
+use std::collections::HashSet;
+
+/// Content type of requests carrying raw image data.
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Content type of requests carrying an image URL.
+const JSON: &str = "application/json";
+
+/// Name of the header carrying the client request ID, see
+/// [`Client::with_request_id_generator`].
+pub const CLIENT_REQUEST_ID: &str = crate::common::request_id::HEADER;
 
 /// Maximum input image size allowed by the API.
 pub const MAX_IMAGE_SIZE: usize = 4 * 1024 * 1024; // 4194304 bytes
 
+/// Language code of English, the default language of the API.
+const ENGLISH: &str = "en";
+
+/// Visual features that are only available in English.
+const ENGLISH_ONLY_FEATURES: &[VisualFeatureTypes] =
+    &[VisualFeatureTypes::Brands, VisualFeatureTypes::Objects];
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Represents the various errors that can occur while using the [`Client`].
@@ -34,16 +58,92 @@ pub enum Error {
     #[error("API error response: {0}")]
     API(#[from] ComputerVisionErrorResponse),
 
+    /// The API responded with an error status, but the body is not a
+    /// well-formed error response, e.g. because it comes from a gateway or
+    /// was truncated.
+    ///
+    /// `message` is the `message` of the body if it is JSON with one, or
+    /// else the body itself, shortened. `client_request_id` is the client
+    /// request ID of the request, see [`Error::client_request_id`].
+    #[error("HTTP error {status}: {message}")]
+    Http {
+        status: reqwest::StatusCode,
+        message: String,
+        client_request_id: Option<String>,
+    },
+
     /// Wrapper around [`reqwest::Error`].
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
 
+    /// Wrapper around [`image::ImageError`].
+    ///
+    /// This error occurs in [`Client::analyze_image_autoresize`] when the
+    /// image cannot be decoded or re-encoded.
+    #[cfg(feature = "image")]
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    /// Wrapper around [`std::io::Error`].
+    ///
+    /// This error occurs in [`Client::analyze_image_file_async`] when the file
+    /// cannot be opened or read.
+    #[cfg(feature = "fs")]
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
     /// Wrapper around [`ValidationError`].
     ///
     /// Represents errors identified through client validation, before any
     /// interaction with external servers.
     #[error(transparent)]
     Validation(#[from] ValidationError),
+
+    /// The API returned a response body that is not JSON of the expected
+    /// shape.
+    ///
+    /// This is the case whichever parser is used; with the `simd-json`
+    /// feature, the error carries the message of `simd-json`.
+    #[error("JSON error: {0}")]
+    Json(#[source] serde_json::Error),
+
+    /// The API response does not contain the result of a visual feature, e.g.
+    /// in [`Client::describe_url`] or [`ImageAnalysis::require_description`]
+    /// if the description is missing.
+    #[error("The response does not contain the `{0}` result.")]
+    FeatureNotReturned(VisualFeatureTypes),
+}
+
+impl Error {
+    /// Returns the client request ID of the request that failed with an
+    /// [`Error::API`] or [`Error::Http`], as echoed by the API or else as sent,
+    /// if any, for correlation with the logs of the API.
+    ///
+    /// See [`Client::with_request_id_generator`].
+    pub fn client_request_id(&self) -> Option<&str> {
+        match self {
+            Error::API(err) => err.client_request_id.as_deref(),
+            Error::Http {
+                client_request_id, ..
+            } => client_request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the API rejected the request because a requested
+    /// feature is not supported, e.g. in the region of the resource.
+    ///
+    /// Callers can degrade gracefully by dropping the feature and retrying.
+    pub fn is_feature_unsupported(&self) -> bool {
+        matches!(
+            self,
+            Error::API(err) if matches!(
+                err.error.innererror.code,
+                ComputerVisionInnerErrorCodeValue::NotSupportedFeature
+                    | ComputerVisionInnerErrorCodeValue::NotSupportedVisualFeature
+            )
+        )
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -55,6 +155,78 @@ pub enum ValidationError {
     /// invalid for use with the Azure API.
     #[error("Invalid header value: {0}")]
     InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
+
+    /// The provided endpoint is not an `https://` or `http://` URL with a
+    /// host.
+    ///
+    /// This error occurs specifically in [`Client::new`] when the provided
+    /// endpoint has no scheme, e.g.
+    /// `"myresource.cognitiveservices.azure.com/"`, or no host, e.g.
+    /// `"https:///"`.
+    #[error("Invalid endpoint (expected an `https://` URL with a host): {0}")]
+    InvalidEndpoint(String),
+
+    /// The provided data URI is malformed, is not a base64-encoded image, or
+    /// its image exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// This error occurs specifically in [`Client::analyze_data_uri`].
+    #[cfg(feature = "data-uri")]
+    #[error("Invalid data URI: {0}")]
+    InvalidDataUri(&'static str),
+
+    /// The file exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// This error occurs specifically in [`Client::analyze_image_file_async`],
+    /// which checks the size of the file before uploading it.
+    #[cfg(feature = "fs")]
+    #[error("File of {0} bytes exceeds the maximum image size.")]
+    FileTooLarge(u64),
+
+    /// A requested visual feature is only available in English, but another
+    /// [`language`] was requested.
+    ///
+    /// [`VisualFeatureTypes::Objects`] and [`VisualFeatureTypes::Brands`] are
+    /// English-only. The client validates this before sending a request for
+    /// an Analyze Image operation.
+    ///
+    /// [`language`]: AnalyzeImageOptions#structfield.language
+    #[error(
+        "The `{feature:?}` feature is only available in English, not \
+         `{language}`."
+    )]
+    FeatureLanguageConflict {
+        /// The English-only feature.
+        feature: VisualFeatureTypes,
+
+        /// The requested language.
+        language: String,
+    },
+
+    /// The image exceeds [`MAX_IMAGE_SIZE`] and cannot be downscaled to fit
+    /// without its dimensions dropping to 50 x 50 pixels or below.
+    ///
+    /// This error occurs specifically in [`Client::analyze_image_autoresize`].
+    #[cfg(feature = "image")]
+    #[error("Image cannot be downscaled to fit the maximum image size.")]
+    ImageTooLarge,
+
+    /// The image is 50 pixels or less wide or high; the API requires images
+    /// greater than 50 x 50 pixels.
+    ///
+    /// This error occurs specifically in [`Client::analyze_image_autoresize`].
+    #[cfg(feature = "image")]
+    #[error(
+        "Image of {width} x {height} pixels is too small: must be greater \
+         than {min} x {min} pixels.",
+        min = crate::common::MIN_DIMENSION
+    )]
+    ImageTooSmall {
+        /// Width of the image, in pixels.
+        width: u32,
+
+        /// Height of the image, in pixels.
+        height: u32,
+    },
 }
 
 /// Image analysis parameters.
@@ -111,11 +283,89 @@ pub struct AnalyzeImageOptions<'a> {
     pub visual_features: Option<&'a [VisualFeatureTypes]>,
 }
 
+impl<'a> AnalyzeImageOptions<'a> {
+    /// Returns options requesting every visual feature, e.g. for exploration.
+    ///
+    /// See [`VisualFeatureTypes::ALL`].
+    pub fn all_features() -> Self {
+        Self {
+            visual_features: Some(VisualFeatureTypes::ALL),
+            ..Default::default()
+        }
+    }
+
+    /// Returns a copy of the options with [`language`] overridden, e.g. to
+    /// analyze the same image in multiple languages.
+    ///
+    /// [`language`]: Self#structfield.language
+    pub fn with_language(&self, language: &'a str) -> Self {
+        Self {
+            language: Some(language),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the domain models excluded from the description, as sent in
+    /// the `descriptionExclude` query parameter, or an empty slice if none.
+    ///
+    /// If the `tracing` feature is enabled, the exclusions of each request are
+    /// logged with the returned captions at the debug level.
+    pub fn effective_exclusions(&self) -> &'a [DescriptionExclude] {
+        self.description_exclude.unwrap_or_default()
+    }
+}
+
+/// The result of an Analyze Image operation together with the options that
+/// produced it, as returned by [`Client::analyze_image_url_traced`].
+#[derive(Debug)]
+pub struct AnalysisWithRequest {
+    /// The analysis result.
+    pub result: ImageAnalysis,
+
+    /// The requested visual features.
+    pub requested_features: Vec<VisualFeatureTypes>,
+
+    /// The requested model version, if any.
+    pub model_version: Option<String>,
+
+    /// The client request ID sent in the [`CLIENT_REQUEST_ID`] header, if
+    /// any, see [`Client::with_request_id_generator`].
+    pub client_request_id: Option<String>,
+}
+
+/// The results of [`Client::analyze_image_urls`].
+#[derive(Debug)]
+pub struct BatchReport {
+    /// The result for each image URL, in the order given.
+    pub results: Vec<Result<ImageAnalysis>>,
+}
+
+impl BatchReport {
+    /// Returns the distinct model versions that served the successful results.
+    ///
+    /// More than one version means the model changed within the batch, e.g.
+    /// when requesting `model_version: Some("latest")`, so the results may not
+    /// be comparable.
+    pub fn model_versions(&self) -> HashSet<&str> {
+        self.results
+            .iter()
+            .flatten()
+            .map(|analysis| analysis.model_version.as_str())
+            .collect()
+    }
+}
+
 /// Client for the [Analyze Image API v3.2](https://learn.microsoft.com/en-us/rest/api/computervision/analyze-image/analyze-image?view=rest-computervision-v3.2).
 #[derive(Debug, Clone)]
 pub struct Client {
     client: reqwest::Client,
     url: String,
+    #[cfg(feature = "compression")]
+    compression_threshold: Option<usize>,
+    request_id_generator: Option<fn() -> String>,
+    http_version: HttpVersion,
+    /// See [`Client::with_content_type`].
+    set_content_type: bool,
 }
 
 impl Client {
@@ -125,6 +375,14 @@ impl Client {
     /// - `key`: Azure AI Services key.
     /// - `endpoint`: Azure AI Services Computer Vision endpoint.
     ///
+    /// # Errors
+    ///
+    /// - [`ValidationError::InvalidEndpoint`] if `endpoint` is not an
+    ///   `https://` or `http://` URL with a host.
+    /// - [`ValidationError::InvalidHeaderValue`] if `key` contains characters
+    ///   that are invalid in an HTTP header.
+    /// - [`Error::Reqwest`] if the HTTP client cannot be built.
+    ///
     /// # Example
     ///
     /// ```
@@ -137,16 +395,157 @@ impl Client {
     /// let client = Client::new(key, &endpoint).unwrap();
     /// ```
     pub fn new(key: String, endpoint: &str) -> Result<Self> {
+        Self::with_http_version(key, endpoint, HttpVersion::Auto)
+    }
+
+    /// Create a new `Client` that speaks HTTP/2 without negotiating it first
+    /// ("prior knowledge"), which saves a round trip per connection.
+    ///
+    /// By default, clients negotiate the HTTP version with the server. Only
+    /// use this if the endpoint, and any proxy in between, supports HTTP/2.
+    ///
+    /// Not available on `wasm32`, where the browser chooses the version.
+    ///
+    /// # Parameters
+    /// - `key`: Azure AI Services key.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_http2_prior_knowledge(
+        key: String,
+        endpoint: &str,
+    ) -> Result<Self> {
+        Self::with_http_version(key, endpoint, HttpVersion::Http2PriorKnowledge)
+    }
+
+    /// Create a new `Client` that only uses HTTP/1.1, e.g. behind corporate
+    /// proxies that break HTTP/2.
+    ///
+    /// Not available on `wasm32`, where the browser chooses the version.
+    ///
+    /// # Parameters
+    /// - `key`: Azure AI Services key.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_http1_only(key: String, endpoint: &str) -> Result<Self> {
+        Self::with_http_version(key, endpoint, HttpVersion::Http1Only)
+    }
+
+    fn with_http_version(
+        key: String,
+        endpoint: &str,
+        http_version: HttpVersion,
+    ) -> Result<Self> {
         let secret = Secret::new(key);
+        let client = Self::create_http_client(secret, http_version)?;
+
+        Ok(Client {
+            http_version,
+            ..Self::from_http(client, endpoint)?
+        })
+    }
+
+    /// Create a new `Client` that sends requests with `http`, e.g. an HTTP
+    /// client configured with custom middleware, proxies or timeouts.
+    ///
+    /// `http` is used as is, so it must set the `Ocp-Apim-Subscription-Key`
+    /// header itself, e.g. as a default header.
+    ///
+    /// The `Content-Type` header is set on each request according to the kind
+    /// of input, overriding any default `Content-Type` of `http`, unless
+    /// disabled with [`Client::with_content_type`].
+    ///
+    /// # Parameters
+    /// - `http`: HTTP client to send requests with.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint, or e.g. the
+    ///   URL of a mock server such as `"http://127.0.0.1:8080/"`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ValidationError::InvalidEndpoint`] if `endpoint` is not an
+    ///   `https://` or `http://` URL with a host.
+    pub fn with_http_client(
+        http: reqwest::Client,
+        endpoint: &str,
+    ) -> Result<Self> {
+        Self::from_http(http, endpoint)
+    }
+
+    fn from_http(http: reqwest::Client, base_url: &str) -> Result<Self> {
+        Self::validate_endpoint(base_url)?;
+
+        Ok(Client {
+            client: http,
+            url: format!("{}vision/v3.2/analyze", base_url),
+            #[cfg(feature = "compression")]
+            compression_threshold: None,
+            request_id_generator: request_id::default_generator(),
+            http_version: HttpVersion::Auto,
+            set_content_type: true,
+        })
+    }
+
+    /// Set the `Content-Type` header of each request according to the kind of
+    /// input, as by default, or leave it to the HTTP client if
+    /// `set_content_type` is `false`, e.g. to middleware of a client passed to
+    /// [`Client::with_http_client`] that sets its own.
+    pub fn with_content_type(mut self, set_content_type: bool) -> Self {
+        self.set_content_type = set_content_type;
+        self
+    }
+
+    /// Compress image data larger than `threshold` bytes with gzip before
+    /// uploading it, setting `Content-Encoding: gzip`.
+    ///
+    /// This applies to [`Client::analyze_image`] (and methods built on it)
+    /// and may reduce upload times over slow links. Requests for image URLs
+    /// and [`bytes::Bytes`] input are sent uncompressed.
+    ///
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, threshold: usize) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Attach a client request ID produced by `generator` to every request,
+    /// in the [`CLIENT_REQUEST_ID`] header, for correlating logs.
+    ///
+    /// The ID is returned by [`Client::analyze_image_url_traced`] and carried
+    /// by API and HTTP errors, see [`Error::client_request_id`].
+    ///
+    /// With the `uuid` feature, clients attach a random UUID (v4) by default.
+    pub fn with_request_id_generator(
+        mut self,
+        generator: fn() -> String,
+    ) -> Self {
+        self.request_id_generator = Some(generator);
+        self
+    }
 
+    /// Returns a copy of the client that authenticates with `new_key`, e.g.
+    /// to rotate keys in a long-running service without restarting it.
+    ///
+    /// The endpoint and other settings are kept. The HTTP client is rebuilt,
+    /// as its default headers cannot be changed once created. This client is
+    /// left unchanged and can be dropped once no longer in use.
+    ///
+    /// # Parameters
+    /// - `new_key`: Azure AI Services key.
+    pub fn rotate_key(&self, new_key: String) -> Result<Self> {
         Ok(Client {
-            client: Self::create_http_client(secret)?,
-            url: format!("{}vision/v3.2/analyze", endpoint),
+            client: Self::create_http_client(
+                Secret::new(new_key),
+                self.http_version,
+            )?,
+            ..self.clone()
         })
     }
 
     /// Analyze the input image.
     ///
+    /// The URL is sent unchanged in the JSON body of the request, so URLs with
+    /// query strings, such as Azure Blob Storage SAS URLs, are supported.
+    ///
     /// # Parameters
     /// - `image_url`: Publicly reachable URL of an image.
     /// - `options`: Optional parameters to be passed to the Analyze Image API.
@@ -209,6 +608,287 @@ impl Client {
             .await
     }
 
+    /// Analyze the input image, also returning the headers of the response.
+    ///
+    /// Useful for monitoring and correlation, e.g. to log the `apim-request-id`
+    /// header of a request. Otherwise the same as
+    /// [`analyze_image_url`](Self::analyze_image_url).
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v32::client::{AnalyzeImageOptions, Client};
+    /// use az_analyze_image::v32::VisualFeatureTypes;
+    /// use std::env;
+    ///
+    /// const IMAGE_URL: &str =
+    ///     "https://upload.wikimedia.org/wikipedia/commons/2/2a/Human_faces.jpg";
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let key = env::var("CV_KEY").expect("No CV_KEY");
+    ///     let endpoint = env::var("CV_ENDPOINT").expect("No CV_ENDPOINT");
+    ///
+    ///     let client = Client::new(key, &endpoint).unwrap();
+    ///
+    ///     let visual_features = vec![VisualFeatureTypes::Tags];
+    ///     let options = AnalyzeImageOptions {
+    ///         visual_features: Some(&visual_features),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let (_analysis, headers) = client
+    ///         .analyze_image_url_full(IMAGE_URL, options)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     println!("{:?}", headers.get("apim-request-id"));
+    /// }
+    /// ```
+    pub async fn analyze_image_url_full(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<(ImageAnalysis, reqwest::header::HeaderMap)> {
+        let description_exclude = options.effective_exclusions();
+        let request_id = self.generate_request_id();
+        let response = self
+            .send_request(
+                ImageInput::Url(image_url),
+                options,
+                request_id.as_deref(),
+            )
+            .await?;
+        let headers = response.headers().clone();
+
+        let analysis = Self::handle_response(
+            response,
+            request_id.as_deref(),
+            description_exclude,
+        )
+        .await?;
+
+        Ok((analysis, headers))
+    }
+
+    /// Analyze the input image, also returning the requested visual features
+    /// and model version, and the client request ID, if any, e.g. for logging
+    /// or reproducibility.
+    ///
+    /// Otherwise the same as [`analyze_image_url`](Self::analyze_image_url).
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    pub async fn analyze_image_url_traced(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<AnalysisWithRequest> {
+        let requested_features = options
+            .visual_features
+            .map(<[_]>::to_vec)
+            .unwrap_or_default();
+        let model_version = options.model_version.map(str::to_string);
+        let description_exclude = options.effective_exclusions();
+
+        let client_request_id = self.generate_request_id();
+        let response = self
+            .send_request(
+                ImageInput::Url(image_url),
+                options,
+                client_request_id.as_deref(),
+            )
+            .await?;
+        let result = Self::handle_response(
+            response,
+            client_request_id.as_deref(),
+            description_exclude,
+        )
+        .await?;
+
+        Ok(AnalysisWithRequest {
+            result,
+            requested_features,
+            model_version,
+            client_request_id,
+        })
+    }
+
+    /// Analyze the input image, also returning the wall-clock time the request
+    /// took as measured by the client, e.g. for latency tracking.
+    ///
+    /// Otherwise the same as [`analyze_image_url`](Self::analyze_image_url).
+    ///
+    /// Not available on `wasm32`, where [`std::time::Instant`] is unsupported.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn analyze_image_url_timed(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<(ImageAnalysis, std::time::Duration)> {
+        let start = std::time::Instant::now();
+        let result = self.analyze_image_url(image_url, options).await?;
+
+        Ok((result, start.elapsed()))
+    }
+
+    /// Analyze the images at `image_urls` with the same options, e.g. for
+    /// batch processing.
+    ///
+    /// Requests are sent one after another to stay within the rate limit of
+    /// the resource. A failed request does not stop the batch; its error is
+    /// reported in place of its result.
+    ///
+    /// # Parameters
+    /// - `image_urls`: Publicly reachable URLs of images.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API
+    ///   for every image.
+    pub async fn analyze_image_urls(
+        &self,
+        image_urls: &[&str],
+        options: AnalyzeImageOptions<'_>,
+    ) -> BatchReport {
+        let mut results = Vec::with_capacity(image_urls.len());
+        for image_url in image_urls {
+            results
+                .push(self.analyze_image_url(image_url, options.clone()).await);
+        }
+
+        BatchReport { results }
+    }
+
+    /// Describe the input image, e.g. for a one-line description.
+    ///
+    /// Requests only the [`VisualFeatureTypes::Description`] feature and
+    /// returns the description of the image. Use
+    /// [`ImageDescriptionDetails::best_caption`] to get the caption with the
+    /// highest confidence.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `language`: The desired language for output generation. Defaults to
+    ///   `"en"` if `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v32::client::Client;
+    /// use az_analyze_image::v32::format_confidence;
+    /// use std::env;
+    ///
+    /// const IMAGE_URL: &str =
+    ///     "https://upload.wikimedia.org/wikipedia/commons/2/2a/Human_faces.jpg";
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let key = env::var("CV_KEY").expect("No CV_KEY");
+    ///     let endpoint = env::var("CV_ENDPOINT").expect("No CV_ENDPOINT");
+    ///
+    ///     let client = Client::new(key, &endpoint).unwrap();
+    ///
+    ///     let description = client.describe_url(IMAGE_URL, None).await.unwrap();
+    ///
+    ///     if let Some(caption) = description.best_caption() {
+    ///         println!(
+    ///             "{} ({})",
+    ///             caption.text,
+    ///             format_confidence(caption.confidence, 4)
+    ///         );
+    ///     }
+    /// }
+    /// ```
+    pub async fn describe_url(
+        &self,
+        image_url: &str,
+        language: Option<&str>,
+    ) -> Result<ImageDescriptionDetails> {
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&[VisualFeatureTypes::Description]),
+            language,
+            ..Default::default()
+        };
+
+        self.analyze_image_url(image_url, options)
+            .await?
+            .description
+            .ok_or(Error::FeatureNotReturned(VisualFeatureTypes::Description))
+    }
+
+    /// Returns the metadata of the image at `image_url`, i.e. its dimensions
+    /// and format, without requesting any visual feature.
+    ///
+    /// The API still categorizes the image, as if
+    /// [`VisualFeatureTypes::Categories`] were requested, but the categories
+    /// are discarded.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    pub async fn metadata_url(&self, image_url: &str) -> Result<ImageMetadata> {
+        Ok(self
+            .analyze_image_url(image_url, AnalyzeImageOptions::default())
+            .await?
+            .metadata)
+    }
+
+    /// Analyze the input image, trying each of `model_versions` in order.
+    ///
+    /// If the API rejects a model version with
+    /// [`ComputerVisionInnerErrorCodeValue::InvalidModel`], the request is
+    /// retried with the next one, and the first successful analysis is
+    /// returned. Any other error is returned immediately. If every model
+    /// version is rejected, the last error is returned.
+    ///
+    /// The `model_version` of `options` is ignored unless `model_versions` is
+    /// empty.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    /// - `model_versions`: Model versions to try, e.g.
+    ///   `&["2021-05-01", "latest"]`.
+    pub async fn analyze_image_url_with_fallback<'a>(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'a>,
+        model_versions: &[&'a str],
+    ) -> Result<ImageAnalysis> {
+        let Some((last, rest)) = model_versions.split_last() else {
+            return self.analyze_image_url(image_url, options).await;
+        };
+
+        for model_version in rest {
+            let options = AnalyzeImageOptions {
+                model_version: Some(model_version),
+                ..options.clone()
+            };
+
+            match self.analyze_image_url(image_url, options).await {
+                Err(Error::API(err))
+                    if err.error.innererror.code
+                        == ComputerVisionInnerErrorCodeValue::InvalidModel =>
+                {
+                    continue
+                }
+                result => return result,
+            }
+        }
+
+        let options = AnalyzeImageOptions {
+            model_version: Some(last),
+            ..options
+        };
+        self.analyze_image_url(image_url, options).await
+    }
+
     /// Analyze the input image.
     ///
     /// # Parameters
@@ -224,7 +904,8 @@ impl Client {
     /// };
     /// use std::env;
     ///
-    /// const IMAGE_PATH: &str = "./tests/images/people.jpg";
+    /// const IMAGE_PATH: &str =
+    ///     concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
     ///
     /// #[tokio::main]
     /// async fn main() {
@@ -277,42 +958,383 @@ impl Client {
             .await
     }
 
-    async fn analyze_image_(
-        &self,
-        input: ImageInput<'_>,
-        options: AnalyzeImageOptions<'_>,
-    ) -> Result<ImageAnalysis> {
-        let query_params = Self::build_query_params(&options);
-
-        let request = self.client.post(&self.url).query(&query_params);
-
-        let response = match input {
-            ImageInput::Url(image_url) => {
-                let image_url = ImageUrl {
-                    url: image_url.to_string(),
-                };
-                request.json(&image_url).send().await?
-            }
-            ImageInput::Data(image_data) => {
-                request.body(image_data.to_vec()).send().await?
-            }
-        };
-
-        Self::handle_response(response).await
-    }
-
-    async fn handle_response(
-        response: reqwest::Response,
-    ) -> Result<ImageAnalysis> {
-        if response.status().is_success() {
-            let analysis: ImageAnalysis = response.json().await?;
-            return Ok(analysis);
-        }
-        let err = response.json::<ComputerVisionErrorResponse>().await?;
-        Err(Error::API(err))
+    /// Analyze the input image, held as [`bytes::Bytes`].
+    ///
+    /// Unlike [`Client::analyze_image`], the image data is handed to the HTTP
+    /// client without being copied, which suits web frameworks that already
+    /// provide request bodies as `Bytes`.
+    ///
+    /// # Parameters
+    /// - `image_data`: Image bytes.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v32::client::{AnalyzeImageOptions, Client};
+    /// use az_analyze_image::v32::VisualFeatureTypes;
+    /// use bytes::Bytes;
+    /// use std::env;
+    ///
+    /// const IMAGE_PATH: &str =
+    ///     concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let key = env::var("CV_KEY").expect("No CV_KEY");
+    ///     let endpoint = env::var("CV_ENDPOINT").expect("No CV_ENDPOINT");
+    ///
+    ///     let client = Client::new(key, &endpoint).unwrap();
+    ///
+    ///     let image_bytes = Bytes::from(std::fs::read(IMAGE_PATH).unwrap());
+    ///
+    ///     let visual_features = vec![VisualFeatureTypes::Faces];
+    ///
+    ///     let options = AnalyzeImageOptions {
+    ///         visual_features: Some(&visual_features),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let analysis = client
+    ///         .analyze_image_bytes(image_bytes, options)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(analysis.faces.expect("no faces").len() == 2);
+    /// }
+    /// ```
+    pub async fn analyze_image_bytes(
+        &self,
+        image_data: bytes::Bytes,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysis> {
+        self.analyze_image_(ImageInput::Bytes(image_data), options)
+            .await
+    }
+
+    /// Analyze the image embedded in a base64 data URI, e.g.
+    /// `data:image/png;base64,...` from a web page or the clipboard.
+    ///
+    /// The payload is decoded and uploaded as binary image data. Returns
+    /// [`ValidationError::InvalidDataUri`] if `uri` is not a base64-encoded
+    /// data URI of an image format supported by the API, the decoded payload
+    /// is not an image of that format, or it exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// Requires the `data-uri` feature.
+    ///
+    /// # Parameters
+    /// - `uri`: Data URI of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "data-uri")]
+    pub async fn analyze_data_uri(
+        &self,
+        uri: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysis> {
+        let image_data = crate::common::data_uri::decode(uri)
+            .map_err(ValidationError::InvalidDataUri)?;
+        if image_data.len() > MAX_IMAGE_SIZE {
+            return Err(ValidationError::InvalidDataUri(
+                "image exceeds the maximum image size",
+            )
+            .into());
+        }
+
+        self.analyze_image_(ImageInput::Bytes(image_data.into()), options)
+            .await
+    }
+
+    /// Analyze the input image, downscaling it first if it exceeds
+    /// [`MAX_IMAGE_SIZE`] (4 MiB).
+    ///
+    /// Images within the limit are sent unchanged. Larger images are re-encoded
+    /// as JPEG and downscaled, preserving their aspect ratio, until they fit.
+    /// Returns [`ValidationError::ImageTooLarge`] if the image cannot fit
+    /// while staying above the minimum dimensions of 50 x 50 pixels.
+    /// Returns [`ValidationError::ImageTooSmall`] if the image is not above
+    /// those dimensions to begin with.
+    ///
+    /// Requires the `image` feature, and must be called within a Tokio
+    /// runtime, on whose blocking thread pool images are downscaled.
+    ///
+    /// # Parameters
+    /// - `image_data`: Image bytes.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "image")]
+    pub async fn analyze_image_autoresize(
+        &self,
+        image_data: &[u8],
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysis> {
+        use crate::common::{resize, MIN_DIMENSION};
+
+        let (width, height) = resize::dimensions(image_data)?;
+        if width <= MIN_DIMENSION || height <= MIN_DIMENSION {
+            return Err(ValidationError::ImageTooSmall { width, height }.into());
+        }
+
+        let image_data =
+            resize::fit_to_size_blocking(image_data, MAX_IMAGE_SIZE)
+                .await?
+                .ok_or(ValidationError::ImageTooLarge)?;
+
+        self.analyze_image_(ImageInput::Data(&image_data), options)
+            .await
+    }
+
+    /// Analyze the image at `path`, streaming it from disk rather than reading
+    /// the whole file into memory first.
+    ///
+    /// Peak memory use does not grow with the size of the file, which makes
+    /// this method suitable for large images, e.g. in batch document
+    /// processing. Returns [`ValidationError::FileTooLarge`] without sending a
+    /// request if the file exceeds [`MAX_IMAGE_SIZE`]. The size of the file is
+    /// sent as the `Content-Length` of the request, so no more than that is
+    /// uploaded even if the file grows in the meantime.
+    ///
+    /// Requires the `fs` feature.
+    ///
+    /// # Parameters
+    /// - `path`: Path of an image file.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "fs")]
+    pub async fn analyze_image_file_async(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysis> {
+        match file::open(path.as_ref(), MAX_IMAGE_SIZE as u64).await? {
+            FileBody::Stream(body, length) => {
+                self.analyze_image_(ImageInput::Stream(body, length), options)
+                    .await
+            }
+            FileBody::TooLarge(length) => {
+                Err(ValidationError::FileTooLarge(length).into())
+            }
+        }
+    }
+
+    async fn analyze_image_(
+        &self,
+        input: ImageInput<'_>,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysis> {
+        let description_exclude = options.effective_exclusions();
+        let request_id = self.generate_request_id();
+        let response = self
+            .send_request(input, options, request_id.as_deref())
+            .await?;
+
+        Self::handle_response(
+            response,
+            request_id.as_deref(),
+            description_exclude,
+        )
+        .await
+    }
+
+    fn generate_request_id(&self) -> Option<String> {
+        self.request_id_generator.map(|generate| generate())
+    }
+
+    async fn send_request(
+        &self,
+        input: ImageInput<'_>,
+        options: AnalyzeImageOptions<'_>,
+        request_id: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        self.validate_parameters(&options)?;
+
+        let query_params = Self::build_query_params(&options);
+
+        let mut request = self.client.post(&self.url).query(&query_params);
+
+        if let Some(request_id) = request_id {
+            request = request.header(CLIENT_REQUEST_ID, request_id);
+        }
+
+        // The content type depends on the kind of input, so it is set per
+        // request rather than as a default header: URLs are sent as
+        // `application/json` and raw image data as `application/octet-stream`.
+        // See `Client::with_content_type`.
+        let request = match input {
+            ImageInput::Url(image_url) => {
+                let image_url = ImageUrl {
+                    url: image_url.to_string(),
+                };
+                // Serializing a string field cannot fail.
+                let body = serde_json::to_vec(&image_url)
+                    .expect("image URL serialization failed");
+                self.content_type(request, JSON).body(body)
+            }
+            ImageInput::Data(image_data) => self.data_body(
+                self.content_type(request, OCTET_STREAM),
+                image_data,
+            ),
+            ImageInput::Bytes(image_data) => {
+                self.content_type(request, OCTET_STREAM).body(image_data)
+            }
+            #[cfg(feature = "fs")]
+            ImageInput::Stream(body, length) => self
+                .content_type(request, OCTET_STREAM)
+                .header(reqwest::header::CONTENT_LENGTH, length)
+                .body(body),
+        };
+
+        Ok(request.send().await?)
+    }
+
+    /// Sets the `Content-Type` of `request` to `content_type`, unless
+    /// disabled with [`Client::with_content_type`].
+    fn content_type(
+        &self,
+        request: reqwest::RequestBuilder,
+        content_type: &'static str,
+    ) -> reqwest::RequestBuilder {
+        if self.set_content_type {
+            request.header(reqwest::header::CONTENT_TYPE, content_type)
+        } else {
+            request
+        }
+    }
+
+    fn validate_parameters(
+        &self,
+        options: &AnalyzeImageOptions,
+    ) -> std::result::Result<(), ValidationError> {
+        // Empty values are not sent, so the API defaults to English.
+        let Some(language) =
+            options.language.filter(|language| !language.is_empty())
+        else {
+            return Ok(());
+        };
+        if language.eq_ignore_ascii_case(ENGLISH) {
+            return Ok(());
+        }
+
+        let english_only = options.visual_features.and_then(|features| {
+            features
+                .iter()
+                .find(|feature| ENGLISH_ONLY_FEATURES.contains(feature))
+        });
+        if let Some(feature) = english_only {
+            return Err(ValidationError::FeatureLanguageConflict {
+                feature: feature.clone(),
+                language: language.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn data_body(
+        &self,
+        request: reqwest::RequestBuilder,
+        image_data: &[u8],
+    ) -> reqwest::RequestBuilder {
+        #[cfg(feature = "compression")]
+        if self
+            .compression_threshold
+            .is_some_and(|threshold| image_data.len() > threshold)
+        {
+            return request
+                .header(
+                    reqwest::header::CONTENT_ENCODING,
+                    crate::common::compression::GZIP,
+                )
+                .body(crate::common::compression::gzip(image_data));
+        }
+
+        request.body(image_data.to_vec())
+    }
+
+    /// Returns the result of `response`, or its error, carrying the client
+    /// request ID echoed by the API or else the one sent, `request_id`.
+    ///
+    /// With the `tracing` feature, a successful result is logged together with
+    /// `description_exclude`, the domain models excluded from its description.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn handle_response(
+        response: reqwest::Response,
+        request_id: Option<&str>,
+        description_exclude: &[DescriptionExclude],
+    ) -> Result<ImageAnalysis> {
+        let client_request_id =
+            request_id::echoed(response.headers(), request_id);
+
+        // Some partial failures are reported with a success status.
+        if response.status().is_success() {
+            let body: SuccessBody<ImageAnalysis, ComputerVisionErrorResponse> =
+                Self::json(response).await?;
+
+            return match body {
+                SuccessBody::Result(analysis) => {
+                    #[cfg(feature = "tracing")]
+                    if !description_exclude.is_empty() {
+                        let captions: Vec<&str> = analysis
+                            .description
+                            .iter()
+                            .flat_map(|description| &description.captions)
+                            .map(|caption| caption.text.as_str())
+                            .collect();
+                        tracing::debug!(
+                            ?description_exclude,
+                            ?captions,
+                            "applied description exclusions"
+                        );
+                    }
+                    Ok(analysis)
+                }
+                SuccessBody::Error(err) => {
+                    Err(Error::API(ComputerVisionErrorResponse {
+                        client_request_id,
+                        ..err
+                    }))
+                }
+            };
+        }
+
+        // The body of an error response is parsed leniently, so that the
+        // status is reported even if the body is malformed.
+        let status = response.status();
+        match error_body::from_response(response).await {
+            ErrorBody::Parsed(err) => {
+                Err(Error::API(ComputerVisionErrorResponse {
+                    client_request_id,
+                    ..err
+                }))
+            }
+            ErrorBody::Message(message) => Err(Error::Http {
+                status,
+                message,
+                client_request_id,
+            }),
+        }
+    }
+
+    /// Deserializes the JSON body of `response`. The body is parsed with
+    /// `simd-json` if the `simd-json` feature is enabled.
+    async fn json<T: serde::de::DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T> {
+        let body = response.bytes().await?;
+
+        #[cfg(feature = "simd-json")]
+        {
+            // Reuses the buffer of `body`, which is not shared.
+            let mut body = Vec::from(body);
+            simd_json::serde::from_slice(&mut body)
+                .map_err(|err| Error::Json(serde::de::Error::custom(err)))
+        }
+
+        #[cfg(not(feature = "simd-json"))]
+        serde_json::from_slice(&body).map_err(Error::Json)
     }
 
     // POST {Endpoint}/vision/v3.2/analyze?visualFeatures={visualFeatures}&details={details}&language={language}&descriptionExclude={descriptionExclude}&model-version={model-version}
+    /// Returns the query parameters for `options`, sorted by name, so that
+    /// equal options always yield the same query string, e.g. for caching
+    /// responses keyed on it.
     fn build_query_params<'a>(
         options: &AnalyzeImageOptions,
     ) -> Vec<(&'a str, String)> {
@@ -369,15 +1391,38 @@ impl Client {
             }
         }
 
+        query_params.sort_by_key(|&(name, _)| name);
+
         query_params
     }
 
-    fn create_http_client(key: Secret) -> Result<reqwest::Client> {
+    fn validate_endpoint(
+        endpoint: &str,
+    ) -> std::result::Result<(), ValidationError> {
+        let has_scheme =
+            endpoint.starts_with("https://") || endpoint.starts_with("http://");
+        let has_host = reqwest::Url::parse(endpoint)
+            .is_ok_and(|url| url.host_str().is_some_and(|h| !h.is_empty()));
+
+        if has_scheme && has_host {
+            return Ok(());
+        }
+        Err(ValidationError::InvalidEndpoint(endpoint.to_string()))
+    }
+
+    fn create_http_client(
+        key: Secret,
+        http_version: HttpVersion,
+    ) -> Result<reqwest::Client> {
         let headers = Self::create_headers(&key)?;
+        let builder = reqwest::Client::builder().default_headers(headers);
 
-        Ok(reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?)
+        // Large results, e.g. OCR of dense documents, are requested
+        // compressed and decompressed by reqwest.
+        #[cfg(all(feature = "decompression", not(target_arch = "wasm32")))]
+        let builder = builder.gzip(true).brotli(true);
+
+        Ok(http_version.configure(builder).build()?)
     }
 
     fn create_headers(
@@ -386,16 +1431,10 @@ impl Client {
         let mut headers = reqwest::header::HeaderMap::new();
 
         let mut header_key =
-            reqwest::header::HeaderValue::from_str(key.value())?;
+            key.expose_for(reqwest::header::HeaderValue::from_str)?;
         header_key.set_sensitive(true);
 
         headers.insert("Ocp-Apim-Subscription-Key", header_key);
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static(
-                "application/octet-stream",
-            ),
-        );
 
         Ok(headers)
     }
@@ -404,11 +1443,21 @@ impl Client {
 enum ImageInput<'a> {
     Url(&'a str),
     Data(&'a [u8]),
+    Bytes(bytes::Bytes),
+    /// A streaming body and its length in bytes.
+    #[cfg(feature = "fs")]
+    Stream(reqwest::Body, u64),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "image")]
+    use crate::common::images;
+    use crate::testing::mock;
+    use crate::v32::testing::{mock_client, options, TAGS};
+
+    const MOCK_ENDPOINT: &str = "https://mock.cognitiveservices.azure.com/";
 
     fn header_value_to_str(value: &reqwest::header::HeaderValue) -> &str {
         value.to_str().unwrap_or("")
@@ -423,15 +1472,10 @@ mod tests {
             header_value_to_str(
                 headers.get("Ocp-Apim-Subscription-Key").unwrap()
             ),
-            key.value()
+            "dummy_key"
         );
 
-        assert_eq!(
-            header_value_to_str(
-                headers.get(reqwest::header::CONTENT_TYPE).unwrap()
-            ),
-            "application/octet-stream"
-        );
+        assert!(headers.get(reqwest::header::CONTENT_TYPE).is_none());
     }
 
     #[test]
@@ -446,8 +1490,10 @@ mod tests {
 
     #[test]
     fn test_create_http_client() {
-        let result =
-            Client::create_http_client(Secret::new("dummy_key".into()));
+        let result = Client::create_http_client(
+            Secret::new("dummy_key".into()),
+            HttpVersion::Auto,
+        );
 
         assert!(result.is_ok());
     }
@@ -497,11 +1543,7 @@ mod tests {
 
     #[test]
     fn test_build_query_params_with_visual_features() {
-        let visual_features = vec![VisualFeatureTypes::Description];
-        let options = AnalyzeImageOptions {
-            visual_features: Some(&visual_features),
-            ..Default::default()
-        };
+        let options = options(&[VisualFeatureTypes::Description]);
         let query_params = Client::build_query_params(&options);
 
         assert_eq!(
@@ -586,4 +1628,1050 @@ mod tests {
         assert!(query_params
             .contains(&("descriptionExclude", "Landmarks".to_string())));
     }
+
+    #[tokio::test]
+    async fn test_analyze_image_bytes() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let image_data = bytes::Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let analysis = client
+            .analyze_image_bytes(image_data.clone(), options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.body, image_data);
+        assert!(analysis.tags.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key() {
+        let (endpoint, server) =
+            mock::serve(vec![(200, TAGS), (200, TAGS)]).await;
+        let client = Client::new("old_key".into(), &endpoint).unwrap();
+        let rotated = client.rotate_key("new_key".into()).unwrap();
+
+        let options = AnalyzeImageOptions::default();
+
+        rotated
+            .analyze_image_url("https://example.com/image.jpg", options.clone())
+            .await
+            .unwrap();
+        client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert_eq!(
+            requests[0].header("ocp-apim-subscription-key"),
+            Some("new_key")
+        );
+        assert_eq!(
+            requests[1].header("ocp-apim-subscription-key"),
+            Some("old_key")
+        );
+    }
+
+    #[test]
+    fn test_rotate_key_invalid_key() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        assert!(matches!(
+            client.rotate_key("invalid\nkey".into()),
+            Err(Error::Validation(ValidationError::InvalidHeaderValue(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_full_headers() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            200,
+            &[("x-custom-header", "custom-value")],
+            TAGS,
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let (analysis, headers) = client
+            .analyze_image_url_full(
+                "https://example.com/image.jpg",
+                AnalyzeImageOptions::default(),
+            )
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert!(analysis.tags.is_some());
+        assert_eq!(headers.get("x-custom-header").unwrap(), "custom-value");
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_image_file_async() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let path =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
+        let options = AnalyzeImageOptions::default();
+
+        client
+            .analyze_image_file_async(path, options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(request.body, std::fs::read(path).unwrap());
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_image_file_async_missing_file() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = AnalyzeImageOptions::default();
+
+        let result = client
+            .analyze_image_file_async("does/not/exist.jpg", options)
+            .await;
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_image_file_async_too_large() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        // Sparse, so no disk space is used.
+        let path = std::env::temp_dir().join(format!(
+            "az_analyze_image_v32_too_large_{}",
+            std::process::id()
+        ));
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_len(MAX_IMAGE_SIZE as u64 + 1).unwrap();
+
+        let result = client.analyze_image_file_async(&path, options).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::FileTooLarge(size)))
+                if size == MAX_IMAGE_SIZE as u64 + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_timed() {
+        let delay = std::time::Duration::from_millis(20);
+        let (endpoint, server) =
+            mock::serve_once_delayed(200, TAGS, delay).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let (_, elapsed) = client
+            .analyze_image_url_timed("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert!(elapsed >= delay);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_urls_model_versions() {
+        const NEWER_MODEL: &str = r#"{
+            "metadata": {"format": "Jpeg", "height": 500, "width": 500},
+            "modelVersion": "2023-05-01",
+            "requestId": "00000000-0000-0000-0000-000000000000",
+            "tags": []
+        }"#;
+        let (endpoint, server) = mock::serve(vec![
+            (200, TAGS),
+            (200, NEWER_MODEL),
+            (
+                400,
+                include_str!(
+                    "../../tests/samples/v32/error_invalid_image_size.json"
+                ),
+            ),
+        ])
+        .await;
+        let client = mock_client(&endpoint);
+
+        let visual_features = vec![VisualFeatureTypes::Tags];
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&visual_features),
+            model_version: Some("latest"),
+            ..Default::default()
+        };
+
+        let report = client
+            .analyze_image_urls(
+                &[
+                    "https://example.com/1.jpg",
+                    "https://example.com/2.jpg",
+                    "https://example.com/3.jpg",
+                ],
+                options,
+            )
+            .await;
+        server.await.unwrap();
+
+        assert_eq!(report.results.len(), 3);
+        assert!(matches!(report.results[2], Err(Error::API(_))));
+        assert_eq!(
+            report.model_versions(),
+            HashSet::from(["2021-05-01", "2023-05-01"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_traced() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let visual_features =
+            vec![VisualFeatureTypes::Tags, VisualFeatureTypes::Color];
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&visual_features),
+            model_version: Some("2021-05-01"),
+            description_exclude: Some(&[DescriptionExclude::Celebrities]),
+            ..Default::default()
+        };
+
+        let traced = client
+            .analyze_image_url_traced("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert_eq!(traced.requested_features, visual_features);
+        assert_eq!(traced.model_version.as_deref(), Some("2021-05-01"));
+        assert!(traced.result.tags.is_some());
+    }
+
+    #[test]
+    fn test_effective_exclusions() {
+        let description_exclude = vec![
+            DescriptionExclude::Celebrities,
+            DescriptionExclude::Landmarks,
+        ];
+        let options = AnalyzeImageOptions {
+            description_exclude: Some(&description_exclude),
+            ..Default::default()
+        };
+
+        assert_eq!(options.effective_exclusions(), description_exclude);
+        assert!(AnalyzeImageOptions::default()
+            .effective_exclusions()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generator() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint)
+            .with_request_id_generator(|| "request-1".to_string());
+
+        let options = AnalyzeImageOptions::default();
+
+        let traced = client
+            .analyze_image_url_traced("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header(CLIENT_REQUEST_ID), Some("request-1"));
+        assert_eq!(traced.client_request_id.as_deref(), Some("request-1"));
+    }
+
+    #[tokio::test]
+    async fn test_error_carries_client_request_id() {
+        let (endpoint, server) = mock::serve_once(
+            400,
+            include_str!(
+                "../../tests/samples/v32/error_invalid_image_size.json"
+            ),
+        )
+        .await;
+        let client = mock_client(&endpoint)
+            .with_request_id_generator(|| "request-1".to_string());
+
+        let options = AnalyzeImageOptions::default();
+
+        let err = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap_err();
+        server.await.unwrap();
+
+        assert!(matches!(err, Error::API(_)));
+        assert_eq!(err.client_request_id(), Some("request-1"));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[tokio::test]
+    async fn test_default_uuid_request_ids() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let options = AnalyzeImageOptions::default();
+
+        let traced = client
+            .analyze_image_url_traced("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        let request_id = traced.client_request_id.unwrap();
+        assert!(uuid::Uuid::parse_str(&request_id).is_ok());
+        assert_eq!(
+            request.header(CLIENT_REQUEST_ID),
+            Some(request_id.as_str())
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_image_autoresize_too_small() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = AnalyzeImageOptions::default();
+
+        let result = client
+            .analyze_image_autoresize(&images::png(49, 49), options)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::ImageTooSmall {
+                width: 49,
+                height: 49
+            }))
+        ));
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_image_autoresize_small_enough() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+        let options = AnalyzeImageOptions::default();
+
+        let image_data = images::png(51, 51);
+        client
+            .analyze_image_autoresize(&image_data, options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.body, image_data);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_sas_url() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        const SAS_URL: &str = "https://account.blob.core.windows.net/images/caf%C3%A9%20photo.jpg?sv=2022-11-02&ss=b&srt=o&sp=r&se=2030-01-01T00:00:00Z&sig=abc%2Bdef%2F%3D";
+        let options = AnalyzeImageOptions::default();
+
+        client.analyze_image_url(SAS_URL, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        let body: ImageUrl = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body.url, SAS_URL);
+    }
+
+    #[tokio::test]
+    async fn test_content_type_url_request() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-type"), Some("application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_content_type_data_request() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        // A default Content-Type of a custom HTTP client must not leak into
+        // the request alongside the per-request one.
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("text/plain"),
+        );
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        let client = Client::with_http_client(http, &endpoint).unwrap();
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        client
+            .analyze_image(&[0xFF, 0xD8, 0xFF, 0xE0], options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(request.head.matches("content-type").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_without_content_type() {
+        let (endpoint, server) =
+            mock::serve(vec![(200, TAGS), (200, TAGS)]).await;
+        let client =
+            Client::with_http_client(reqwest::Client::new(), &endpoint)
+                .unwrap()
+                .with_content_type(false);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        client
+            .analyze_image_url("https://example.com/image.jpg", options.clone())
+            .await
+            .unwrap();
+        client
+            .analyze_image(&[0xFF, 0xD8, 0xFF, 0xE0], options)
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert!(requests
+            .iter()
+            .all(|request| request.header("content-type").is_none()));
+        assert_eq!(
+            requests[0].body,
+            br#"{"url":"https://example.com/image.jpg"}"#
+        );
+    }
+
+    #[test]
+    fn test_create_headers_no_content_type() {
+        let headers =
+            Client::create_headers(&Secret::new("dummy_key".into())).unwrap();
+
+        assert!(headers.get(reqwest::header::CONTENT_TYPE).is_none());
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_client_is_send_sync() {
+        assert_send_sync::<Client>();
+    }
+
+    #[test]
+    fn test_analyze_futures_are_send() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        assert_send(&client.analyze_image_url("", Default::default()));
+        assert_send(&client.analyze_image(&[], Default::default()));
+        assert_send(
+            &client
+                .analyze_image_bytes(bytes::Bytes::new(), Default::default()),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_in_spawned_task() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let task = tokio::spawn(async move {
+            let options = options(&[VisualFeatureTypes::Tags]);
+
+            client
+                .analyze_image_url("https://example.com/image.jpg", options)
+                .await
+        });
+
+        assert!(task.await.unwrap().is_ok());
+        server.await.unwrap();
+    }
+
+    const INVALID_MODEL: &str = r#"{
+        "error": {
+            "code": "InvalidArgument",
+            "innererror": {
+                "code": "InvalidModel",
+                "message": "The model version is invalid."
+            },
+            "message": "The model version is invalid."
+        }
+    }"#;
+
+    const INVALID_IMAGE_URL: &str = r#"{
+        "error": {
+            "code": "InvalidArgument",
+            "innererror": {
+                "code": "InvalidImageUrl",
+                "message": "Image URL is badly formatted."
+            },
+            "message": "Image URL is badly formatted."
+        }
+    }"#;
+
+    #[test]
+    fn test_error_is_feature_unsupported() {
+        let error = |code: &str| {
+            Error::API(
+                serde_json::from_str(&format!(
+                    r#"{{
+                        "error": {{
+                            "code": "InvalidRequest",
+                            "innererror": {{
+                                "code": "{}",
+                                "message": "message"
+                            }},
+                            "message": "message"
+                        }}
+                    }}"#,
+                    code
+                ))
+                .unwrap(),
+            )
+        };
+
+        assert!(error("UnsupportedFeature").is_feature_unsupported());
+        assert!(error("NotSupportedVisualFeature").is_feature_unsupported());
+        assert!(!error("InvalidImageUrl").is_feature_unsupported());
+        assert!(!Error::FeatureNotReturned(VisualFeatureTypes::Description)
+            .is_feature_unsupported());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_with_fallback() {
+        let (endpoint, server) =
+            mock::serve(vec![(400, INVALID_MODEL), (200, TAGS)]).await;
+        let client = mock_client(&endpoint);
+
+        let analysis = client
+            .analyze_image_url_with_fallback(
+                "https://example.com/image.jpg",
+                AnalyzeImageOptions::default(),
+                &["2023-05-01", "latest"],
+            )
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert!(analysis.tags.is_some());
+        assert!(requests[0].head.contains("model-version=2023-05-01"));
+        assert!(requests[1].head.contains("model-version=latest"));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_with_fallback_other_error() {
+        let (endpoint, server) =
+            mock::serve(vec![(400, INVALID_IMAGE_URL)]).await;
+        let client = mock_client(&endpoint);
+
+        let result = client
+            .analyze_image_url_with_fallback(
+                "https://example.com/image.jpg",
+                AnalyzeImageOptions::default(),
+                &["2023-05-01", "latest"],
+            )
+            .await;
+        let requests = server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::API(err)) if err.error.innererror.code
+                == ComputerVisionInnerErrorCodeValue::InvalidImageUrl
+        ));
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_analyze_image_compression() {
+        use std::io::Read;
+
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint).with_compression(64);
+
+        let image_data = [0xAB; 1024];
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        client.analyze_image(&image_data, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-encoding"), Some("gzip"));
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+
+        let mut body = Vec::new();
+        flate2::read::GzDecoder::new(request.body.as_slice())
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, image_data);
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_analyze_image_compression_below_threshold() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint).with_compression(4096);
+
+        let image_data = [0xAB; 1024];
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        client.analyze_image(&image_data, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-encoding"), None);
+        assert_eq!(request.body, image_data);
+    }
+
+    #[tokio::test]
+    async fn test_describe_url() {
+        let (endpoint, server) = mock::serve_once(
+            200,
+            include_str!("../../tests/samples/v32/description.json"),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let description = client
+            .describe_url("https://example.com/image.jpg", Some("es"))
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert!(request.head.contains("visualFeatures=Description"));
+        assert!(request.head.contains("language=es"));
+        assert_eq!(
+            description.best_caption().unwrap().text,
+            "a man wearing a hat"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_url() {
+        let (endpoint, server) = mock::serve_once(
+            200,
+            include_str!("../../tests/samples/v32/no_query_parameters.json"),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let metadata = client
+            .metadata_url("https://example.com/image.png")
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert!(!request.head.contains("visualFeatures"));
+        assert_eq!(metadata.width, 1038);
+        assert_eq!(metadata.height, 692);
+        assert_eq!(metadata.format, "Png");
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_empty_error_body() {
+        let (endpoint, server) = mock::serve_once(503, "").await;
+        let client = mock_client(&endpoint);
+
+        let result = client
+            .analyze_image_url(
+                "https://example.com/image.jpg",
+                AnalyzeImageOptions::default(),
+            )
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::Http { status, message, .. })
+                if status.as_u16() == 503 && message.is_empty()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_describe_url_missing_description() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let result = client
+            .describe_url("https://example.com/image.jpg", None)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::FeatureNotReturned(VisualFeatureTypes::Description))
+        ));
+    }
+
+    #[cfg(all(feature = "compression", feature = "decompression"))]
+    #[tokio::test]
+    async fn test_analyze_image_url_gzip_response() {
+        let json = TAGS;
+        let (endpoint, server) = mock::serve_once_bytes(
+            200,
+            &[("Content-Encoding", "gzip")],
+            crate::common::compression::gzip(json.as_bytes()),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        let expected: ImageAnalysis = serde_json::from_str(json).unwrap();
+        assert!(request.header("accept-encoding").unwrap().contains("gzip"));
+        assert_eq!(result.tags.unwrap().len(), expected.tags.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_success_status_with_error_body() {
+        let (endpoint, server) = mock::serve_once(
+            200,
+            include_str!(
+                "../../tests/samples/v32/error_invalid_image_size.json"
+            ),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::API(err)) if err.code() == "InvalidRequest"
+        ));
+    }
+
+    #[cfg(feature = "decompression")]
+    #[tokio::test]
+    async fn test_analyze_image_url_invalid_gzip_response() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            200,
+            &[("Content-Encoding", "gzip")],
+            "{}",
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(result, Err(Error::Reqwest(_))));
+    }
+
+    #[cfg(feature = "data-uri")]
+    #[tokio::test]
+    async fn test_analyze_data_uri() {
+        use crate::common::data_uri::tests::PNG_DATA_URI;
+
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let result = client.analyze_data_uri(PNG_DATA_URI, options).await;
+        let request = server.await.unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(
+            request.body,
+            crate::common::data_uri::decode(PNG_DATA_URI).unwrap()
+        );
+    }
+
+    #[cfg(feature = "data-uri")]
+    #[tokio::test]
+    async fn test_analyze_data_uri_malformed() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let result = client
+            .analyze_data_uri("data:image/png;base64,not base64!", options)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidDataUri(_)))
+        ));
+    }
+
+    #[test]
+    fn test_validate_parameters_english_only_feature() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let visual_features =
+            vec![VisualFeatureTypes::Tags, VisualFeatureTypes::Objects];
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&visual_features),
+            ..Default::default()
+        };
+
+        let result = client.validate_parameters(&options.with_language("fr"));
+        assert!(matches!(
+            result,
+            Err(ValidationError::FeatureLanguageConflict {
+                feature: VisualFeatureTypes::Objects,
+                ref language,
+            }) if language == "fr"
+        ));
+
+        assert!(client
+            .validate_parameters(&options.with_language("en"))
+            .is_ok());
+        assert!(client.validate_parameters(&options).is_ok());
+    }
+
+    #[test]
+    fn test_validate_parameters_language_without_english_only_feature() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let visual_features = vec![VisualFeatureTypes::Description];
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&visual_features),
+            language: Some("fr"),
+            ..Default::default()
+        };
+
+        assert!(client.validate_parameters(&options).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_rejects_brands_in_other_language() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let visual_features = vec![VisualFeatureTypes::Brands];
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&visual_features),
+            language: Some("es"),
+            ..Default::default()
+        };
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(
+                ValidationError::FeatureLanguageConflict {
+                    feature: VisualFeatureTypes::Brands,
+                    ..
+                }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_with_http1_only() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client =
+            Client::with_http1_only("dummy_key".into(), &endpoint).unwrap();
+        assert_eq!(client.http_version, HttpVersion::Http1Only);
+
+        let options = options(&[VisualFeatureTypes::Tags]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        let request = server.await.unwrap();
+
+        assert!(result.is_ok());
+        assert!(request.head.lines().next().unwrap().ends_with("HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_with_http2_prior_knowledge() {
+        let client = Client::with_http2_prior_knowledge(
+            "dummy_key".into(),
+            MOCK_ENDPOINT,
+        )
+        .unwrap();
+        assert_eq!(client.http_version, HttpVersion::Http2PriorKnowledge);
+
+        let rotated = client.rotate_key("new_key".into()).unwrap();
+        assert_eq!(rotated.http_version, HttpVersion::Http2PriorKnowledge);
+
+        assert_eq!(
+            Client::new("dummy_key".into(), MOCK_ENDPOINT)
+                .unwrap()
+                .http_version,
+            HttpVersion::Auto
+        );
+    }
+
+    #[test]
+    fn test_new_rejects_endpoint_without_scheme() {
+        let result = Client::new(
+            "dummy_key".into(),
+            "myresource.cognitiveservices.azure.com/",
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidEndpoint(_)))
+        ));
+    }
+
+    #[test]
+    fn test_new_accepts_https_endpoint() {
+        let result = Client::new(
+            "dummy_key".into(),
+            "https://myresource.cognitiveservices.azure.com/",
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_new_rejects_endpoint_without_host() {
+        let result = Client::new("dummy_key".into(), "https:///");
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidEndpoint(_)))
+        ));
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_key() {
+        let result = Client::new("dummy\nkey".into(), MOCK_ENDPOINT);
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidHeaderValue(_)))
+        ));
+    }
+
+    #[test]
+    fn test_build_query_params_with_language_override() {
+        let options = AnalyzeImageOptions {
+            language: Some("en"),
+            ..Default::default()
+        };
+
+        let query_params =
+            Client::build_query_params(&options.with_language("de"));
+
+        assert!(query_params.contains(&("language", "de".to_string())));
+        assert!(!query_params.contains(&("language", "en".to_string())));
+        assert_eq!(options.language, Some("en"));
+    }
+
+    #[test]
+    fn test_build_query_params_all_features() {
+        let query_params =
+            Client::build_query_params(&AnalyzeImageOptions::all_features());
+
+        let (_, features) = query_params
+            .iter()
+            .find(|(name, _)| *name == "visualFeatures")
+            .unwrap();
+        let features: Vec<&str> = features.split(',').collect();
+
+        assert_eq!(features.len(), VisualFeatureTypes::ALL.len());
+        for feature in VisualFeatureTypes::ALL {
+            assert!(features.contains(&format!("{:?}", feature).as_str()));
+        }
+    }
+
+    fn query_param_names<'a>(
+        query_params: &[(&'a str, String)],
+    ) -> Vec<&'a str> {
+        query_params.iter().map(|&(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_build_query_params_order() {
+        let visual_features = vec![VisualFeatureTypes::Description];
+        let details = vec![Details::Landmarks];
+        let description_exclude = vec![DescriptionExclude::Celebrities];
+        let options = AnalyzeImageOptions {
+            description_exclude: Some(&description_exclude),
+            details: Some(&details),
+            language: Some("en"),
+            model_version: Some("latest"),
+            visual_features: Some(&visual_features),
+        };
+
+        assert_eq!(
+            query_param_names(&Client::build_query_params(&options)),
+            [
+                "descriptionExclude",
+                "details",
+                "language",
+                "model-version",
+                "visualFeatures",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_query_params_order_subsets() {
+        let visual_features = vec![VisualFeatureTypes::Tags];
+        let options = AnalyzeImageOptions {
+            visual_features: Some(&visual_features),
+            language: Some("en"),
+            ..Default::default()
+        };
+        assert_eq!(
+            query_param_names(&Client::build_query_params(&options)),
+            ["language", "visualFeatures"]
+        );
+
+        let details = vec![Details::Celebrities];
+        let options = AnalyzeImageOptions {
+            model_version: Some("latest"),
+            details: Some(&details),
+            ..Default::default()
+        };
+        assert_eq!(
+            query_param_names(&Client::build_query_params(&options)),
+            ["details", "model-version"]
+        );
+    }
 }
@@ -0,0 +1,27 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Shared scaffolding of the unit tests of the v3.2 client.
+
+use super::client::{AnalyzeImageOptions, Client};
+use super::VisualFeatureTypes;
+
+/// Response body of a `Tags` analysis.
+pub const TAGS: &str = include_str!("../../tests/samples/v32/tags.json");
+
+/// Returns a client of `endpoint`, e.g. that of a mock server.
+pub fn mock_client(endpoint: &str) -> Client {
+    Client::new("dummy_key".into(), endpoint).unwrap()
+}
+
+/// Returns options requesting `visual_features`.
+pub fn options(
+    visual_features: &[VisualFeatureTypes],
+) -> AnalyzeImageOptions<'_> {
+    AnalyzeImageOptions {
+        visual_features: Some(visual_features),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,121 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! The [`ImageAnalyzer`] trait of the v4.0 [`Client`].
+
+use super::client::{AnalyzeImageOptions, Client, Result};
+use super::ImageAnalysisResult;
+
+crate::common::analyzer::image_analyzer!(
+    ImageAnalysisResult,
+    r#"
+```
+use az_analyze_image::v40::analyzer::ImageAnalyzer;
+use az_analyze_image::v40::client::{AnalyzeImageOptions, Result};
+use az_analyze_image::v40::VisualFeature;
+
+async fn caption(
+    analyzer: &impl ImageAnalyzer,
+    image_url: &str,
+) -> Result<Option<String>> {
+    let features = [VisualFeature::Caption];
+    let options = AnalyzeImageOptions {
+        features: Some(&features),
+        ..Default::default()
+    };
+
+    let analysis = analyzer.analyze_image_url(image_url, options).await?;
+    Ok(analysis.caption_result.map(|caption| caption.text))
+}
+```
+"#
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mock;
+    use crate::v40::client::Error;
+    use crate::v40::testing::{mock_client, options};
+    use crate::v40::{ErrorResponse, VisualFeature};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    const CAPTIONS: &str =
+        include_str!("../../tests/samples/v40/captions.json");
+
+    /// A fake that answers every request with a sample and records the URLs.
+    struct FakeAnalyzer {
+        urls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ImageAnalyzer for FakeAnalyzer {
+        async fn analyze_image_url(
+            &self,
+            image_url: &str,
+            _options: AnalyzeImageOptions<'_>,
+        ) -> Result<ImageAnalysisResult> {
+            self.urls.lock().unwrap().push(image_url.to_string());
+
+            Ok(serde_json::from_str(CAPTIONS).unwrap())
+        }
+
+        async fn analyze_image(
+            &self,
+            _image_data: &[u8],
+            _options: AnalyzeImageOptions<'_>,
+        ) -> Result<ImageAnalysisResult> {
+            let err: ErrorResponse = serde_json::from_str(include_str!(
+                "../../tests/samples/v40/error_image_too_large.json"
+            ))
+            .unwrap();
+
+            Err(err.into())
+        }
+    }
+
+    /// Code under test that only depends on the trait.
+    async fn caption(
+        analyzer: &impl ImageAnalyzer,
+        image_url: &str,
+    ) -> Result<String> {
+        let options = options(&[VisualFeature::Caption]);
+
+        let analysis = analyzer.analyze_image_url(image_url, options).await?;
+        Ok(analysis.require_caption()?.text.clone())
+    }
+
+    #[tokio::test]
+    async fn test_fake_analyzer() {
+        let fake = FakeAnalyzer {
+            urls: Mutex::new(Vec::new()),
+        };
+
+        let text = caption(&fake, "https://example.com/image.jpg").await;
+        let result = fake
+            .analyze_image(b"image data", AnalyzeImageOptions::default())
+            .await;
+
+        assert!(!text.unwrap().is_empty());
+        assert_eq!(
+            *fake.urls.lock().unwrap(),
+            ["https://example.com/image.jpg"]
+        );
+        assert!(matches!(result, Err(Error::API(_))));
+    }
+
+    #[tokio::test]
+    async fn test_client_analyzer() {
+        let (endpoint, server) = mock::serve_once(200, CAPTIONS).await;
+
+        let text =
+            caption(&mock_client(&endpoint), "https://example.com/image.jpg")
+                .await;
+        server.await.unwrap();
+
+        assert!(!text.unwrap().is_empty());
+    }
+}
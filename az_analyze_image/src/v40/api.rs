@@ -3,11 +3,24 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0 OR MIT
 */
 
-use super::{Number, PixelCount};
+use super::client::{Error, Result};
+use super::confidence::cmp_confidence;
+use super::{
+    coco, geometry, tags, ApiError, CommonTag, NormRect, Number,
+    ParseFeatureError, PixelCount, PixelRect, TagAggregation,
+};
+use crate::common::error::is_retryable_code;
+use crate::common::{MAX_DIMENSION, MIN_DIMENSION};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess,
+    Unexpected, Visitor,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 
 /// An object describing adult content match.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AdultMatch {
     /// A value indicating the confidence level of matched adult content.
     pub confidence: Number,
@@ -19,7 +32,7 @@ pub struct AdultMatch {
 
 /// An object describing whether the image contains adult-oriented content
 /// and/or is racy.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AdultResult {
     /// An object describing adult content match.
     pub adult: AdultMatch,
@@ -32,7 +45,7 @@ pub struct AdultResult {
 }
 
 /// A bounding box for an area inside an image.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BoundingBox {
     /// Height measured from the top-left point of the area, in pixels.
     pub h: PixelCount,
@@ -47,8 +60,43 @@ pub struct BoundingBox {
     pub y: PixelCount,
 }
 
+impl From<&BoundingBox> for PixelRect {
+    fn from(bounding_box: &BoundingBox) -> Self {
+        PixelRect {
+            x: bounding_box.x,
+            y: bounding_box.y,
+            w: bounding_box.w,
+            h: bounding_box.h,
+        }
+    }
+}
+
+impl BoundingBox {
+    /// Returns the part of the box within an image of the given size, e.g. the
+    /// size in [`ImageAnalysisResult::metadata`], as the API occasionally
+    /// returns boxes extending slightly beyond the image.
+    pub fn clamp_to(
+        &self,
+        width: PixelCount,
+        height: PixelCount,
+    ) -> BoundingBox {
+        PixelRect::from(self).clamp_to(width, height).into()
+    }
+}
+
+impl From<PixelRect> for BoundingBox {
+    fn from(rect: PixelRect) -> Self {
+        BoundingBox {
+            h: rect.h,
+            w: rect.w,
+            x: rect.x,
+            y: rect.y,
+        }
+    }
+}
+
 /// A brief description of what the image depicts.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CaptionResult {
     /// The level of confidence the service has in the caption.
     pub confidence: Number,
@@ -59,7 +107,7 @@ pub struct CaptionResult {
 
 /// A region identified for smart cropping. There will be one region returned
 /// for each requested aspect ratio.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CropRegion {
     /// The aspect ratio of the crop region.
     #[serde(rename = "aspectRatio")]
@@ -71,7 +119,7 @@ pub struct CropRegion {
 }
 
 /// A brief description of what the image depicts.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DenseCaption {
     /// A bounding box for an area inside an image.
     #[serde(rename = "boundingBox")]
@@ -84,31 +132,78 @@ pub struct DenseCaption {
     pub text: String,
 }
 
+impl DenseCaption {
+    /// Returns `true` if the caption covers the whole image of the given
+    /// metadata, as the first dense caption typically does.
+    pub fn is_full_image(&self, meta: &ImageMetadataApiModel) -> bool {
+        let bounding_box = &self.bounding_box;
+
+        bounding_box.x == 0
+            && bounding_box.y == 0
+            && bounding_box.w == meta.width
+            && bounding_box.h == meta.height
+    }
+}
+
 /// A list of captions.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DenseCaptionsResult {
     /// A list of captions.
     pub values: Vec<DenseCaption>,
 }
 
+impl DenseCaptionsResult {
+    /// Returns the captions sorted by confidence (descending).
+    ///
+    /// The API does not return the captions in confidence order: the first
+    /// caption always describes the whole image.
+    pub fn sorted_by_confidence(&self) -> Vec<&DenseCaption> {
+        let mut captions: Vec<&DenseCaption> = self.values.iter().collect();
+        captions.sort_by(|a, b| cmp_confidence(&b.confidence, &a.confidence));
+        captions
+    }
+
+    /// Returns the `n` most confident captions, most confident first.
+    pub fn top(&self, n: usize) -> Vec<&DenseCaption> {
+        let mut captions = self.sorted_by_confidence();
+        captions.truncate(n);
+        captions
+    }
+
+    /// Returns the caption texts sorted by confidence (descending).
+    pub fn texts(&self) -> Vec<&str> {
+        self.sorted_by_confidence()
+            .into_iter()
+            .map(|caption| caption.text.as_str())
+            .collect()
+    }
+}
+
 /// Describes a detected object in an image.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DetectedObject {
     /// A bounding box for an area inside an image.
     #[serde(rename = "boundingBox")]
     pub bounding_box: BoundingBox,
 
-    /// Id of the detected object.
-    // pub id: String,
-    // TODO: When is this ever provided?
+    /// Id of the detected object. Provided by custom models, but not by the
+    /// `objects` feature.
     pub id: Option<String>,
 
     /// Classification confidences of the detected object.
     pub tags: Vec<Tag>,
 }
 
+impl DetectedObject {
+    /// Returns the id of the object, or `idx`, e.g. its index in
+    /// [`ObjectsResult::values`], if the API did not provide one.
+    pub fn id_or_index(&self, idx: usize) -> String {
+        self.id.clone().unwrap_or_else(|| idx.to_string())
+    }
+}
+
 /// A person detected in an image.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DetectedPerson {
     /// A bounding box for an area inside an image.
     #[serde(rename = "boundingBox")]
@@ -121,7 +216,7 @@ pub struct DetectedPerson {
 
 /// A content line object consisting of an adjacent sequence of content
 /// elements, such as words and selection marks.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DocumentLine {
     /// Bounding box of the line.
     #[serde(rename = "boundingBox")]
@@ -131,11 +226,25 @@ pub struct DocumentLine {
     pub content: String,
 
     /// Location of the line in the reading order concatenated content.
+    #[serde(default)]
     pub spans: Vec<DocumentSpan>,
 }
 
+impl DocumentLine {
+    /// Returns the four corners of the bounding polygon as `(x, y)` pairs, or
+    /// `None` if the bounding box does not consist of exactly 8 numbers.
+    pub fn polygon(&self) -> Option<[(Number, Number); 4]> {
+        match self.bounding_box.as_slice() {
+            &[x1, y1, x2, y2, x3, y3, x4, y4] => {
+                Some([(x1, y1), (x2, y2), (x3, y3), (x4, y4)])
+            }
+            _ => None,
+        }
+    }
+}
+
 /// The content and layout elements extracted from a page from the input.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DocumentPage {
     /// The general orientation of the content in clockwise direction, measured
     /// in degrees between (-180, 180].
@@ -146,6 +255,7 @@ pub struct DocumentPage {
 
     /// Extracted lines from the page, potentially containing both textual and
     /// visual elements.
+    #[serde(default)]
     pub lines: Vec<DocumentLine>,
 
     /// 1-based page number in the input document.
@@ -153,18 +263,71 @@ pub struct DocumentPage {
     pub page_number: usize,
 
     /// Location of the page in the reading order concatenated content.
+    #[serde(default)]
     pub spans: Vec<DocumentSpan>,
 
     /// The width of the image/PDF in pixels/inches, respectively.
     pub width: Number,
 
     /// Extracted words from the page.
+    #[serde(default)]
     pub words: Vec<DocumentWord>,
 }
 
+impl DocumentPage {
+    /// Returns the general orientation of the content in clockwise direction,
+    /// in degrees, normalized to (-180, 180].
+    pub fn skew_degrees(&self) -> Number {
+        normalize_degrees(self.angle)
+    }
+
+    /// Returns the clockwise rotation, in degrees within (-180, 180], that
+    /// straightens the content, e.g. before cropping.
+    pub fn deskew_degrees(&self) -> Number {
+        normalize_degrees(-self.angle)
+    }
+
+    /// Returns `true` if the content is rotated by more than `tolerance`
+    /// degrees in either direction.
+    pub fn is_rotated(&self, tolerance: Number) -> bool {
+        self.skew_degrees().abs() > tolerance
+    }
+
+    /// Returns the `(width, height)` of the page in pixels, or `None` if they
+    /// do not look like pixel counts, e.g. the dimensions in inches of a PDF
+    /// page.
+    ///
+    /// The dimensions look like pixel counts if both are whole numbers greater
+    /// than 50 and less than 16,000, the bounds the API imposes on images.
+    pub fn pixel_dimensions(&self) -> Option<(PixelCount, PixelCount)> {
+        let to_pixels = |value: Number| {
+            (value.fract() == 0.0
+                && value > Number::from(MIN_DIMENSION)
+                && value < Number::from(MAX_DIMENSION))
+            .then_some(value as PixelCount)
+        };
+
+        Some((to_pixels(self.width)?, to_pixels(self.height)?))
+    }
+}
+
+/// Normalizes an angle in degrees to (-180, 180].
+pub fn normalize_degrees(degrees: Number) -> Number {
+    if degrees > -180.0 && degrees <= 180.0 {
+        return degrees;
+    }
+
+    let degrees = degrees.rem_euclid(360.0);
+    if degrees > 180.0 {
+        degrees - 360.0
+    } else {
+        degrees
+    }
+}
+
 /// Contiguous region of the concatenated content property, specified as an
 /// offset and length.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DocumentSpan {
     /// Number of characters in the content represented by the span.
     pub length: usize,
@@ -174,7 +337,7 @@ pub struct DocumentSpan {
 }
 
 /// An object representing observed text styles.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DocumentStyle {
     /// Confidence of correctly identifying the style.
     pub confidence: Number,
@@ -185,13 +348,14 @@ pub struct DocumentStyle {
 
     /// Location of the text elements in the concatenated content the style
     /// applies to.
+    #[serde(default)]
     pub spans: Vec<DocumentSpan>,
 }
 
 /// A word object consisting of a contiguous sequence of characters. For
 /// non-space delimited languages, such as Chinese, Japanese, and Korean, each
 /// character is represented as its own word.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DocumentWord {
     /// Bounding box of the word.
     #[serde(rename = "boundingBox")]
@@ -209,11 +373,64 @@ pub struct DocumentWord {
 }
 
 /// Response returned when an error occurs.
-#[derive(Debug, Deserialize, Serialize, thiserror::Error)]
-#[error("{:#?}", error)] // TODO.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ErrorResponse {
     /// Error info.
     pub error: ErrorResponseDetails,
+
+    /// The delay requested by the `Retry-After` header of the response, if
+    /// any, e.g. when rate limited. Not part of the body.
+    #[serde(skip)]
+    pub retry_after: Option<std::time::Duration>,
+
+    /// The client request ID of the failed request, as echoed by the API or
+    /// else as sent, if any. Not part of the body.
+    #[serde(skip)]
+    pub client_request_id: Option<String>,
+}
+
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+/// The [`source`](std::error::Error::source) of an `ErrorResponse` is its
+/// inner error, if any, so that walking the error chain reveals the codes and
+/// messages of the nested errors.
+impl std::error::Error for ErrorResponse {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error
+            .innererror
+            .as_ref()
+            .map(|inner| inner as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl ErrorResponse {
+    /// Returns the target of the error, i.e. the parameter that was rejected
+    /// (e.g. `"features"`), if the API reported one.
+    pub fn target(&self) -> Option<&str> {
+        self.error.target.as_deref()
+    }
+}
+
+impl ApiError for ErrorResponse {
+    fn is_retryable(&self) -> bool {
+        is_retryable_code(&self.error.code)
+            || std::iter::successors(self.error.innererror.as_ref(), |inner| {
+                inner.innererror.as_deref()
+            })
+            .any(|inner| is_retryable_code(&inner.code))
+    }
+
+    fn code(&self) -> &str {
+        &self.error.code
+    }
+
+    fn message(&self) -> &str {
+        &self.error.message
+    }
 }
 
 /// Error info.
@@ -235,6 +452,18 @@ pub struct ErrorResponseDetails {
     pub target: Option<String>,
 }
 
+impl std::fmt::Display for ErrorResponseDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)?;
+
+        if let Some(target) = &self.target {
+            write!(f, " (target: {})", target)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Detailed error.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ErrorResponseInnerError {
@@ -242,14 +471,28 @@ pub struct ErrorResponseInnerError {
     pub code: String,
 
     /// Detailed error.
-    pub innererror: Box<ErrorResponseInnerError>,
+    pub innererror: Option<Box<ErrorResponseInnerError>>,
 
     /// Error message.
     pub message: String,
 }
 
+impl std::fmt::Display for ErrorResponseInnerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ErrorResponseInnerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.innererror
+            .as_deref()
+            .map(|inner| inner as &(dyn std::error::Error + 'static))
+    }
+}
+
 /// Describe the combined results of different types of image analysis.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ImageAnalysisResult {
     /// An object describing whether the image contains adult-oriented content
     /// and/or is racy.
@@ -300,8 +543,192 @@ pub struct ImageAnalysisResult {
     pub tags_result: Option<TagsResult>,
 }
 
+impl ImageAnalysisResult {
+    /// Serializes the result to pretty-printed JSON, with the field names of
+    /// the API, e.g. for logging or caching.
+    ///
+    /// The JSON can be deserialized back into an `ImageAnalysisResult`.
+    pub fn to_pretty_json(
+        &self,
+    ) -> std::result::Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Returns the [`CaptionResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_caption(&self) -> Result<&CaptionResult> {
+        self.caption_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::Caption))
+    }
+
+    /// Returns the [`DenseCaptionsResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_dense_captions(&self) -> Result<&DenseCaptionsResult> {
+        self.dense_captions_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::DenseCaptions))
+    }
+
+    /// Returns the [`ObjectsResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_objects(&self) -> Result<&ObjectsResult> {
+        self.objects_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::Objects))
+    }
+
+    /// Returns the [`PeopleResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_people(&self) -> Result<&PeopleResult> {
+        self.people_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::People))
+    }
+
+    /// Returns the [`ReadResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_read(&self) -> Result<&ReadResult> {
+        self.read_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::Read))
+    }
+
+    /// Returns the [`SmartCropsResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_smart_crops(&self) -> Result<&SmartCropsResult> {
+        self.smart_crops_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::SmartCrops))
+    }
+
+    /// Returns the [`TagsResult`], or [`Error::FeatureNotReturned`] if
+    /// the response does not contain it.
+    pub fn require_tags(&self) -> Result<&TagsResult> {
+        self.tags_result
+            .as_ref()
+            .ok_or(Error::FeatureNotReturned(VisualFeature::Tags))
+    }
+
+    /// Returns whether `feature` was returned and, if so, whether anything was
+    /// found, e.g. to tell an image without objects from a response without
+    /// an objects result.
+    ///
+    /// The results counted are the lines of all pages for `Read` and the
+    /// caption itself for `Caption`.
+    pub fn feature_status(&self, feature: VisualFeature) -> FeatureStatus {
+        let count = match feature {
+            VisualFeature::Caption => self.caption_result.as_ref().map(|_| 1),
+            VisualFeature::DenseCaptions => self
+                .dense_captions_result
+                .as_ref()
+                .map(DenseCaptionsResult::len),
+            VisualFeature::Objects => {
+                self.objects_result.as_ref().map(ObjectsResult::len)
+            }
+            VisualFeature::People => {
+                self.people_result.as_ref().map(PeopleResult::len)
+            }
+            VisualFeature::Read => self.read_result.as_ref().map(|read| {
+                read.pages.iter().map(|page| page.lines.len()).sum()
+            }),
+            VisualFeature::SmartCrops => {
+                self.smart_crops_result.as_ref().map(SmartCropsResult::len)
+            }
+            VisualFeature::Tags => {
+                self.tags_result.as_ref().map(TagsResult::len)
+            }
+        };
+
+        match count {
+            None => FeatureStatus::NotRequested,
+            Some(0) => FeatureStatus::EmptyResult,
+            Some(count) => FeatureStatus::HasResults(count),
+        }
+    }
+
+    /// Returns `true` if a person was detected with a confidence of at least
+    /// `min_confidence`, stopping at the first one. `false` if the `People`
+    /// feature was not requested.
+    pub fn has_people(&self, min_confidence: Number) -> bool {
+        self.people_result
+            .iter()
+            .flatten()
+            .any(|person| person.confidence >= min_confidence)
+    }
+
+    /// Returns the bounding boxes of the result relative to the size of the
+    /// image, e.g. for resolution-independent storage, or `None` if the
+    /// metadata gives the image a zero width or height.
+    pub fn normalized(&self) -> Option<NormalizedAnalysis> {
+        let (width, height) = (self.metadata.width, self.metadata.height);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let normalize = |bounding_box: &BoundingBox| {
+            PixelRect::from(bounding_box).normalize(width, height)
+        };
+
+        Some(NormalizedAnalysis {
+            width,
+            height,
+            dense_captions: self
+                .dense_captions_result
+                .iter()
+                .flatten()
+                .map(|caption| normalize(&caption.bounding_box))
+                .collect::<Option<_>>()?,
+            objects: self
+                .objects_result
+                .iter()
+                .flatten()
+                .map(|object| normalize(&object.bounding_box))
+                .collect::<Option<_>>()?,
+            people: self
+                .people_result
+                .iter()
+                .flatten()
+                .map(|person| normalize(&person.bounding_box))
+                .collect::<Option<_>>()?,
+            smart_crops: self
+                .smart_crops_result
+                .iter()
+                .flatten()
+                .map(|crop| normalize(&crop.bounding_box))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// The bounding boxes of an [`ImageAnalysisResult`] relative to the size of
+/// the image, as returned by [`ImageAnalysisResult::normalized`].
+///
+/// Boxes are in the same order as the values of the corresponding results,
+/// and empty if a feature was not requested. Use [`NormRect::to_pixels`] to
+/// map them to an image of any resolution.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NormalizedAnalysis {
+    /// The width of the original image in pixels.
+    pub width: PixelCount,
+
+    /// The height of the original image in pixels.
+    pub height: PixelCount,
+
+    /// The bounding boxes of the dense captions.
+    pub dense_captions: Vec<NormRect>,
+
+    /// The bounding boxes of the detected objects.
+    pub objects: Vec<NormRect>,
+
+    /// The bounding boxes of the detected people.
+    pub people: Vec<NormRect>,
+
+    /// The bounding boxes of the smart crop regions.
+    pub smart_crops: Vec<NormRect>,
+}
+
 /// The image metadata information such as height and width.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ImageMetadataApiModel {
     /// The height of the image in pixels.
     pub height: PixelCount,
@@ -310,8 +737,26 @@ pub struct ImageMetadataApiModel {
     pub width: PixelCount,
 }
 
+impl ImageMetadataApiModel {
+    /// Returns the aspect ratio of the image, i.e. `width / height`, or `0.0`
+    /// if the height is 0.
+    pub fn aspect_ratio(&self) -> Number {
+        geometry::aspect_ratio(self.width, self.height)
+    }
+
+    /// Returns `true` if the image is taller than it is wide.
+    pub fn is_portrait(&self) -> bool {
+        self.height > self.width
+    }
+
+    /// Returns `true` if the image is wider than it is tall.
+    pub fn is_landscape(&self) -> bool {
+        self.width > self.height
+    }
+}
+
 /// Describes the prediction result of an image.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ImagePredictionResult {
     /// Describes detected objects in an image.
     #[serde(rename = "objectsResult")]
@@ -330,27 +775,97 @@ pub struct ImageUrl {
 }
 
 /// Describes detected objects in an image.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ObjectsResult {
     /// An array of detected objects.
     pub values: Vec<DetectedObject>,
 }
 
+impl ObjectsResult {
+    /// Returns the detected objects sorted top-to-bottom, left-to-right.
+    ///
+    /// Objects whose top edges lie within `row_tolerance` pixels of each other
+    /// are treated as being on the same row and are ordered by their left
+    /// edge.
+    pub fn in_reading_order(
+        &self,
+        row_tolerance: PixelCount,
+    ) -> Vec<&DetectedObject> {
+        geometry::reading_order(&self.values, row_tolerance, |object| {
+            (object.bounding_box.x, object.bounding_box.y)
+        })
+    }
+
+    /// Returns the smallest box enclosing all detected objects, e.g. for
+    /// framing or cropping, or `None` if no objects were detected.
+    pub fn union_box(&self) -> Option<BoundingBox> {
+        geometry::union(
+            self.values
+                .iter()
+                .map(|object| PixelRect::from(&object.bounding_box)),
+        )
+        .map(BoundingBox::from)
+    }
+}
+
 /// An object describing whether the image contains people.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PeopleResult {
     /// An array of detected people.
     pub values: Vec<DetectedPerson>,
 }
 
+/// How [`PeopleResult::primary`] picks the dominant person.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrimaryPerson {
+    /// The person with the largest bounding box, ties broken by confidence.
+    #[default]
+    LargestArea,
+
+    /// The most confident person, ties broken by bounding box area.
+    HighestConfidence,
+}
+
+impl PeopleResult {
+    /// Returns the smallest box enclosing all detected people, e.g. for
+    /// framing or cropping, or `None` if no people were detected.
+    pub fn union_box(&self) -> Option<BoundingBox> {
+        geometry::union(
+            self.values
+                .iter()
+                .map(|person| PixelRect::from(&person.bounding_box)),
+        )
+        .map(BoundingBox::from)
+    }
+
+    /// Returns the dominant person according to `strategy`, e.g. the subject
+    /// of a portrait, or `None` if no people were detected.
+    pub fn primary(&self, strategy: PrimaryPerson) -> Option<&DetectedPerson> {
+        let area = |person: &DetectedPerson| {
+            PixelRect::from(&person.bounding_box).area()
+        };
+
+        self.values.iter().max_by(|a, b| {
+            let by_area = area(a).cmp(&area(b));
+            let by_confidence = cmp_confidence(&a.confidence, &b.confidence);
+
+            match strategy {
+                PrimaryPerson::LargestArea => by_area.then(by_confidence),
+                PrimaryPerson::HighestConfidence => by_confidence.then(by_area),
+            }
+        })
+    }
+}
+
 /// The results of an Read operation.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ReadResult {
     /// Concatenate string representation of all textual and visual elements in
     /// reading order.
     pub content: String,
 
     /// A list of analyzed pages.
+    #[serde(default)]
     pub pages: Vec<DocumentPage>,
 
     /// The method used to compute string offset and length, possible values
@@ -358,19 +873,234 @@ pub struct ReadResult {
     #[serde(rename = "stringIndexType")]
     pub string_index_type: String,
 
-    /// Extracted font styles.
+    /// Extracted font styles. Empty if the API omits them.
+    #[serde(default)]
     pub styles: Vec<DocumentStyle>,
 }
 
+impl ReadResult {
+    /// Returns an iterator over the text of each line, across all pages, paired
+    /// with the corners of its bounding polygon.
+    ///
+    /// Lines whose bounding box does not consist of exactly 8 numbers are
+    /// skipped.
+    pub fn lines_with_boxes(
+        &self,
+    ) -> impl Iterator<Item = (&str, [(Number, Number); 4])> {
+        self.pages
+            .iter()
+            .flat_map(|page| page.lines.iter())
+            .filter_map(|line| Some((line.content.as_str(), line.polygon()?)))
+    }
+
+    /// Returns the text of all lines, joining the lines of a page with
+    /// `line_sep` and the pages with `page_sep`.
+    ///
+    /// For a single page, `to_text("\n", "\n")` typically equals
+    /// [`content`](Self::content).
+    pub fn to_text(&self, line_sep: &str, page_sep: &str) -> String {
+        self.pages
+            .iter()
+            .map(|page| {
+                page.lines
+                    .iter()
+                    .map(|line| line.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join(line_sep)
+            })
+            .collect::<Vec<_>>()
+            .join(page_sep)
+    }
+
+    /// Returns the spans of content detected as handwritten with a confidence
+    /// of at least `min_confidence`.
+    ///
+    /// Spans locate text in [`content`](Self::content).
+    pub fn handwritten_spans(
+        &self,
+        min_confidence: Number,
+    ) -> Vec<&DocumentSpan> {
+        self.styles
+            .iter()
+            .filter(|style| {
+                style.is_handwritten && style.confidence >= min_confidence
+            })
+            .flat_map(|style| style.spans.iter())
+            .collect()
+    }
+}
+
+/// Deserializes the pages of the `readResult` of an Analyze Image response
+/// body read from `reader`, passing each page to `on_page` as soon as it is
+/// parsed, e.g. to process a very large document without holding all its
+/// pages in memory at once.
+///
+/// Returns the number of pages. The body is read incrementally and everything
+/// but the pages is skipped, so a body without a `readResult`, such as an
+/// error response, yields no pages. Wrap unbuffered readers, such as files, in
+/// a [`BufReader`](std::io::BufReader).
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::for_each_read_page;
+///
+/// let body = r#"{"readResult": {"pages": [
+///     {"angle": 0, "height": 10, "pageNumber": 1, "width": 10},
+///     {"angle": 0, "height": 10, "pageNumber": 2, "width": 10}
+/// ]}}"#;
+///
+/// let mut numbers = Vec::new();
+/// let count = for_each_read_page(body.as_bytes(), |page| {
+///     numbers.push(page.page_number);
+/// })
+/// .unwrap();
+///
+/// assert_eq!(count, 2);
+/// assert_eq!(numbers, [1, 2]);
+/// ```
+pub fn for_each_read_page<R: std::io::Read>(
+    reader: R,
+    mut on_page: impl FnMut(DocumentPage),
+) -> serde_json::Result<usize> {
+    let mut count = 0;
+    let mut on_page = |page| {
+        count += 1;
+        on_page(page);
+    };
+
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    PathSeed {
+        path: &["readResult", "pages"],
+        on_page: &mut on_page,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(count)
+}
+
+/// Visits the value at `path`, skipping everything else, and passes each
+/// element of the array found there to `on_page`.
+struct PathSeed<'p, 'f, F> {
+    path: &'p [&'p str],
+    on_page: &'f mut F,
+}
+
+impl<'de, F: FnMut(DocumentPage)> DeserializeSeed<'de> for PathSeed<'_, '_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, F: FnMut(DocumentPage)> Visitor<'de> for PathSeed<'_, '_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path.first() {
+            Some(key) => write!(f, "a JSON object with optional `{}`", key),
+            None => f.write_str("a JSON array of pages"),
+        }
+    }
+
+    // A `null` result has no pages.
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<(), A::Error> {
+        let Some((first, rest)) = self.path.split_first() else {
+            return Err(de::Error::invalid_type(Unexpected::Map, &self));
+        };
+
+        while let Some(key) = map.next_key::<std::borrow::Cow<str>>()? {
+            if key == *first {
+                map.next_value_seed(PathSeed {
+                    path: rest,
+                    on_page: &mut *self.on_page,
+                })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<(), A::Error> {
+        if !self.path.is_empty() {
+            return Err(de::Error::invalid_type(Unexpected::Seq, &self));
+        }
+
+        while let Some(page) = seq.next_element::<DocumentPage>()? {
+            (self.on_page)(page);
+        }
+
+        Ok(())
+    }
+}
+
 /// Smart cropping result.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SmartCropsResult {
     /// Recommended regions for cropping the image.
     pub values: Vec<CropRegion>,
 }
 
+impl SmartCropsResult {
+    /// Returns the crop region if exactly one was returned.
+    ///
+    /// When `smartcrops_aspect_ratios` is not specified, the service returns a
+    /// single crop region with an aspect ratio it sees fit between 0.5 and 2.0
+    /// (inclusive). Otherwise, one region is returned for each requested aspect
+    /// ratio, and this method returns `None` unless exactly one was requested.
+    pub fn single(&self) -> Option<&CropRegion> {
+        match self.values.as_slice() {
+            [crop] => Some(crop),
+            _ => None,
+        }
+    }
+
+    /// Pairs each of the `requested` aspect ratios with the crop region whose
+    /// aspect ratio is nearest to it, in the order requested.
+    ///
+    /// The service rounds the aspect ratios of the returned regions, so they
+    /// may not equal the requested ones. Requests without a region within
+    /// `tol` of them are skipped.
+    pub fn by_requested(
+        &self,
+        requested: &[Number],
+        tol: Number,
+    ) -> Vec<(Number, &CropRegion)> {
+        requested
+            .iter()
+            .filter_map(|&ratio| {
+                let crop = self.values.iter().min_by(|a, b| {
+                    (a.aspect_ratio - ratio)
+                        .abs()
+                        .total_cmp(&(b.aspect_ratio - ratio).abs())
+                })?;
+
+                ((crop.aspect_ratio - ratio).abs() <= tol)
+                    .then_some((ratio, crop))
+            })
+            .collect()
+    }
+}
+
 /// An entity observation in the image, along with the confidence score.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Tag {
     /// The level of confidence that the entity was observed.
     pub confidence: Number,
@@ -379,17 +1109,217 @@ pub struct Tag {
     pub name: String,
 }
 
+impl From<&Tag> for CommonTag {
+    fn from(tag: &Tag) -> Self {
+        CommonTag {
+            name: tag.name.clone(),
+            confidence: tag.confidence,
+            hint: None,
+        }
+    }
+}
+
 /// A list of tags with confidence level.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TagsResult {
     /// A list of tags with confidence level.
     pub values: Vec<Tag>,
 }
 
+impl TagsResult {
+    /// Counts the tags per confidence range of width `bucket_size`, keyed by
+    /// the index of the range, e.g. key `9` for 0.9 to 1.0 with a
+    /// `bucket_size` of `0.1`.
+    ///
+    /// Returns `None` if `bucket_size` is not positive.
+    pub fn confidence_buckets(
+        &self,
+        bucket_size: Number,
+    ) -> Option<BTreeMap<u32, usize>> {
+        tags::confidence_buckets(
+            self.values.iter().map(|tag| tag.confidence),
+            bucket_size,
+        )
+    }
+}
+
+/// A dense caption together with the objects detected in its region, as
+/// returned by [`merge_regions`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionSummary<'a> {
+    /// The bounding box of the dense caption.
+    pub bounding_box: PixelRect,
+
+    /// The text of the dense caption.
+    pub caption: &'a str,
+
+    /// The most confident tag of each object overlapping the region.
+    pub object_labels: Vec<&'a str>,
+}
+
+/// Returns the intersection over union (IoU) of two bounding boxes, between 0
+/// (disjoint) and 1 (identical), e.g. for custom overlap filters.
+///
+/// Returns 0 if both boxes have zero area.
+pub fn iou(a: &BoundingBox, b: &BoundingBox) -> Number {
+    PixelRect::from(a).iou(&PixelRect::from(b))
+}
+
+/// Associates each dense caption with the detected objects whose bounding
+/// boxes have an intersection over union (IoU) of at least `iou` with it.
+///
+/// Returns one [`RegionSummary`] per dense caption, in order. Objects without
+/// tags are ignored.
+pub fn merge_regions<'a>(
+    dense: &'a DenseCaptionsResult,
+    objects: &'a ObjectsResult,
+    iou: Number,
+) -> Vec<RegionSummary<'a>> {
+    dense
+        .values
+        .iter()
+        .map(|caption| {
+            let bounding_box = PixelRect::from(&caption.bounding_box);
+
+            let object_labels = objects
+                .values
+                .iter()
+                .filter(|object| {
+                    self::iou(&caption.bounding_box, &object.bounding_box)
+                        >= iou
+                })
+                .filter_map(|object| {
+                    object.tags.iter().max_by(|a, b| {
+                        cmp_confidence(&a.confidence, &b.confidence)
+                    })
+                })
+                .map(|tag| tag.name.as_str())
+                .collect();
+
+            RegionSummary {
+                bounding_box,
+                caption: &caption.text,
+                object_labels,
+            }
+        })
+        .collect()
+}
+
+/// Converts detected objects to a [COCO](https://cocodataset.org/#format-data)
+/// annotation document, e.g. to use them as training data.
+///
+/// The document lists the image `image_id` of size `image_size` (width,
+/// height) under `images`, each distinct object label under `categories`, and
+/// an annotation per object with its `bbox` as `[x, y, w, h]` in pixels and
+/// its `score`. An object is labeled with its most confident tag; objects
+/// without tags are skipped. IDs of categories and annotations start at 1.
+pub fn to_coco_annotations(
+    result: &ObjectsResult,
+    image_id: u64,
+    image_size: (PixelCount, PixelCount),
+) -> serde_json::Value {
+    let detections = result.values.iter().filter_map(|object| {
+        let tag = object
+            .tags
+            .iter()
+            .max_by(|a, b| cmp_confidence(&a.confidence, &b.confidence))?;
+
+        Some(coco::Detection {
+            label: &tag.name,
+            rect: PixelRect::from(&object.bounding_box),
+            score: Some(tag.confidence),
+        })
+    });
+
+    coco::annotations(detections, image_id, image_size)
+}
+
+/// Combines the tags of multiple images by tag name, e.g. to tag an album.
+///
+/// Returns `(name, confidence)` pairs sorted by combined confidence
+/// (descending), then by name.
+pub fn aggregate_tags(
+    results: &[&TagsResult],
+    aggregation: TagAggregation,
+) -> Vec<(String, Number)> {
+    tags::aggregate(
+        results.iter().map(|result| {
+            result
+                .values
+                .iter()
+                .map(|tag| (tag.name.as_str(), tag.confidence))
+        }),
+        aggregation,
+    )
+}
+
+/// Implements methods and `IntoIterator` delegating to the `values` of a result
+/// wrapper.
+macro_rules! impl_values {
+    ($wrapper:ty, $item:ty) => {
+        impl $wrapper {
+            /// Returns the number of values.
+            pub fn len(&self) -> usize {
+                self.values.len()
+            }
+
+            /// Returns `true` if there are no values.
+            pub fn is_empty(&self) -> bool {
+                self.values.is_empty()
+            }
+
+            /// Returns an iterator over the values.
+            pub fn iter(&self) -> std::slice::Iter<'_, $item> {
+                self.values.iter()
+            }
+        }
+
+        impl IntoIterator for $wrapper {
+            type Item = $item;
+            type IntoIter = std::vec::IntoIter<$item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.values.into_iter()
+            }
+        }
+
+        impl<'a> IntoIterator for &'a $wrapper {
+            type Item = &'a $item;
+            type IntoIter = std::slice::Iter<'a, $item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.values.iter()
+            }
+        }
+    };
+}
+
+impl_values!(DenseCaptionsResult, DenseCaption);
+impl_values!(ObjectsResult, DetectedObject);
+impl_values!(PeopleResult, DetectedPerson);
+impl_values!(SmartCropsResult, CropRegion);
+impl_values!(TagsResult, Tag);
+
+/// Whether a visual feature was returned, and with how many results, see
+/// [`ImageAnalysisResult::feature_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureStatus {
+    /// The response does not contain the feature, e.g. because it was not
+    /// requested or is not supported for the image.
+    NotRequested,
+
+    /// The feature was analyzed, but nothing was found.
+    EmptyResult,
+
+    /// The feature was analyzed and has the given number of results.
+    HasResults(usize),
+}
+
 /// The visual features requested: `tags`, `objects`, `caption`, `denseCaptions`
 /// , `read`, `smartCrops`, `people`. This parameter needs to be specified if
 /// the parameter "model-name" is not specified.
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum VisualFeature {
     #[serde(rename = "caption")]
     Caption,
@@ -413,6 +1343,54 @@ pub enum VisualFeature {
     Tags,
 }
 
+impl VisualFeature {
+    /// Every visual feature.
+    pub const ALL: &'static [VisualFeature] = &[
+        VisualFeature::Caption,
+        VisualFeature::DenseCaptions,
+        VisualFeature::Objects,
+        VisualFeature::People,
+        VisualFeature::Read,
+        VisualFeature::SmartCrops,
+        VisualFeature::Tags,
+    ];
+
+    /// Parses each of `names` as a [`VisualFeature`], e.g. to select features
+    /// from configuration, failing on the first unknown name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v40::VisualFeature;
+    ///
+    /// let features = VisualFeature::from_strings(&["caption", "tags"]);
+    ///
+    /// assert_eq!(
+    ///     features.unwrap(),
+    ///     [VisualFeature::Caption, VisualFeature::Tags]
+    /// );
+    /// ```
+    pub fn from_strings(
+        names: &[&str],
+    ) -> std::result::Result<Vec<Self>, ParseFeatureError> {
+        names.iter().map(|name| name.parse()).collect()
+    }
+}
+
+/// Parses the name of a feature as used by the API, e.g. `"denseCaptions"`,
+/// ignoring ASCII case.
+impl std::str::FromStr for VisualFeature {
+    type Err = ParseFeatureError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|feature| feature.to_string().eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| ParseFeatureError(s.to_string()))
+    }
+}
+
 impl std::fmt::Display for VisualFeature {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let feature_str = match self {
@@ -427,3 +1405,1197 @@ impl std::fmt::Display for VisualFeature {
         write!(f, "{}", feature_str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::round_trip;
+
+    fn detected_object(
+        name: &str,
+        x: PixelCount,
+        y: PixelCount,
+    ) -> DetectedObject {
+        DetectedObject {
+            bounding_box: BoundingBox { h: 10, w: 10, x, y },
+            id: None,
+            tags: vec![Tag {
+                confidence: 1.0,
+                name: name.to_string(),
+            }],
+        }
+    }
+
+    const ERROR_WITH_TARGET: &str = r#"{
+        "error": {
+            "code": "InvalidRequest",
+            "message": "The feature 'foo' is not supported.",
+            "target": "features"
+        }
+    }"#;
+
+    #[test]
+    fn test_error_response_target() {
+        let err: ErrorResponse =
+            serde_json::from_str(ERROR_WITH_TARGET).unwrap();
+
+        assert_eq!(err.target(), Some("features"));
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: The feature 'foo' is not supported. \
+             (target: features)"
+        );
+    }
+
+    #[test]
+    fn test_error_response_no_target() {
+        let err: ErrorResponse = serde_json::from_str(
+            r#"{"error": {"code": "InvalidRequest", "message": "Bad."}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(err.target(), None);
+        assert_eq!(err.to_string(), "InvalidRequest: Bad.");
+    }
+
+    #[test]
+    fn test_error_response_source_chain() {
+        let err: ErrorResponse = serde_json::from_str(
+            r#"{
+                "error": {
+                    "code": "InvalidRequest",
+                    "message": "The image is invalid.",
+                    "innererror": {
+                        "code": "InvalidImageFormat",
+                        "message": "Unsupported format.",
+                        "innererror": {
+                            "code": "NotSupportedImage",
+                            "message": "Animated images are not supported."
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut chain = Vec::new();
+        let mut source = std::error::Error::source(&err);
+        while let Some(err) = source {
+            chain.push(err.to_string());
+            source = err.source();
+        }
+
+        assert_eq!(err.to_string(), "InvalidRequest: The image is invalid.");
+        assert_eq!(
+            chain,
+            [
+                "InvalidImageFormat: Unsupported format.",
+                "NotSupportedImage: Animated images are not supported.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_response_without_inner_error_has_no_source() {
+        let err: ErrorResponse =
+            serde_json::from_str(ERROR_WITH_TARGET).unwrap();
+
+        assert!(std::error::Error::source(&err).is_none());
+    }
+
+    #[test]
+    fn test_objects_in_reading_order() {
+        let objects = ObjectsResult {
+            values: vec![
+                detected_object("bottom", 5, 100),
+                detected_object("top-right", 200, 8),
+                detected_object("top-left", 10, 12),
+            ],
+        };
+
+        let names: Vec<&str> = objects
+            .in_reading_order(5)
+            .iter()
+            .map(|object| object.tags[0].name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["top-left", "top-right", "bottom"]);
+    }
+
+    #[test]
+    fn test_bounding_box_clamp_to() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/objects.json"
+        ))
+        .unwrap();
+        let (width, height) =
+            (analysis.metadata.width, analysis.metadata.height);
+        assert_eq!((width, height), (1038, 692));
+
+        let bounding_box = BoundingBox {
+            h: 100,
+            w: 100,
+            x: 1000,
+            y: 650,
+        };
+        let clamped = bounding_box.clamp_to(width, height);
+
+        assert_eq!(
+            (clamped.x, clamped.y, clamped.w, clamped.h),
+            (1000, 650, 38, 42)
+        );
+    }
+
+    #[test]
+    fn test_detected_object_id() {
+        let json = r#"{
+            "boundingBox": {"x": 10, "y": 20, "w": 30, "h": 40},
+            "id": "widget-1",
+            "tags": [{"name": "widget", "confidence": 0.87}]
+        }"#;
+
+        let object: DetectedObject = serde_json::from_str(json).unwrap();
+
+        assert_eq!(object.id.as_deref(), Some("widget-1"));
+        assert_eq!(object.id_or_index(3), "widget-1");
+        round_trip::assert_round_trip::<DetectedObject>(json);
+    }
+
+    #[test]
+    fn test_detected_object_id_or_index() {
+        let object = detected_object("chair", 0, 0);
+
+        assert_eq!(object.id_or_index(3), "3");
+    }
+
+    #[test]
+    fn test_objects_union_box() {
+        let objects = ObjectsResult {
+            values: vec![
+                detected_object("bottom", 5, 100),
+                detected_object("top-right", 200, 8),
+                detected_object("top-left", 10, 12),
+            ],
+        };
+
+        let union = PixelRect::from(&objects.union_box().unwrap());
+
+        assert_eq!(
+            union,
+            PixelRect {
+                x: 5,
+                y: 8,
+                w: 205,
+                h: 102,
+            }
+        );
+        for object in &objects.values {
+            let rect = PixelRect::from(&object.bounding_box);
+            assert_eq!(union.union(&rect), union);
+        }
+        assert!(ObjectsResult { values: vec![] }.union_box().is_none());
+    }
+
+    #[test]
+    fn test_objects_in_reading_order_zero_tolerance() {
+        let objects = ObjectsResult {
+            values: vec![
+                detected_object("bottom", 5, 100),
+                detected_object("top-right", 200, 8),
+                detected_object("top-left", 10, 12),
+            ],
+        };
+
+        let names: Vec<&str> = objects
+            .in_reading_order(0)
+            .iter()
+            .map(|object| object.tags[0].name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["top-right", "top-left", "bottom"]);
+    }
+
+    #[test]
+    fn test_smart_crops_single() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/smart_crops.json"
+        ))
+        .unwrap();
+        let smart_crops = analysis.smart_crops_result.unwrap();
+
+        let crop = smart_crops.single().expect("no single crop");
+        assert_eq!(crop.aspect_ratio, 1.09);
+        assert_eq!(crop.bounding_box.x, 303);
+    }
+
+    #[test]
+    fn test_smart_crops_single_multiple() {
+        let crop = |aspect_ratio| CropRegion {
+            aspect_ratio,
+            bounding_box: BoundingBox {
+                h: 10,
+                w: 10,
+                x: 0,
+                y: 0,
+            },
+        };
+        let smart_crops = SmartCropsResult {
+            values: vec![crop(0.75), crop(1.8)],
+        };
+
+        assert!(smart_crops.single().is_none());
+    }
+
+    #[test]
+    fn test_smart_crops_by_requested() {
+        let smart_crops: SmartCropsResult = serde_json::from_str(
+            r#"{
+                "values": [
+                    {
+                        "aspectRatio": 1.79,
+                        "boundingBox": {"h": 170, "w": 305, "x": 0, "y": 20}
+                    },
+                    {
+                        "aspectRatio": 0.74,
+                        "boundingBox": {"h": 340, "w": 252, "x": 40, "y": 0}
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let crops = smart_crops.by_requested(&[0.75, 1.8], 0.02);
+
+        assert_eq!(crops.len(), 2);
+        assert_eq!(crops[0].0, 0.75);
+        assert_eq!(crops[0].1.aspect_ratio, 0.74);
+        assert_eq!(crops[1].0, 1.8);
+        assert_eq!(crops[1].1.aspect_ratio, 1.79);
+
+        assert!(smart_crops.by_requested(&[1.2], 0.02).is_empty());
+    }
+
+    #[test]
+    fn test_tag_to_common_tag() {
+        let tag = Tag {
+            confidence: 0.9,
+            name: "hat".to_string(),
+        };
+
+        assert_eq!(
+            CommonTag::from(&tag),
+            CommonTag {
+                name: "hat".to_string(),
+                confidence: 0.9,
+                hint: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tags_confidence_buckets() {
+        let tags = tags_result(&[
+            ("a", 0.05),
+            ("b", 0.3),
+            ("c", 0.35),
+            ("d", 0.7),
+            ("e", 0.95),
+            ("f", 1.0),
+        ]);
+
+        let buckets = tags.confidence_buckets(0.1).unwrap();
+
+        assert_eq!(
+            buckets.into_iter().collect::<Vec<_>>(),
+            vec![(0, 1), (3, 2), (7, 1), (9, 2)]
+        );
+        assert_eq!(
+            tags.confidence_buckets(0.5)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![(0, 3), (1, 3)]
+        );
+        assert_eq!(tags.confidence_buckets(0.0), None);
+        assert_eq!(tags.confidence_buckets(-0.1), None);
+        assert_eq!(tags.confidence_buckets(Number::NAN), None);
+    }
+
+    #[test]
+    fn test_merge_regions() {
+        let bounding_box = |x, y| BoundingBox {
+            h: 100,
+            w: 100,
+            x,
+            y,
+        };
+        let dense = DenseCaptionsResult {
+            values: vec![
+                DenseCaption {
+                    bounding_box: bounding_box(0, 0),
+                    confidence: 0.9,
+                    text: "a dog on grass".to_string(),
+                },
+                DenseCaption {
+                    bounding_box: bounding_box(500, 500),
+                    confidence: 0.8,
+                    text: "a tree".to_string(),
+                },
+            ],
+        };
+        let objects = ObjectsResult {
+            values: vec![DetectedObject {
+                bounding_box: bounding_box(10, 10),
+                id: None,
+                tags: tags_result(&[("animal", 0.6), ("dog", 0.9)]).values,
+            }],
+        };
+
+        let regions = merge_regions(&dense, &objects, 0.5);
+
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].caption, "a dog on grass");
+        assert_eq!(regions[0].object_labels, vec!["dog"]);
+        assert_eq!(regions[0].bounding_box.x, 0);
+        assert_eq!(regions[1].caption, "a tree");
+        assert!(regions[1].object_labels.is_empty());
+
+        assert!(merge_regions(&dense, &objects, 0.9)[0]
+            .object_labels
+            .is_empty());
+    }
+
+    #[test]
+    fn test_values_wrapper_empty() {
+        let tags = TagsResult { values: vec![] };
+
+        assert_eq!(tags.len(), 0);
+        assert!(tags.is_empty());
+        assert!(tags.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_values_wrapper_non_empty() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/people.json"
+        ))
+        .unwrap();
+        let people = analysis.people_result.unwrap();
+
+        assert_eq!(people.len(), people.values.len());
+        assert!(!people.is_empty());
+        assert_eq!(people.iter().count(), people.len());
+    }
+
+    #[test]
+    fn test_values_wrapper_into_iter() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/all_features.json"
+        ))
+        .unwrap();
+
+        let dense_captions = analysis.dense_captions_result.unwrap();
+        let objects = analysis.objects_result.unwrap();
+        let people = analysis.people_result.unwrap();
+        let smart_crops = analysis.smart_crops_result.unwrap();
+        let tags = analysis.tags_result.unwrap();
+
+        let mut count = 0;
+        for person in &people {
+            assert!(person.confidence >= 0.0);
+            count += 1;
+        }
+        assert_eq!(count, people.len());
+
+        assert_eq!((&dense_captions).into_iter().count(), 10);
+        assert_eq!((&objects).into_iter().count(), 2);
+        assert_eq!((&smart_crops).into_iter().count(), 1);
+        assert_eq!((&tags).into_iter().count(), 22);
+
+        let texts: Vec<String> = dense_captions
+            .into_iter()
+            .map(|caption| caption.text)
+            .collect();
+        assert_eq!(texts.len(), 10);
+        assert_eq!(objects.into_iter().count(), 2);
+        assert_eq!(people.into_iter().count(), 4);
+        assert_eq!(smart_crops.into_iter().count(), 1);
+
+        let names: Vec<String> = tags.into_iter().map(|tag| tag.name).collect();
+        assert_eq!(names.len(), 22);
+    }
+
+    #[test]
+    fn test_read_result_lines_with_boxes() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/read.json"
+        ))
+        .unwrap();
+        let read = analysis.read_result.unwrap();
+
+        let lines: Vec<_> = read.lines_with_boxes().collect();
+
+        assert_eq!(lines.len(), read.pages[0].lines.len());
+        assert_eq!(
+            lines[1],
+            (
+                "E Conference room 154584354",
+                [
+                    (130.0, 153.0),
+                    (224.0, 154.0),
+                    (224.0, 161.0),
+                    (130.0, 161.0)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_result_to_text() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/read.json"
+        ))
+        .unwrap();
+        let read = analysis.read_result.unwrap();
+
+        assert_eq!(read.to_text("\n", "\n"), read.content);
+        assert!(read.to_text(" ", "\n").starts_with(
+            "9:35 AM E Conference room 154584354 #: 555-173-4547"
+        ));
+    }
+
+    #[test]
+    fn test_read_result_to_text_pages() {
+        let page = |lines: &[&str]| DocumentPage {
+            angle: 0.0,
+            height: 100.0,
+            lines: lines
+                .iter()
+                .map(|content| DocumentLine {
+                    bounding_box: vec![],
+                    content: content.to_string(),
+                    spans: vec![],
+                })
+                .collect(),
+            page_number: 1,
+            spans: vec![],
+            width: 100.0,
+            words: vec![],
+        };
+        let read = ReadResult {
+            content: String::new(),
+            pages: vec![page(&["a", "b"]), page(&["c"])],
+            string_index_type: "TextElements".to_string(),
+            styles: vec![],
+        };
+
+        assert_eq!(read.to_text(" ", "\n\n"), "a b\n\nc");
+        assert_eq!(read.to_text("", ""), "abc");
+    }
+
+    #[test]
+    fn test_read_result_missing_collections() {
+        let read: ReadResult = serde_json::from_str(
+            r#"{
+                "content": "Hello",
+                "pages": [
+                    {
+                        "angle": 0.0,
+                        "height": 100.0,
+                        "lines": [
+                            {
+                                "boundingBox": [0, 0, 10, 0, 10, 5, 0, 5],
+                                "content": "Hello"
+                            }
+                        ],
+                        "pageNumber": 1,
+                        "width": 100.0
+                    }
+                ],
+                "stringIndexType": "TextElements"
+            }"#,
+        )
+        .unwrap();
+
+        assert!(read.styles.is_empty());
+        assert!(read.pages[0].spans.is_empty());
+        assert!(read.pages[0].words.is_empty());
+        assert!(read.pages[0].lines[0].spans.is_empty());
+        assert_eq!(read.to_text("\n", "\n"), "Hello");
+    }
+
+    #[test]
+    fn test_read_result_handwritten_spans() {
+        let read: ReadResult = serde_json::from_str(
+            r#"{
+                "content": "Printed Handwritten Faint",
+                "pages": [],
+                "stringIndexType": "TextElements",
+                "styles": [
+                    {
+                        "confidence": 0.9,
+                        "isHandwritten": false,
+                        "spans": [{"length": 7, "offset": 0}]
+                    },
+                    {
+                        "confidence": 0.8,
+                        "isHandwritten": true,
+                        "spans": [{"length": 11, "offset": 8}]
+                    },
+                    {
+                        "confidence": 0.3,
+                        "isHandwritten": true,
+                        "spans": [{"length": 5, "offset": 20}]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let spans = read.handwritten_spans(0.5);
+
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].offset, spans[0].length), (8, 11));
+        assert_eq!(read.handwritten_spans(0.0).len(), 2);
+        assert!(read.handwritten_spans(0.95).is_empty());
+    }
+
+    #[test]
+    fn test_document_line_polygon_malformed() {
+        let line = DocumentLine {
+            bounding_box: vec![1.0, 2.0, 3.0],
+            content: "text".to_string(),
+            spans: vec![],
+        };
+
+        assert!(line.polygon().is_none());
+    }
+
+    fn person(
+        w: PixelCount,
+        h: PixelCount,
+        confidence: Number,
+    ) -> DetectedPerson {
+        DetectedPerson {
+            bounding_box: BoundingBox { h, w, x: 0, y: 0 },
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_people_primary() {
+        let people = PeopleResult {
+            values: vec![
+                person(10, 10, 0.9),
+                person(40, 80, 0.6),
+                person(20, 20, 0.7),
+            ],
+        };
+
+        let largest = people.primary(PrimaryPerson::LargestArea).unwrap();
+        assert_eq!(largest.bounding_box.w, 40);
+
+        let confident =
+            people.primary(PrimaryPerson::HighestConfidence).unwrap();
+        assert_eq!(confident.confidence, 0.9);
+    }
+
+    #[test]
+    fn test_people_union_box() {
+        let people = PeopleResult {
+            values: vec![person(10, 10, 0.9), person(40, 80, 0.6)],
+        };
+
+        let union = people.union_box().unwrap();
+
+        assert_eq!((union.x, union.y, union.w, union.h), (0, 0, 40, 80));
+        assert!(PeopleResult { values: vec![] }.union_box().is_none());
+    }
+
+    #[test]
+    fn test_people_primary_ties() {
+        let people = PeopleResult {
+            values: vec![person(20, 20, 0.5), person(20, 20, 0.8)],
+        };
+        assert_eq!(
+            people
+                .primary(PrimaryPerson::LargestArea)
+                .unwrap()
+                .confidence,
+            0.8
+        );
+
+        let people = PeopleResult {
+            values: vec![person(30, 30, 0.8), person(10, 10, 0.8)],
+        };
+        assert_eq!(
+            people
+                .primary(PrimaryPerson::HighestConfidence)
+                .unwrap()
+                .bounding_box
+                .w,
+            30
+        );
+
+        assert!(PeopleResult { values: vec![] }
+            .primary(PrimaryPerson::default())
+            .is_none());
+    }
+
+    fn tags_result(tags: &[(&str, Number)]) -> TagsResult {
+        TagsResult {
+            values: tags
+                .iter()
+                .map(|&(name, confidence)| Tag {
+                    confidence,
+                    name: name.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_tags() {
+        let a = tags_result(&[("dog", 0.9), ("grass", 0.6)]);
+        let b = tags_result(&[("dog", 0.7), ("ball", 0.8)]);
+
+        assert_eq!(
+            aggregate_tags(&[&a, &b], TagAggregation::Max),
+            vec![
+                ("dog".to_string(), 0.9),
+                ("ball".to_string(), 0.8),
+                ("grass".to_string(), 0.6),
+            ]
+        );
+
+        let mean = aggregate_tags(&[&a, &b], TagAggregation::Mean);
+        let names: Vec<&str> = mean.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["dog", "ball", "grass"]);
+        assert!((mean[0].1 - 0.8).abs() < 1e-9);
+        assert!((mean[1].1 - 0.4).abs() < 1e-9);
+        assert!((mean[2].1 - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_document_page_rotation() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/read.json"
+        ))
+        .unwrap();
+        let page = &analysis.read_result.unwrap().pages[0];
+
+        assert_eq!(page.skew_degrees(), 0.3048);
+        assert_eq!(page.deskew_degrees(), -0.3048);
+        assert!(page.is_rotated(0.1));
+        assert!(!page.is_rotated(1.0));
+    }
+
+    #[test]
+    fn test_document_page_pixel_dimensions() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/read.json"
+        ))
+        .unwrap();
+        let mut page = analysis.read_result.unwrap().pages.remove(0);
+
+        assert_eq!(page.pixel_dimensions(), Some((1038, 692)));
+
+        // A US Letter PDF page, in inches.
+        page.width = 8.5;
+        page.height = 11.0;
+        assert_eq!(page.pixel_dimensions(), None);
+
+        page.width = 1038.5;
+        page.height = 692.0;
+        assert_eq!(page.pixel_dimensions(), None);
+    }
+
+    #[test]
+    fn test_bounding_box_to_norm_rect() {
+        let bounding_box = BoundingBox {
+            h: 50,
+            w: 100,
+            x: 50,
+            y: 25,
+        };
+
+        let rect = PixelRect::from(&bounding_box);
+
+        assert_eq!(
+            rect,
+            PixelRect {
+                x: 50,
+                y: 25,
+                w: 100,
+                h: 50
+            }
+        );
+        assert_eq!(
+            rect.normalize(200, 100),
+            Some(NormRect {
+                x: 0.25,
+                y: 0.25,
+                w: 0.5,
+                h: 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn test_normalize_degrees() {
+        assert_eq!(normalize_degrees(0.0), 0.0);
+        assert_eq!(normalize_degrees(180.0), 180.0);
+        assert_eq!(normalize_degrees(-180.0), 180.0);
+        assert_eq!(normalize_degrees(270.0), -90.0);
+        assert_eq!(normalize_degrees(-450.0), -90.0);
+    }
+
+    #[test]
+    fn test_image_metadata_landscape() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/people.json"
+        ))
+        .unwrap();
+        let metadata = analysis.metadata;
+
+        assert_eq!((metadata.width, metadata.height), (1038, 692));
+        assert_eq!(metadata.aspect_ratio(), 1.5);
+        assert!(metadata.is_landscape());
+        assert!(!metadata.is_portrait());
+    }
+
+    #[test]
+    fn test_image_metadata_portrait() {
+        let metadata = ImageMetadataApiModel {
+            height: 1280,
+            width: 853,
+        };
+
+        assert!((metadata.aspect_ratio() - 0.6664).abs() < 1e-4);
+        assert!(metadata.is_portrait());
+        assert!(!metadata.is_landscape());
+
+        let metadata = ImageMetadataApiModel {
+            height: 0,
+            width: 853,
+        };
+        assert_eq!(metadata.aspect_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_image_url_query_string_round_trip() {
+        let url = "https://account.blob.core.windows.net/images/a%20b.jpg\
+                   ?sv=2022-11-02&sp=r&sig=abc%2Bdef%2F%3D&name=caf\u{e9}";
+        let image_url = ImageUrl {
+            url: url.to_string(),
+        };
+
+        let json = serde_json::to_string(&image_url).unwrap();
+        let round_tripped: ImageUrl = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains("?sv=2022-11-02&sp=r&sig=abc%2Bdef%2F%3D"));
+        assert_eq!(round_tripped.url, url);
+    }
+
+    fn should_retry(err: &impl ApiError) -> bool {
+        err.is_retryable()
+    }
+
+    #[test]
+    fn test_api_error_service_unavailable() {
+        let err: ErrorResponse = serde_json::from_str(
+            r#"{"error": {"code": "ServiceUnavailable", "message": "Busy."}}"#,
+        )
+        .unwrap();
+
+        assert!(should_retry(&err));
+        assert_eq!(err.code(), "ServiceUnavailable");
+        assert_eq!(err.message(), "Busy.");
+    }
+
+    #[test]
+    fn test_api_error_invalid_request() {
+        let err: ErrorResponse = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/error_image_too_large.json"
+        ))
+        .unwrap();
+
+        assert!(!should_retry(&err));
+        assert_eq!(err.code(), "InvalidRequest");
+    }
+
+    #[test]
+    fn test_api_error_retryable_inner_code() {
+        let err: ErrorResponse = serde_json::from_str(
+            r#"{"error": {"code": "InvalidRequest", "message": "message",
+                "innererror": {"code": "Unspecified", "message": "message",
+                    "innererror": {"code": "Timeout", "message": "message"}}}}"#,
+        )
+        .unwrap();
+
+        assert!(should_retry(&err));
+    }
+
+    #[test]
+    fn test_normalized() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/objects.json"
+        ))
+        .unwrap();
+
+        let normalized = analysis.normalized().unwrap();
+
+        assert_eq!((normalized.width, normalized.height), (1038, 692));
+        assert_eq!(normalized.objects.len(), 2);
+        assert_eq!(
+            normalized.objects[0],
+            NormRect {
+                x: 655.0 / 1038.0,
+                y: 83.0 / 692.0,
+                w: 263.0 / 1038.0,
+                h: 605.0 / 692.0
+            }
+        );
+        assert_eq!(
+            normalized.objects[0].to_pixels(1038, 692),
+            PixelRect {
+                x: 655,
+                y: 83,
+                w: 263,
+                h: 605
+            }
+        );
+        assert!(normalized.people.is_empty());
+
+        let mut analysis = analysis;
+        analysis.metadata.height = 0;
+        assert!(analysis.normalized().is_none());
+    }
+
+    fn dense_captions() -> DenseCaptionsResult {
+        serde_json::from_str::<ImageAnalysisResult>(include_str!(
+            "../../tests/samples/v40/dense_captions.json"
+        ))
+        .unwrap()
+        .dense_captions_result
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dense_caption_is_full_image() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/dense_captions.json"
+        ))
+        .unwrap();
+        let captions = &analysis.dense_captions_result.unwrap().values;
+
+        assert!(captions[0].is_full_image(&analysis.metadata));
+        assert!(!captions[1].is_full_image(&analysis.metadata));
+    }
+
+    #[test]
+    fn test_dense_captions_sorted_by_confidence() {
+        let dense = dense_captions();
+
+        let sorted = dense.sorted_by_confidence();
+        assert_eq!(sorted.len(), 10);
+        assert!(sorted
+            .windows(2)
+            .all(|pair| pair[0].confidence >= pair[1].confidence));
+
+        // The first value describes the whole image, not the best region.
+        assert_eq!(dense.values[0].text, "a man pointing at a screen");
+        assert_eq!(sorted[0].text, "a close up of a man's head");
+    }
+
+    #[test]
+    fn test_dense_captions_top_and_texts() {
+        let dense = dense_captions();
+
+        let top: Vec<&str> = dense
+            .top(2)
+            .iter()
+            .map(|caption| caption.text.as_str())
+            .collect();
+        assert_eq!(
+            top,
+            ["a close up of a man's head", "a man pointing at a screen"]
+        );
+        assert_eq!(dense.top(20).len(), 10);
+        assert!(dense.top(0).is_empty());
+
+        let texts = dense.texts();
+        assert_eq!(texts.len(), 10);
+        assert_eq!(&texts[..2], top);
+        assert_eq!(texts[9], "a person in a yellow coat");
+    }
+
+    #[test]
+    fn test_require_present() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/people.json"
+        ))
+        .unwrap();
+
+        assert!(!analysis.require_people().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_require_absent() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/people.json"
+        ))
+        .unwrap();
+
+        assert!(matches!(
+            analysis.require_tags(),
+            Err(Error::FeatureNotReturned(VisualFeature::Tags))
+        ));
+        assert!(matches!(
+            analysis.require_read(),
+            Err(Error::FeatureNotReturned(VisualFeature::Read))
+        ));
+    }
+
+    #[test]
+    fn test_for_each_read_page() {
+        let mut body: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/read.json"
+        ))
+        .unwrap();
+        let pages = body["readResult"]["pages"].as_array_mut().unwrap();
+        let mut second_page = pages[0].clone();
+        second_page["pageNumber"] = 2.into();
+        pages.push(second_page);
+        let body = serde_json::to_vec(&body).unwrap();
+
+        let mut pages = Vec::new();
+        let count =
+            for_each_read_page(&body[..], |page| pages.push(page)).unwrap();
+
+        let expected: ImageAnalysisResult =
+            serde_json::from_slice(&body).unwrap();
+        let expected = expected.require_read().unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(pages.len(), expected.pages.len());
+        for (page, expected) in pages.iter().zip(&expected.pages) {
+            assert_eq!(page.page_number, expected.page_number);
+            assert_eq!(page.lines.len(), expected.lines.len());
+        }
+    }
+
+    #[test]
+    fn test_for_each_read_page_without_read_result() {
+        let count = for_each_read_page(
+            include_str!("../../tests/samples/v40/error_image_too_large.json")
+                .as_bytes(),
+            |_| panic!("unexpected page"),
+        )
+        .unwrap();
+
+        assert_eq!(count, 0);
+        assert_eq!(
+            for_each_read_page(&br#"{"readResult": null}"#[..], |_| {})
+                .unwrap(),
+            0
+        );
+        assert!(
+            for_each_read_page(&br#"{"readResult": []}"#[..], |_| {}).is_err()
+        );
+    }
+
+    #[test]
+    fn test_to_coco_annotations() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/objects.json"
+        ))
+        .unwrap();
+        let objects = analysis.require_objects().unwrap();
+
+        let coco = to_coco_annotations(objects, 7, (1038, 692));
+
+        assert_eq!(coco["images"][0]["id"], 7);
+        assert_eq!(coco["images"][0]["width"], 1038);
+        assert_eq!(coco["categories"][0]["name"], "person");
+        assert_eq!(coco["categories"][1]["name"], "television");
+
+        let annotation = &coco["annotations"][0];
+        assert_eq!(annotation["id"], 1);
+        assert_eq!(annotation["image_id"], 7);
+        assert_eq!(annotation["category_id"], 1);
+        assert_eq!(annotation["bbox"], serde_json::json!([655, 83, 263, 605]));
+        assert_eq!(annotation["area"], 263 * 605);
+        assert_eq!(annotation["score"], 0.905);
+        assert_eq!(
+            coco["annotations"].as_array().unwrap().len(),
+            objects.len()
+        );
+    }
+
+    #[test]
+    fn test_feature_status() {
+        let mut analysis: ImageAnalysisResult = serde_json::from_str(
+            include_str!("../../tests/samples/v40/objects.json"),
+        )
+        .unwrap();
+        let count = analysis.require_objects().unwrap().len();
+
+        assert!(count > 0);
+        assert_eq!(
+            analysis.feature_status(VisualFeature::Objects),
+            FeatureStatus::HasResults(count)
+        );
+
+        analysis.objects_result = Some(ObjectsResult { values: vec![] });
+
+        assert_eq!(
+            analysis.feature_status(VisualFeature::Objects),
+            FeatureStatus::EmptyResult
+        );
+    }
+
+    #[test]
+    fn test_feature_status_not_requested() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/tags.json"
+        ))
+        .unwrap();
+
+        assert_eq!(
+            analysis.feature_status(VisualFeature::Objects),
+            FeatureStatus::NotRequested
+        );
+        assert!(matches!(
+            analysis.feature_status(VisualFeature::Tags),
+            FeatureStatus::HasResults(_)
+        ));
+    }
+
+    #[test]
+    fn test_visual_feature_from_strings() {
+        let features =
+            VisualFeature::from_strings(&["caption", "denseCaptions", "TAGS"]);
+
+        assert_eq!(
+            features.unwrap(),
+            [
+                VisualFeature::Caption,
+                VisualFeature::DenseCaptions,
+                VisualFeature::Tags
+            ]
+        );
+        for feature in VisualFeature::ALL {
+            assert_eq!(
+                &feature.to_string().parse::<VisualFeature>().unwrap(),
+                feature
+            );
+        }
+    }
+
+    #[test]
+    fn test_visual_feature_from_strings_unknown() {
+        let result = VisualFeature::from_strings(&["caption", "faces", "foo"]);
+
+        assert_eq!(result, Err(ParseFeatureError("faces".to_string())));
+    }
+
+    #[test]
+    fn test_has_people() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/people.json"
+        ))
+        .unwrap();
+
+        assert!(analysis.has_people(0.9));
+        assert!(!analysis.has_people(0.99));
+    }
+
+    #[test]
+    fn test_has_people_not_requested() {
+        let analysis: ImageAnalysisResult = serde_json::from_str(include_str!(
+            "../../tests/samples/v40/tags.json"
+        ))
+        .unwrap();
+
+        assert!(!analysis.has_people(0.0));
+    }
+
+    #[test]
+    fn test_iou() {
+        let a = BoundingBox {
+            x: 0,
+            y: 0,
+            w: 10,
+            h: 10,
+        };
+        let b = BoundingBox {
+            x: 5,
+            y: 0,
+            w: 10,
+            h: 10,
+        };
+        let c = BoundingBox {
+            x: 20,
+            y: 20,
+            w: 5,
+            h: 5,
+        };
+        let empty = BoundingBox {
+            x: 0,
+            y: 0,
+            w: 0,
+            h: 0,
+        };
+
+        assert_eq!(iou(&a, &a), 1.0);
+        assert_eq!(iou(&a, &c), 0.0);
+        // Intersection 50, union 150.
+        assert!((iou(&a, &b) - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(iou(&empty, &empty), 0.0);
+        assert_eq!(iou(&a, &empty), 0.0);
+    }
+
+    #[test]
+    fn test_to_pretty_json() {
+        for sample in round_trip::samples("v40") {
+            let analysis: ImageAnalysisResult =
+                serde_json::from_str(&sample).unwrap();
+
+            let json = analysis.to_pretty_json().unwrap();
+            let reparsed: ImageAnalysisResult =
+                serde_json::from_str(&json).unwrap();
+
+            assert!(json.contains("\n  \"modelVersion\": "));
+            assert_eq!(
+                serde_json::to_value(&reparsed).unwrap(),
+                serde_json::to_value(&analysis).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_samples_round_trip() {
+        for sample in [
+            include_str!("../../tests/samples/v40/all_features.json"),
+            include_str!("../../tests/samples/v40/captions.json"),
+            include_str!("../../tests/samples/v40/dense_captions.json"),
+            include_str!("../../tests/samples/v40/objects.json"),
+            include_str!("../../tests/samples/v40/people.json"),
+            include_str!("../../tests/samples/v40/read.json"),
+            include_str!("../../tests/samples/v40/smart_crops.json"),
+            include_str!("../../tests/samples/v40/tags.json"),
+        ] {
+            round_trip::assert_round_trip::<ImageAnalysisResult>(sample);
+        }
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn test_samples_simd_json() {
+        for sample in round_trip::samples("v40") {
+            round_trip::assert_simd_json_identical::<ImageAnalysisResult>(
+                &sample,
+            );
+        }
+    }
+}
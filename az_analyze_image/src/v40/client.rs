@@ -12,15 +12,49 @@ SPDX-License-Identifier: Apache-2.0 OR MIT
 //! - Must have dimensions greater than 50 x 50 pixels and less than 16,000 x
 //!     16,000 pixels.
 
+use crate::common::cache::{LruCache, RequestImage, RequestKey};
+use crate::common::error_body::{self, ErrorBody};
+#[cfg(feature = "fs")]
+use crate::common::file::{self, FileBody};
+use crate::common::http::HttpVersion;
+use crate::common::request_id;
 use crate::common::secret::Secret;
+use crate::common::success_body::SuccessBody;
 use crate::v40::*;
 use serde::Serialize;
+use std::sync::{Arc, Mutex};
 
 const DEFAULT_API_VERSION: &str = "2023-04-01-preview";
 
+/// Lowercase extensions of the image formats supported by the API, see
+/// [`Client::analyze_directory`].
+#[cfg(feature = "fs")]
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "bmp", "gif", "ico", "jpeg", "jpg", "mpo", "png", "tif", "tiff", "webp",
+];
+
+/// API version of the generally available (GA) Image Analysis 4.0 API.
+pub const GA_API_VERSION: &str = "2024-02-01";
+
+/// Content type of requests carrying raw image data.
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Content type of requests carrying an image URL.
+const JSON: &str = "application/json";
+
+/// Name of the header carrying the client request ID, see
+/// [`Client::with_request_id_generator`].
+pub const CLIENT_REQUEST_ID: &str = crate::common::request_id::HEADER;
+
 /// Maximum input image size allowed by the API.
 pub const MAX_IMAGE_SIZE: usize = 20 * 1024 * 1024; // 20971520 bytes
 
+/// Minimum aspect ratio accepted for the `smartCrops` feature.
+pub const MIN_SMARTCROPS_ASPECT_RATIO: f64 = 0.75;
+
+/// Maximum aspect ratio accepted for the `smartCrops` feature.
+pub const MAX_SMARTCROPS_ASPECT_RATIO: f64 = 1.8;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Represents the various errors that can occur while using the [`Client`].
@@ -37,16 +71,153 @@ pub enum Error {
     #[error("API error response: {0}")]
     API(#[from] ErrorResponse),
 
+    /// The API responded with an error status, but the body is not a
+    /// well-formed error response, e.g. because it comes from a gateway or
+    /// was truncated.
+    ///
+    /// `message` is the `message` of the body if it is JSON with one, or
+    /// else the body itself, shortened. `client_request_id` is the client
+    /// request ID of the request, see [`Error::client_request_id`].
+    ///
+    /// `retry_after` is the delay requested by the `Retry-After` header of the
+    /// response, if any.
+    #[error("HTTP error {status}: {message}")]
+    Http {
+        status: reqwest::StatusCode,
+        message: String,
+        client_request_id: Option<String>,
+        retry_after: Option<std::time::Duration>,
+    },
+
     /// Wrapper around [`reqwest::Error`].
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
 
+    /// Wrapper around [`image::ImageError`].
+    ///
+    /// This error occurs in [`Client::analyze_image_autoresize`] and
+    /// [`Client::analyze_gif_frames`] when the image cannot be decoded or
+    /// re-encoded.
+    #[cfg(feature = "image")]
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    /// Wrapper around [`std::io::Error`].
+    ///
+    /// This error occurs in [`Client::analyze_url_or_bytes`] when `fetch`
+    /// fails and, with the `fs` feature, in `Client::analyze_image_file_async`
+    /// when the file cannot be opened or read and in
+    /// `Client::analyze_directory` when the directory cannot be listed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
     /// Wrapper around [`ValidationError`].
     ///
     /// Represents errors identified through client validation, before any
     /// interaction with external servers.
     #[error(transparent)]
     Validation(#[from] ValidationError),
+
+    /// The API returned a response body that is not JSON of the expected
+    /// shape.
+    ///
+    /// This is the case whichever parser is used; with the `simd-json`
+    /// feature, the error carries the message of `simd-json`.
+    #[error("JSON error: {0}")]
+    Json(#[source] serde_json::Error),
+
+    /// An attempt of a [`ResilientClient`] request did not complete within the
+    /// configured timeout.
+    ///
+    /// Requires the `retry` feature.
+    ///
+    /// [`ResilientClient`]: crate::v40::resilient::ResilientClient
+    #[cfg(feature = "retry")]
+    #[error("Request timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    /// The API response does not contain the result of a visual feature, e.g.
+    /// in [`ImageAnalysisResult::require_people`] if the
+    /// [`VisualFeature::People`] feature was not requested.
+    #[error("The response does not contain the `{0}` result.")]
+    FeatureNotReturned(VisualFeature),
+}
+
+/// Error codes with which the API rejects requests for unsupported features.
+const FEATURE_UNSUPPORTED_CODES: &[&str] = &[
+    "NotSupported",
+    "NotSupportedFeature",
+    "NotSupportedVisualFeature",
+    "OperationBlocked",
+    "UnsupportedFeature",
+];
+
+impl Error {
+    /// Returns `true` if the API rejected the request because a requested
+    /// feature is not supported, e.g. in the region of the resource.
+    ///
+    /// Callers can degrade gracefully by dropping the feature and retrying.
+    pub fn is_feature_unsupported(&self) -> bool {
+        self.has_code(FEATURE_UNSUPPORTED_CODES)
+    }
+
+    /// Returns `true` if the API could not fetch the image from the requested
+    /// URL, e.g. because it requires authentication or is not reachable.
+    ///
+    /// See [`Client::analyze_url_or_bytes`].
+    pub fn is_invalid_image_url(&self) -> bool {
+        self.has_code(&["InvalidImageUrl"])
+    }
+
+    /// Returns `true` if the API rejected the request because the requested
+    /// language is not supported, e.g. for captions.
+    ///
+    /// See [`Client::caption_url_with_fallback`].
+    pub fn is_language_unsupported(&self) -> bool {
+        self.has_code(&["NotSupportedLanguage"])
+    }
+
+    /// Returns the delay requested by the `Retry-After` header of an error
+    /// response, if any.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Error::API(err) => err.retry_after,
+            Error::Http { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Returns the client request ID of the request that failed with an
+    /// [`Error::API`] or [`Error::Http`], as echoed by the API or else as sent,
+    /// if any, for correlation with the logs of the API.
+    ///
+    /// See [`Client::with_request_id_generator`].
+    pub fn client_request_id(&self) -> Option<&str> {
+        match self {
+            Error::API(err) => err.client_request_id.as_deref(),
+            Error::Http {
+                client_request_id, ..
+            } => client_request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this is an [`Error::API`] whose code, or the code of
+    /// its inner error, is one of `codes`.
+    fn has_code(&self, codes: &[&str]) -> bool {
+        let Error::API(err) = self else {
+            return false;
+        };
+
+        std::iter::once(err.error.code.as_str())
+            .chain(
+                err.error
+                    .innererror
+                    .as_ref()
+                    .map(|inner| inner.code.as_str()),
+            )
+            .any(|code| codes.contains(&code))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -59,6 +230,73 @@ pub enum ValidationError {
     #[error("Invalid header value: {0}")]
     InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
 
+    /// The provided endpoint is not an `https://` or `http://` URL with a
+    /// host.
+    ///
+    /// This error occurs specifically in [`Client::new`] and
+    /// [`Client::with_failover`] when a provided endpoint has no scheme, e.g.
+    /// `"myresource.cognitiveservices.azure.com/"`, or no host, e.g.
+    /// `"https:///"`.
+    #[error("Invalid endpoint (expected an `https://` URL with a host): {0}")]
+    InvalidEndpoint(String),
+
+    /// No endpoint was provided.
+    ///
+    /// This error occurs specifically in [`Client::with_failover`] when
+    /// `endpoints` is empty.
+    #[error("No endpoint was provided")]
+    NoEndpoints,
+
+    /// The provided data URI is malformed, is not a base64-encoded image, or
+    /// its image exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// This error occurs specifically in [`Client::analyze_data_uri`].
+    #[cfg(feature = "data-uri")]
+    #[error("Invalid data URI: {0}")]
+    InvalidDataUri(&'static str),
+
+    /// The file exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// This error occurs specifically in [`Client::analyze_image_file_async`]
+    /// and `Client::analyze_image_mmap`, which check the size of the file
+    /// before uploading it.
+    #[cfg(feature = "fs")]
+    #[error("File of {0} bytes exceeds the maximum image size.")]
+    FileTooLarge(u64),
+
+    /// The image exceeds [`MAX_IMAGE_SIZE`] and cannot be downscaled to fit
+    /// without its dimensions dropping to 50 x 50 pixels or below.
+    ///
+    /// This error occurs specifically in [`Client::analyze_image_autoresize`].
+    #[cfg(feature = "image")]
+    #[error("Image cannot be downscaled to fit the maximum image size.")]
+    ImageTooLarge,
+
+    /// The image is 50 pixels or less wide or high; the API requires images
+    /// greater than 50 x 50 pixels.
+    ///
+    /// This error occurs specifically in [`Client::analyze_image_autoresize`].
+    #[cfg(feature = "image")]
+    #[error(
+        "Image of {width} x {height} pixels is too small: must be greater \
+         than {min} x {min} pixels.",
+        min = crate::common::MIN_DIMENSION
+    )]
+    ImageTooSmall {
+        /// Width of the image, in pixels.
+        width: u32,
+
+        /// Height of the image, in pixels.
+        height: u32,
+    },
+
+    /// The frame stride is 0; it must be positive.
+    ///
+    /// This error occurs specifically in [`Client::analyze_gif_frames`].
+    #[cfg(feature = "image")]
+    #[error("Frame stride must be positive.")]
+    ZeroFrameStride,
+
     /// The Analyze Image API v4.0 expects that either [`features`] or
     /// [`model_name`] are specified. The client validates this requirement
     /// before sending a request for an Analyze Image operation.
@@ -67,6 +305,29 @@ pub enum ValidationError {
     /// [`model_name`]: self::AnalyzeImageOptions#structfield.model_name
     #[error("Either `features` or `model_name` must be specified.")]
     NoFeaturesOrModelName,
+
+    /// Both [`features`] and [`model_name`] are specified. `features` applies
+    /// to the generic models and `model_name` to a custom model, so a request
+    /// specifying both is ambiguous. Only returned if enabled with
+    /// [`Client::with_strict_model_features`].
+    ///
+    /// [`features`]: self::AnalyzeImageOptions#structfield.features
+    /// [`model_name`]: self::AnalyzeImageOptions#structfield.model_name
+    #[error("Only one of `features` or `model_name` may be specified.")]
+    ConflictingModelAndFeatures,
+
+    /// A value in [`smartcrops_aspect_ratios`] is not a number between
+    /// [`MIN_SMARTCROPS_ASPECT_RATIO`] and [`MAX_SMARTCROPS_ASPECT_RATIO`]
+    /// (inclusive).
+    ///
+    /// [`smartcrops_aspect_ratios`]: self::AnalyzeImageOptions#structfield.smartcrops_aspect_ratios
+    #[error(
+        "Invalid smart crops aspect ratio `{0}`: must be between {min} and \
+         {max} (inclusive).",
+        min = MIN_SMARTCROPS_ASPECT_RATIO,
+        max = MAX_SMARTCROPS_ASPECT_RATIO
+    )]
+    InvalidSmartCropsAspectRatio(String),
 }
 
 /// Image analysis parameters.
@@ -74,9 +335,11 @@ pub enum ValidationError {
 /// Note that the Analyze Image API requires that either `features` or
 /// `model_name` is specified. The client will return [`NoFeaturesOrModelName`]
 /// in analyze image operations in the event that none of these parameters are
-/// provided.
+/// provided, and [`ConflictingModelAndFeatures`] if both are and
+/// [`Client::with_strict_model_features`] is enabled.
 ///
 /// [`NoFeaturesOrModelName`]: self::ValidationError#variant.NoFeaturesOrModelName
+/// [`ConflictingModelAndFeatures`]: self::ValidationError#variant.ConflictingModelAndFeatures
 // URI Parameters. Only api_version is mandatory.
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct AnalyzeImageOptions<'a> {
@@ -107,15 +370,152 @@ pub struct AnalyzeImageOptions<'a> {
     /// Supported values are between 0.75 and 1.8 (inclusive). Multiple values
     /// should be comma-separated. If this parameter is not specified, the
     /// service will return one crop suggestion with an aspect ratio it sees fit
-    /// between 0.5 and 2.0 (inclusive).
+    /// between 0.5 and 2.0 (inclusive). The client returns
+    /// [`InvalidSmartCropsAspectRatio`] for values outside the supported range.
+    ///
+    /// [`InvalidSmartCropsAspectRatio`]: self::ValidationError#variant.InvalidSmartCropsAspectRatio
     pub smartcrops_aspect_ratios: Option<&'a str>, // "smartcrops-aspect-ratios"
 }
 
+impl<'a> AnalyzeImageOptions<'a> {
+    /// Returns options requesting every visual feature, e.g. for exploration.
+    ///
+    /// See [`VisualFeature::ALL`].
+    pub fn all_features() -> Self {
+        Self {
+            features: Some(VisualFeature::ALL),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the options with [`smartcrops_aspect_ratios`] set to `ratios`.
+    ///
+    /// [`smartcrops_aspect_ratios`]: Self#structfield.smartcrops_aspect_ratios
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v40::client::{
+    ///     AnalyzeImageOptions, SmartCropsAspectRatios,
+    /// };
+    /// use az_analyze_image::v40::VisualFeature;
+    ///
+    /// let features = [VisualFeature::SmartCrops];
+    /// let ratios = SmartCropsAspectRatios::new(&[0.9, 1.33]).unwrap();
+    ///
+    /// let options = AnalyzeImageOptions {
+    ///     features: Some(&features),
+    ///     ..Default::default()
+    /// }
+    /// .smart_crops_ratios(&ratios);
+    ///
+    /// assert_eq!(options.smartcrops_aspect_ratios, Some("0.9,1.33"));
+    /// ```
+    pub fn smart_crops_ratios(
+        self,
+        ratios: &'a SmartCropsAspectRatios,
+    ) -> Self {
+        Self {
+            smartcrops_aspect_ratios: Some(ratios.as_str()),
+            ..self
+        }
+    }
+
+    /// Returns a copy of the options with [`language`] overridden, e.g. to
+    /// analyze the same image in multiple languages.
+    ///
+    /// [`language`]: Self#structfield.language
+    pub fn with_language(&self, language: &'a str) -> Self {
+        Self {
+            language: Some(language),
+            ..self.clone()
+        }
+    }
+}
+
+/// Aspect ratios for the `smartCrops` feature, checked to be between
+/// [`MIN_SMARTCROPS_ASPECT_RATIO`] and [`MAX_SMARTCROPS_ASPECT_RATIO`]
+/// (inclusive), see [`AnalyzeImageOptions::smart_crops_ratios`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartCropsAspectRatios(String);
+
+impl SmartCropsAspectRatios {
+    /// Returns the aspect ratios `ratios`, or
+    /// [`ValidationError::InvalidSmartCropsAspectRatio`] for the first one
+    /// outside the supported range.
+    ///
+    /// No ratios are sent if `ratios` is empty.
+    pub fn new(ratios: &[f64]) -> std::result::Result<Self, ValidationError> {
+        if let Some(ratio) = ratios
+            .iter()
+            .find(|&&ratio| !is_valid_smartcrops_aspect_ratio(ratio))
+        {
+            return Err(ValidationError::InvalidSmartCropsAspectRatio(
+                ratio.to_string(),
+            ));
+        }
+
+        Ok(SmartCropsAspectRatios(
+            ratios
+                .iter()
+                .map(f64::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        ))
+    }
+
+    /// Returns the ratios as the comma-separated value of
+    /// [`AnalyzeImageOptions::smartcrops_aspect_ratios`].
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn is_valid_smartcrops_aspect_ratio(ratio: f64) -> bool {
+    (MIN_SMARTCROPS_ASPECT_RATIO..=MAX_SMARTCROPS_ASPECT_RATIO).contains(&ratio)
+}
+
+/// The result of an Analyze Image operation together with the options that
+/// produced it, as returned by [`Client::analyze_image_url_traced`].
+#[derive(Debug)]
+pub struct AnalysisWithRequest {
+    /// The analysis result.
+    pub result: ImageAnalysisResult,
+
+    /// The requested visual features.
+    pub requested_features: Vec<VisualFeature>,
+
+    /// The requested custom model, if any.
+    pub model_name: Option<String>,
+
+    /// The client request ID sent in the [`CLIENT_REQUEST_ID`] header, if
+    /// any, see [`Client::with_request_id_generator`].
+    pub client_request_id: Option<String>,
+}
+
 /// Client for the [Analyze Image API v4.0](https://learn.microsoft.com/en-us/rest/api/computervision/image-analysis/analyze-image?view=rest-computervision-v4.0-preview%20(2023-04-01)&tabs=HTTP) (`2023-04-01-preview`).
 #[derive(Debug, Clone)]
 pub struct Client {
     client: reqwest::Client,
     url: String,
+    /// Analyze URLs of further endpoints tried in order if the previous one
+    /// fails, see [`Client::with_failover`].
+    failover_urls: Vec<String>,
+    api_version: &'static str,
+    #[cfg(feature = "compression")]
+    compression_threshold: Option<usize>,
+    request_id_generator: Option<fn() -> String>,
+    http_version: HttpVersion,
+    /// See [`Client::with_empty_features`].
+    send_empty_features: bool,
+    /// See [`Client::with_strict_model_features`].
+    strict_model_features: bool,
+    /// Shared by clones of the client, see [`Client::with_cache`].
+    cache: Option<Arc<Mutex<LruCache<RequestKey, ImageAnalysisResult>>>>,
+    /// See [`Client::with_cache_bypass`].
+    bypass_cache: bool,
+    /// See [`Client::with_content_type`].
+    set_content_type: bool,
 }
 
 impl Client {
@@ -125,6 +525,14 @@ impl Client {
     /// - `key`: Azure AI Services key.
     /// - `endpoint`: Azure AI Services Computer Vision endpoint.
     ///
+    /// # Errors
+    ///
+    /// - [`ValidationError::InvalidEndpoint`] if `endpoint` is not an
+    ///   `https://` or `http://` URL with a host.
+    /// - [`ValidationError::InvalidHeaderValue`] if `key` contains characters
+    ///   that are invalid in an HTTP header.
+    /// - [`Error::Reqwest`] if the HTTP client cannot be built.
+    ///
     /// # Example
     ///
     /// ```
@@ -137,16 +545,302 @@ impl Client {
     /// let client = Client::new(key, &endpoint).unwrap();
     /// ```
     pub fn new(key: String, endpoint: &str) -> Result<Self> {
+        Self::with_http_version(key, endpoint, HttpVersion::Auto)
+    }
+
+    /// Create a new `Client` that speaks HTTP/2 without negotiating it first
+    /// ("prior knowledge"), which saves a round trip per connection.
+    ///
+    /// By default, clients negotiate the HTTP version with the server. Only
+    /// use this if the endpoint, and any proxy in between, supports HTTP/2.
+    ///
+    /// Not available on `wasm32`, where the browser chooses the version.
+    ///
+    /// # Parameters
+    /// - `key`: Azure AI Services key.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_http2_prior_knowledge(
+        key: String,
+        endpoint: &str,
+    ) -> Result<Self> {
+        Self::with_http_version(key, endpoint, HttpVersion::Http2PriorKnowledge)
+    }
+
+    /// Create a new `Client` that only uses HTTP/1.1, e.g. behind corporate
+    /// proxies that break HTTP/2.
+    ///
+    /// Not available on `wasm32`, where the browser chooses the version.
+    ///
+    /// # Parameters
+    /// - `key`: Azure AI Services key.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_http1_only(key: String, endpoint: &str) -> Result<Self> {
+        Self::with_http_version(key, endpoint, HttpVersion::Http1Only)
+    }
+
+    /// Create a new `Client` for multiple endpoints, e.g. deployments of the
+    /// same resource in a primary and a secondary region.
+    ///
+    /// Requests are sent to the first endpoint. If it cannot be connected to
+    /// or responds with a server error (`5xx`), the same request is sent to
+    /// the next endpoint, and so on; the response or error of the last
+    /// endpoint tried is returned. Requests whose body is streamed from a
+    /// file cannot be replayed and are only sent to the first endpoint.
+    ///
+    /// `key` is sent to every endpoint, so it must be valid for all of them.
+    ///
+    /// # Parameters
+    /// - `key`: Azure AI Services key.
+    /// - `endpoints`: Azure AI Services Computer Vision endpoints, in order of
+    ///   preference.
+    ///
+    /// # Errors
+    ///
+    /// - [`ValidationError::NoEndpoints`] if `endpoints` is empty.
+    /// - [`ValidationError::InvalidEndpoint`] if any endpoint is not an
+    ///   `https://` or `http://` URL with a host.
+    /// - [`ValidationError::InvalidHeaderValue`] if `key` contains characters
+    ///   that are invalid in an HTTP header.
+    /// - [`Error::Reqwest`] if the HTTP client cannot be built.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v40::client::Client;
+    ///
+    /// let client = Client::with_failover(
+    ///     "key".into(),
+    ///     &[
+    ///         "https://myresource-eastus.cognitiveservices.azure.com/",
+    ///         "https://myresource-westus.cognitiveservices.azure.com/",
+    ///     ],
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn with_failover(key: String, endpoints: &[&str]) -> Result<Self> {
+        let Some((primary, failover)) = endpoints.split_first() else {
+            return Err(ValidationError::NoEndpoints.into());
+        };
+
+        let failover_urls = failover
+            .iter()
+            .map(|endpoint| {
+                Self::validate_endpoint(endpoint)?;
+                Ok(Self::analyze_url(endpoint))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Client {
+            failover_urls,
+            ..Self::new(key, primary)?
+        })
+    }
+
+    fn with_http_version(
+        key: String,
+        endpoint: &str,
+        http_version: HttpVersion,
+    ) -> Result<Self> {
         let secret = Secret::new(key);
+        let client = Self::create_http_client(secret, http_version)?;
+
+        Ok(Client {
+            http_version,
+            ..Self::from_http(client, endpoint)?
+        })
+    }
+
+    /// Create a new `Client` that sends requests with `http`, e.g. an HTTP
+    /// client configured with custom middleware, proxies or timeouts.
+    ///
+    /// `http` is used as is, so it must set the `Ocp-Apim-Subscription-Key`
+    /// header itself, e.g. as a default header.
+    ///
+    /// The `Content-Type` header is set on each request according to the kind
+    /// of input, overriding any default `Content-Type` of `http`, unless
+    /// disabled with [`Client::with_content_type`].
+    ///
+    /// # Parameters
+    /// - `http`: HTTP client to send requests with.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint, or e.g. the
+    ///   URL of a mock server such as `"http://127.0.0.1:8080/"`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ValidationError::InvalidEndpoint`] if `endpoint` is not an
+    ///   `https://` or `http://` URL with a host.
+    pub fn with_http_client(
+        http: reqwest::Client,
+        endpoint: &str,
+    ) -> Result<Self> {
+        Self::from_http(http, endpoint)
+    }
+
+    fn from_http(http: reqwest::Client, base_url: &str) -> Result<Self> {
+        Self::validate_endpoint(base_url)?;
+
+        Ok(Client {
+            client: http,
+            url: Self::analyze_url(base_url),
+            failover_urls: Vec::new(),
+            api_version: DEFAULT_API_VERSION,
+            #[cfg(feature = "compression")]
+            compression_threshold: None,
+            request_id_generator: request_id::default_generator(),
+            http_version: HttpVersion::Auto,
+            send_empty_features: false,
+            strict_model_features: false,
+            cache: None,
+            bypass_cache: false,
+            set_content_type: true,
+        })
+    }
+
+    /// Create a new `Client` for the generally available (GA) API version,
+    /// [`GA_API_VERSION`], rather than `2023-04-01-preview`.
+    ///
+    /// Both versions share the same path. Note that the response schema of
+    /// some features differs between the versions; notably, the GA `read`
+    /// result is organized in blocks rather than pages.
+    ///
+    /// # Parameters
+    /// - `key`: Azure AI Services key.
+    /// - `endpoint`: Azure AI Services Computer Vision endpoint.
+    pub fn v40_ga(key: String, endpoint: &str) -> Result<Self> {
+        Ok(Client {
+            api_version: GA_API_VERSION,
+            ..Self::new(key, endpoint)?
+        })
+    }
+
+    /// Set the `Content-Type` header of each request according to the kind of
+    /// input, as by default, or leave it to the HTTP client if
+    /// `set_content_type` is `false`, e.g. to middleware of a client passed to
+    /// [`Client::with_http_client`] that sets its own.
+    pub fn with_content_type(mut self, set_content_type: bool) -> Self {
+        self.set_content_type = set_content_type;
+        self
+    }
+
+    /// Compress image data larger than `threshold` bytes with gzip before
+    /// uploading it, setting `Content-Encoding: gzip`.
+    ///
+    /// This applies to [`Client::analyze_image`] (and methods built on it)
+    /// and may reduce upload times over slow links. Requests for image URLs
+    /// and [`bytes::Bytes`] input are sent uncompressed.
+    ///
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, threshold: usize) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Send [`features`] as an empty `features=` query parameter when it is
+    /// `Some` but empty, e.g. to observe how the service responds, rather
+    /// than leaving it out as by default.
+    ///
+    /// [`features`]: AnalyzeImageOptions#structfield.features
+    pub fn with_empty_features(mut self, send_empty_features: bool) -> Self {
+        self.send_empty_features = send_empty_features;
+        self
+    }
+
+    /// Reject requests that specify both [`features`] and [`model_name`] with
+    /// [`ValidationError::ConflictingModelAndFeatures`] before sending them,
+    /// rather than leaving it to the service to interpret the combination as
+    /// by default.
+    ///
+    /// [`features`]: AnalyzeImageOptions#structfield.features
+    /// [`model_name`]: AnalyzeImageOptions#structfield.model_name
+    pub fn with_strict_model_features(
+        mut self,
+        strict_model_features: bool,
+    ) -> Self {
+        self.strict_model_features = strict_model_features;
+        self
+    }
+
+    /// Cache the results of up to `capacity` distinct requests in memory, so
+    /// that repeating an identical request returns the cached result without
+    /// sending it, e.g. in pipelines that re-analyze the same images.
+    ///
+    /// Requests are identical if they have the same endpoint, options and
+    /// image URL or data. Entries are keyed by a 128-bit digest of these
+    /// rather than keeping the image data, so memory use grows with
+    /// `capacity` and the size of the results only. When the cache is full,
+    /// the least recently used result is evicted. Only successful results of
+    /// [`analyze_image_url`](Self::analyze_image_url),
+    /// [`analyze_image`](Self::analyze_image) and
+    /// [`analyze_image_bytes`](Self::analyze_image_bytes) (and methods built
+    /// on them, such as `analyze_data_uri`) are cached. See
+    /// [`Client::with_cache_bypass`] to send requests regardless.
+    ///
+    /// The cache is shared by clones of the client and never expires entries;
+    /// see [`Client::clear_cache`].
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(LruCache::new(capacity))));
+        self
+    }
+
+    /// Send every request even if its result is cached, replacing the cached
+    /// result with the new one.
+    ///
+    /// As the cache is shared by clones, a clone can refresh results for the
+    /// original client, e.g. `client.clone().with_cache_bypass(true)`.
+    pub fn with_cache_bypass(mut self, bypass_cache: bool) -> Self {
+        self.bypass_cache = bypass_cache;
+        self
+    }
 
+    /// Removes all results cached since [`Client::with_cache`], if any.
+    pub fn clear_cache(&self) {
+        if let Some(Ok(mut cache)) = self.cache.as_ref().map(|c| c.lock()) {
+            cache.clear();
+        }
+    }
+
+    /// Attach a client request ID produced by `generator` to every request,
+    /// in the [`CLIENT_REQUEST_ID`] header, for correlating logs.
+    ///
+    /// The ID is returned by [`Client::analyze_image_url_traced`] and carried
+    /// by API and HTTP errors, see [`Error::client_request_id`].
+    ///
+    /// With the `uuid` feature, clients attach a random UUID (v4) by default.
+    pub fn with_request_id_generator(
+        mut self,
+        generator: fn() -> String,
+    ) -> Self {
+        self.request_id_generator = Some(generator);
+        self
+    }
+
+    /// Returns a copy of the client that authenticates with `new_key`, e.g.
+    /// to rotate keys in a long-running service without restarting it.
+    ///
+    /// The endpoint and other settings are kept. The HTTP client is rebuilt,
+    /// as its default headers cannot be changed once created. This client is
+    /// left unchanged and can be dropped once no longer in use.
+    ///
+    /// # Parameters
+    /// - `new_key`: Azure AI Services key.
+    pub fn rotate_key(&self, new_key: String) -> Result<Self> {
         Ok(Client {
-            client: Self::create_http_client(secret)?,
-            url: format!("{}computervision/imageanalysis:analyze", endpoint),
+            client: Self::create_http_client(
+                Secret::new(new_key),
+                self.http_version,
+            )?,
+            ..self.clone()
         })
     }
 
     /// Analyze the input image.
     ///
+    /// The URL is sent unchanged in the JSON body of the request, so URLs with
+    /// query strings, such as Azure Blob Storage SAS URLs, are supported.
+    ///
     /// # Parameters
     /// - `image_url`: Publicly reachable URL of an image.
     /// - `options`: Optional parameters to be passed to the Analyze Image API.
@@ -156,7 +850,7 @@ impl Client {
     /// ```
     /// use az_analyze_image::v40::{
     ///     client::{AnalyzeImageOptions, Client},
-    ///     DetectedPerson, ImageAnalysisResult, VisualFeature,
+    ///     format_confidence, DetectedPerson, ImageAnalysisResult, VisualFeature,
     /// };
     /// use std::env;
     ///
@@ -188,9 +882,9 @@ impl Client {
     /// fn print_people_result_values(detected_persons: &[DetectedPerson]) {
     ///     for dp in detected_persons {
     ///         println!(
-    ///             "Detected person with confidence: {:.4} at rectangle\n\
+    ///             "Detected person with confidence: {} at rectangle\n\
     ///              (x, y, w, h) = ({}, {}, {}, {})\n",
-    ///             dp.confidence,
+    ///             format_confidence(dp.confidence, 4),
     ///             dp.bounding_box.x,
     ///             dp.bounding_box.y,
     ///             dp.bounding_box.w,
@@ -208,6 +902,128 @@ impl Client {
             .await
     }
 
+    /// Analyze the input image, also returning the headers of the response.
+    ///
+    /// Useful for monitoring and correlation, e.g. to log the `apim-request-id`
+    /// header of a request. Otherwise the same as
+    /// [`analyze_image_url`](Self::analyze_image_url).
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v40::client::{AnalyzeImageOptions, Client};
+    /// use az_analyze_image::v40::VisualFeature;
+    /// use std::env;
+    ///
+    /// const IMAGE_URL: &str =
+    ///     "https://upload.wikimedia.org/wikipedia/commons/2/2a/Human_faces.jpg";
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let key = env::var("CV_KEY").expect("No CV_KEY");
+    ///     let endpoint = env::var("CV_ENDPOINT").expect("No CV_ENDPOINT");
+    ///
+    ///     let client = Client::new(key, &endpoint).unwrap();
+    ///
+    ///     let features = vec![VisualFeature::Tags];
+    ///     let options = AnalyzeImageOptions {
+    ///         features: Some(&features),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let (_analysis, headers) = client
+    ///         .analyze_image_url_full(IMAGE_URL, options)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     println!("{:?}", headers.get("apim-request-id"));
+    /// }
+    /// ```
+    pub async fn analyze_image_url_full(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<(ImageAnalysisResult, reqwest::header::HeaderMap)> {
+        let request_id = self.generate_request_id();
+        let response = self
+            .send_request(
+                ImageInput::Url(image_url),
+                options,
+                request_id.as_deref(),
+            )
+            .await?;
+        let headers = response.headers().clone();
+
+        let analysis =
+            Self::handle_response(response, request_id.as_deref()).await?;
+
+        Ok((analysis, headers))
+    }
+
+    /// Analyze the input image, also returning the requested visual features
+    /// and model name, and the client request ID, if any, e.g. for logging or
+    /// reproducibility.
+    ///
+    /// Otherwise the same as [`analyze_image_url`](Self::analyze_image_url).
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    pub async fn analyze_image_url_traced(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<AnalysisWithRequest> {
+        let requested_features =
+            options.features.map(<[_]>::to_vec).unwrap_or_default();
+        let model_name = options.model_name.map(str::to_string);
+
+        let client_request_id = self.generate_request_id();
+        let response = self
+            .send_request(
+                ImageInput::Url(image_url),
+                options,
+                client_request_id.as_deref(),
+            )
+            .await?;
+        let result =
+            Self::handle_response(response, client_request_id.as_deref())
+                .await?;
+
+        Ok(AnalysisWithRequest {
+            result,
+            requested_features,
+            model_name,
+            client_request_id,
+        })
+    }
+
+    /// Analyze the input image, also returning the wall-clock time the request
+    /// took as measured by the client, e.g. for latency tracking.
+    ///
+    /// Otherwise the same as [`analyze_image_url`](Self::analyze_image_url).
+    ///
+    /// Not available on `wasm32`, where [`std::time::Instant`] is unsupported.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn analyze_image_url_timed(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<(ImageAnalysisResult, std::time::Duration)> {
+        let start = std::time::Instant::now();
+        let result = self.analyze_image_url(image_url, options).await?;
+
+        Ok((result, start.elapsed()))
+    }
+
     /// Analyze the input image.
     ///
     /// # Parameters
@@ -219,11 +1035,12 @@ impl Client {
     /// ```
     /// use az_analyze_image::v40::{
     ///     client::{AnalyzeImageOptions, Client},
-    ///     DetectedPerson, VisualFeature,
+    ///     format_confidence, DetectedPerson, VisualFeature,
     /// };
     /// use std::env;
     ///
-    /// const IMAGE_PATH: &str = "./tests/images/people.jpg";
+    /// const IMAGE_PATH: &str =
+    ///     concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
     ///
     /// #[tokio::main]
     /// async fn main() {
@@ -254,9 +1071,9 @@ impl Client {
     /// fn print_people_result_values(detected_persons: &[DetectedPerson]) {
     ///     for dp in detected_persons {
     ///         println!(
-    ///             "Detected person with confidence: {:.4} at rectangle\n\
+    ///             "Detected person with confidence: {} at rectangle\n\
     ///              (x, y, w, h) = ({}, {}, {}, {})\n",
-    ///             dp.confidence,
+    ///             format_confidence(dp.confidence, 4),
     ///             dp.bounding_box.x,
     ///             dp.bounding_box.y,
     ///             dp.bounding_box.w,
@@ -275,30 +1092,557 @@ impl Client {
             .await
     }
 
-    async fn analyze_image_(
+    /// Analyze the input image, held as [`bytes::Bytes`].
+    ///
+    /// Unlike [`Client::analyze_image`], the image data is handed to the HTTP
+    /// client without being copied, which suits web frameworks that already
+    /// provide request bodies as `Bytes`.
+    ///
+    /// # Parameters
+    /// - `image_data`: Image bytes.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use az_analyze_image::v40::{
+    ///     client::{AnalyzeImageOptions, Client},
+    ///     VisualFeature,
+    /// };
+    /// use bytes::Bytes;
+    /// use std::env;
+    ///
+    /// const IMAGE_PATH: &str =
+    ///     concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let key = env::var("CV_KEY").expect("No CV_KEY");
+    ///     let endpoint = env::var("CV_ENDPOINT").expect("No CV_ENDPOINT");
+    ///
+    ///     let client = Client::new(key, &endpoint).unwrap();
+    ///
+    ///     let image_bytes = Bytes::from(std::fs::read(IMAGE_PATH).unwrap());
+    ///
+    ///     let features = vec![VisualFeature::People];
+    ///
+    ///     let options = AnalyzeImageOptions {
+    ///         features: Some(&features),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let analysis = client
+    ///         .analyze_image_bytes(image_bytes, options)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let people_result = analysis.people_result.expect("no people result");
+    ///
+    ///     assert!(people_result.values.len() >= 2);
+    /// }
+    /// ```
+    pub async fn analyze_image_bytes(
+        &self,
+        image_data: bytes::Bytes,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        self.analyze_image_(ImageInput::Bytes(image_data), options)
+            .await
+    }
+
+    /// Caption the image at `image_url` in `language`, falling back to English
+    /// if the API does not support captions in `language`.
+    ///
+    /// Returns the caption and the language it is in, i.e. `language` or
+    /// `"en"`. Errors other than [`Error::is_language_unsupported`] are
+    /// returned as is.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `language`: The desired language of the caption, e.g. `"fr"`.
+    pub async fn caption_url_with_fallback<'l>(
+        &self,
+        image_url: &str,
+        language: &'l str,
+    ) -> Result<(CaptionResult, &'l str)> {
+        let features = [VisualFeature::Caption];
+        let options = AnalyzeImageOptions {
+            features: Some(&features),
+            ..Default::default()
+        };
+
+        let (analysis, language) = match self
+            .analyze_image_url(image_url, options.with_language(language))
+            .await
+        {
+            Err(err) if err.is_language_unsupported() && language != "en" => (
+                self.analyze_image_url(image_url, options.with_language("en"))
+                    .await?,
+                "en",
+            ),
+            result => (result?, language),
+        };
+
+        let caption = analysis
+            .caption_result
+            .ok_or(Error::FeatureNotReturned(VisualFeature::Caption))?;
+        Ok((caption, language))
+    }
+
+    /// Analyze the image at `image_url`, uploading its data instead if the API
+    /// cannot fetch the URL, e.g. for images behind authentication or on an
+    /// unreliable CDN.
+    ///
+    /// If the API rejects the URL, i.e. [`Error::is_invalid_image_url`], the
+    /// image data is obtained by awaiting `fetch` and analyzed as with
+    /// [`analyze_image`](Self::analyze_image). `fetch` is dropped without
+    /// being awaited otherwise, so it does no work.
+    ///
+    /// # Parameters
+    /// - `image_url`: URL of an image.
+    /// - `fetch`: Resolves to the data of the image at `image_url`, e.g. a
+    ///   download with the caller's credentials. An error is returned as
+    ///   [`Error::Io`].
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    pub async fn analyze_url_or_bytes(
+        &self,
+        image_url: &str,
+        fetch: impl std::future::Future<Output = std::io::Result<Vec<u8>>>,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        match self.analyze_image_url(image_url, options.clone()).await {
+            Err(err) if err.is_invalid_image_url() => {
+                self.analyze_image(&fetch.await?, options).await
+            }
+            result => result,
+        }
+    }
+
+    /// Analyze the image embedded in a base64 data URI, e.g.
+    /// `data:image/png;base64,...` from a web page or the clipboard.
+    ///
+    /// The payload is decoded and uploaded as binary image data. Returns
+    /// [`ValidationError::InvalidDataUri`] if `uri` is not a base64-encoded
+    /// data URI of an image format supported by the API, the decoded payload
+    /// is not an image of that format, or it exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// Requires the `data-uri` feature.
+    ///
+    /// # Parameters
+    /// - `uri`: Data URI of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "data-uri")]
+    pub async fn analyze_data_uri(
+        &self,
+        uri: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        let image_data = crate::common::data_uri::decode(uri)
+            .map_err(ValidationError::InvalidDataUri)?;
+        if image_data.len() > MAX_IMAGE_SIZE {
+            return Err(ValidationError::InvalidDataUri(
+                "image exceeds the maximum image size",
+            )
+            .into());
+        }
+
+        self.analyze_image_(ImageInput::Bytes(image_data.into()), options)
+            .await
+    }
+
+    /// Analyze the input image, downscaling it first if it exceeds
+    /// [`MAX_IMAGE_SIZE`] (20 MiB).
+    ///
+    /// Images within the limit are sent unchanged. Larger images are re-encoded
+    /// as JPEG and downscaled, preserving their aspect ratio, until they fit.
+    /// Returns [`ValidationError::ImageTooLarge`] if the image cannot fit
+    /// while staying above the minimum dimensions of 50 x 50 pixels.
+    /// Returns [`ValidationError::ImageTooSmall`] if the image is not above
+    /// those dimensions to begin with.
+    ///
+    /// Requires the `image` feature, and must be called within a Tokio
+    /// runtime, on whose blocking thread pool images are downscaled.
+    ///
+    /// # Parameters
+    /// - `image_data`: Image bytes.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "image")]
+    pub async fn analyze_image_autoresize(
+        &self,
+        image_data: &[u8],
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        use crate::common::{resize, MIN_DIMENSION};
+
+        let (width, height) = resize::dimensions(image_data)?;
+        if width <= MIN_DIMENSION || height <= MIN_DIMENSION {
+            return Err(ValidationError::ImageTooSmall { width, height }.into());
+        }
+
+        let image_data =
+            resize::fit_to_size_blocking(image_data, MAX_IMAGE_SIZE)
+                .await?
+                .ok_or(ValidationError::ImageTooLarge)?;
+
+        self.analyze_image_(ImageInput::Data(&image_data), options)
+            .await
+    }
+
+    /// Analyze every `frame_stride`-th frame of an animated GIF, starting with
+    /// the first, e.g. for scene analysis over a short clip.
+    ///
+    /// The API itself only analyzes the first frame of a GIF. This method
+    /// decodes the frames on the blocking thread pool, re-encodes each as PNG
+    /// and analyzes them with at most `concurrency` requests in flight,
+    /// returning one result per analyzed frame, in order.
+    ///
+    /// Each frame is a separate, billed API call; choose `frame_stride` so
+    /// that only as many frames as needed are analyzed, and `concurrency` so
+    /// that the rate limit of the resource is not exceeded.
+    ///
+    /// Returns [`Error::Image`] if the GIF cannot be decoded, and
+    /// [`ValidationError::ZeroFrameStride`] if `frame_stride` is 0.
+    ///
+    /// Requires the `image` feature, and must be called within a Tokio
+    /// runtime.
+    ///
+    /// # Parameters
+    /// - `gif_bytes`: GIF image bytes.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API
+    ///   for every frame.
+    /// - `frame_stride`: Analyze every `frame_stride`-th frame; `1` analyzes
+    ///   all frames.
+    /// - `concurrency`: Maximum number of frames analyzed at a time; `0` is
+    ///   treated as `1`.
+    #[cfg(feature = "image")]
+    pub async fn analyze_gif_frames(
+        &self,
+        gif_bytes: &[u8],
+        options: AnalyzeImageOptions<'_>,
+        frame_stride: usize,
+        concurrency: usize,
+    ) -> Result<Vec<Result<ImageAnalysisResult>>> {
+        use futures_util::stream::{self, StreamExt};
+
+        if frame_stride == 0 {
+            return Err(ValidationError::ZeroFrameStride.into());
+        }
+
+        let gif_bytes = gif_bytes.to_vec();
+        let frames = crate::common::blocking::run(move || {
+            crate::common::frames::gif_frames_png(&gif_bytes, frame_stride)
+        })
+        .await?;
+
+        Ok(stream::iter(frames)
+            .map(|frame| {
+                let options = options.clone();
+                async move {
+                    self.analyze_image_(ImageInput::Data(&frame), options).await
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await)
+    }
+
+    /// Analyze the image at `path`, streaming it from disk rather than reading
+    /// the whole file into memory first.
+    ///
+    /// Peak memory use does not grow with the size of the file, which makes
+    /// this method suitable for large images, e.g. in batch document
+    /// processing. Returns [`ValidationError::FileTooLarge`] without sending a
+    /// request if the file exceeds [`MAX_IMAGE_SIZE`]. The size of the file is
+    /// sent as the `Content-Length` of the request, so no more than that is
+    /// uploaded even if the file grows in the meantime.
+    ///
+    /// Requires the `fs` feature.
+    ///
+    /// # Parameters
+    /// - `path`: Path of an image file.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "fs")]
+    pub async fn analyze_image_file_async(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        match file::open(path.as_ref(), MAX_IMAGE_SIZE as u64).await? {
+            FileBody::Stream(body, length) => {
+                self.analyze_image_(ImageInput::Stream(body, length), options)
+                    .await
+            }
+            FileBody::TooLarge(length) => {
+                Err(ValidationError::FileTooLarge(length).into())
+            }
+        }
+    }
+
+    /// Analyze the image at `path`, memory-mapping the file and uploading the
+    /// mapped pages rather than reading the file into memory first.
+    ///
+    /// Peak heap use does not grow with the size of the file, and, unlike with
+    /// [`Client::analyze_image_file_async`], the body can be sent again, e.g.
+    /// to the next endpoint of [`Client::with_failover`]. Returns
+    /// [`ValidationError::FileTooLarge`] without mapping the file if it
+    /// exceeds [`MAX_IMAGE_SIZE`].
+    ///
+    /// The file must not be modified until the analysis completes: changes
+    /// are visible through the mapping, and truncating the file can crash the
+    /// process, e.g. with `SIGBUS` on Unix.
+    ///
+    /// Requires the `mmap` feature.
+    ///
+    /// # Parameters
+    /// - `path`: Path of an image file.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    #[cfg(feature = "mmap")]
+    pub async fn analyze_image_mmap(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        let file = tokio::fs::File::open(path).await?.into_std().await;
+        let length = file.metadata()?.len();
+        if length > MAX_IMAGE_SIZE as u64 {
+            return Err(ValidationError::FileTooLarge(length).into());
+        }
+
+        // SAFETY: the mapping is only read, and callers must not modify the
+        // file while it is mapped, as documented above.
+        let mmap = unsafe { memmap2::Mmap::map(&file) }?;
+
+        self.analyze_image_(
+            ImageInput::Mapped(bytes::Bytes::from_owner(mmap)),
+            options,
+        )
+        .await
+    }
+
+    /// Analyze the image files directly in `dir`, with at most `concurrency`
+    /// requests in flight at a time.
+    ///
+    /// A file is analyzed if its extension is that of a supported format,
+    /// e.g. `jpg` or `PNG`; other files, subdirectories and entries whose
+    /// metadata cannot be read, e.g. dangling symbolic links, are skipped.
+    /// Each file is streamed from disk as by
+    /// [`Client::analyze_image_file_async`].
+    ///
+    /// Returns the path and result of each analyzed file, sorted by path, or
+    /// [`Error::Io`] if `dir` cannot be listed.
+    ///
+    /// Requires the `fs` feature.
+    ///
+    /// # Parameters
+    /// - `dir`: Path of a directory of image files.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API
+    ///   for every file.
+    /// - `concurrency`: Maximum number of files analyzed at a time; `0` is
+    ///   treated as `1`.
+    #[cfg(feature = "fs")]
+    pub async fn analyze_directory(
+        &self,
+        dir: &std::path::Path,
+        options: AnalyzeImageOptions<'_>,
+        concurrency: usize,
+    ) -> Result<Vec<(std::path::PathBuf, Result<ImageAnalysisResult>)>> {
+        use futures_util::stream::{self, StreamExt};
+
+        let paths = image_files(dir).await?;
+
+        Ok(stream::iter(paths)
+            .map(|path| {
+                let options = options.clone();
+                async move {
+                    let result =
+                        self.analyze_image_file_async(&path, options).await;
+                    (path, result)
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await)
+    }
+
+    async fn analyze_image_(
         &self,
         input: ImageInput<'_>,
         options: AnalyzeImageOptions<'_>,
     ) -> Result<ImageAnalysisResult> {
+        let cache_key = self.cache_key(&input, &options);
+        if let Some(analysis) = cache_key
+            .as_ref()
+            .filter(|_| !self.bypass_cache)
+            .and_then(|key| self.cached(key))
+        {
+            return Ok(analysis);
+        }
+
+        let request_id = self.generate_request_id();
+        let response = self
+            .send_request(input, options, request_id.as_deref())
+            .await?;
+        let analysis =
+            Self::handle_response(response, request_id.as_deref()).await?;
+
+        if let Some(key) = cache_key {
+            self.store(key, &analysis);
+        }
+        Ok(analysis)
+    }
+
+    /// Returns the cache key of a request, or `None` if the client has no
+    /// cache or the request cannot be cached.
+    fn cache_key(
+        &self,
+        input: &ImageInput<'_>,
+        options: &AnalyzeImageOptions<'_>,
+    ) -> Option<RequestKey> {
+        self.cache.as_ref()?;
+
+        let image = match input {
+            ImageInput::Url(image_url) => RequestImage::Url(image_url),
+            ImageInput::Data(image_data) => RequestImage::Data(image_data),
+            ImageInput::Bytes(image_data) => RequestImage::Data(image_data),
+            #[cfg(feature = "mmap")]
+            ImageInput::Mapped(image_data) => RequestImage::Data(image_data),
+            // Digesting a stream would consume it.
+            #[cfg(feature = "fs")]
+            ImageInput::Stream(..) => return None,
+        };
+
+        Some(RequestKey::new(
+            &self.url,
+            &self.query_params(options),
+            image,
+        ))
+    }
+
+    fn cached(&self, key: &RequestKey) -> Option<ImageAnalysisResult> {
+        let mut cache = self.cache.as_ref()?.lock().ok()?;
+
+        cache.get(key).cloned()
+    }
+
+    fn store(&self, key: RequestKey, analysis: &ImageAnalysisResult) {
+        if let Some(Ok(mut cache)) = self.cache.as_ref().map(|c| c.lock()) {
+            cache.insert(key, analysis.clone());
+        }
+    }
+
+    fn analyze_url(endpoint: &str) -> String {
+        format!("{}computervision/imageanalysis:analyze", endpoint)
+    }
+
+    fn generate_request_id(&self) -> Option<String> {
+        self.request_id_generator.map(|generate| generate())
+    }
+
+    async fn send_request(
+        &self,
+        input: ImageInput<'_>,
+        options: AnalyzeImageOptions<'_>,
+        request_id: Option<&str>,
+    ) -> Result<reqwest::Response> {
         self.validate_parameters(&options)?;
 
-        let query_params = Self::build_query_params(&options);
+        let query_params = self.query_params(&options);
+
+        let mut failover_urls = self.failover_urls.iter();
+        let mut url = &self.url;
+        let mut input = input;
+
+        loop {
+            let replay = input.try_clone();
+            let result = self
+                .build_request(url, &query_params, input, request_id)
+                .send()
+                .await;
+
+            match (failover_urls.next(), replay) {
+                (Some(next_url), Some(next_input))
+                    if Self::should_fail_over(&result) =>
+                {
+                    url = next_url;
+                    input = next_input;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    /// Returns `true` if a request with `result` should be sent to the next
+    /// endpoint, if any, see [`Client::with_failover`].
+    fn should_fail_over(
+        result: &std::result::Result<reqwest::Response, reqwest::Error>,
+    ) -> bool {
+        match result {
+            Ok(response) => response.status().is_server_error(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Err(err) => err.is_connect(),
+            #[cfg(target_arch = "wasm32")]
+            Err(_) => false,
+        }
+    }
+
+    fn build_request(
+        &self,
+        url: &str,
+        query_params: &[(&str, String)],
+        input: ImageInput<'_>,
+        request_id: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let mut request = self.client.post(url).query(query_params);
 
-        let request = self.client.post(&self.url).query(&query_params);
+        if let Some(request_id) = request_id {
+            request = request.header(CLIENT_REQUEST_ID, request_id);
+        }
 
-        let response = match input {
+        // The content type depends on the kind of input, so it is set per
+        // request rather than as a default header: URLs are sent as
+        // `application/json` and raw image data as `application/octet-stream`.
+        // See `Client::with_content_type`.
+        match input {
             ImageInput::Url(image_url) => {
                 let image_url = ImageUrl {
                     url: image_url.to_string(),
                 };
-                request.json(&image_url).send().await?
+                // Serializing a string field cannot fail.
+                let body = serde_json::to_vec(&image_url)
+                    .expect("image URL serialization failed");
+                self.content_type(request, JSON).body(body)
             }
-            ImageInput::Data(image_data) => {
-                request.body(image_data.to_vec()).send().await?
+            ImageInput::Data(image_data) => self.data_body(
+                self.content_type(request, OCTET_STREAM),
+                image_data,
+            ),
+            ImageInput::Bytes(image_data) => {
+                self.content_type(request, OCTET_STREAM).body(image_data)
             }
-        };
+            #[cfg(feature = "mmap")]
+            ImageInput::Mapped(image_data) => {
+                self.content_type(request, OCTET_STREAM).body(image_data)
+            }
+            #[cfg(feature = "fs")]
+            ImageInput::Stream(body, length) => self
+                .content_type(request, OCTET_STREAM)
+                .header(reqwest::header::CONTENT_LENGTH, length)
+                .body(body),
+        }
+    }
 
-        Self::handle_response(response).await
+    /// Sets the `Content-Type` of `request` to `content_type`, unless
+    /// disabled with [`Client::with_content_type`].
+    fn content_type(
+        &self,
+        request: reqwest::RequestBuilder,
+        content_type: &'static str,
+    ) -> reqwest::RequestBuilder {
+        if self.set_content_type {
+            request.header(reqwest::header::CONTENT_TYPE, content_type)
+        } else {
+            request
+        }
     }
 
     fn validate_parameters(
@@ -308,27 +1652,166 @@ impl Client {
         if options.features.is_none() && options.model_name.is_none() {
             return Err(ValidationError::NoFeaturesOrModelName);
         }
+
+        // Empty values are not sent, unless requested for `features`, so they
+        // cannot conflict.
+        if self.strict_model_features
+            && options.features.is_some_and(|features| {
+                !features.is_empty() || self.send_empty_features
+            })
+            && options.model_name.is_some_and(|name| !name.is_empty())
+        {
+            return Err(ValidationError::ConflictingModelAndFeatures);
+        }
+
+        if let Some(ratios) = options.smartcrops_aspect_ratios {
+            if !ratios.is_empty() {
+                for ratio in ratios.split(',') {
+                    let valid = ratio
+                        .trim()
+                        .parse::<f64>()
+                        .is_ok_and(is_valid_smartcrops_aspect_ratio);
+                    if !valid {
+                        return Err(
+                            ValidationError::InvalidSmartCropsAspectRatio(
+                                ratio.to_string(),
+                            ),
+                        );
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
+    fn data_body(
+        &self,
+        request: reqwest::RequestBuilder,
+        image_data: &[u8],
+    ) -> reqwest::RequestBuilder {
+        #[cfg(feature = "compression")]
+        if self
+            .compression_threshold
+            .is_some_and(|threshold| image_data.len() > threshold)
+        {
+            return request
+                .header(
+                    reqwest::header::CONTENT_ENCODING,
+                    crate::common::compression::GZIP,
+                )
+                .body(crate::common::compression::gzip(image_data));
+        }
+
+        request.body(image_data.to_vec())
+    }
+
+    /// Returns the result of `response`, or its error, carrying the client
+    /// request ID echoed by the API or else the one sent, `request_id`.
     async fn handle_response(
         response: reqwest::Response,
+        request_id: Option<&str>,
     ) -> Result<ImageAnalysisResult> {
+        let client_request_id =
+            request_id::echoed(response.headers(), request_id);
+
+        // Some partial failures are reported with a success status.
         if response.status().is_success() {
-            let analysis: ImageAnalysisResult = response.json().await?;
-            return Ok(analysis);
+            return match Self::json(response).await? {
+                SuccessBody::<ImageAnalysisResult, ErrorResponse>::Result(
+                    analysis,
+                ) => Ok(analysis),
+                SuccessBody::Error(err) => Err(ErrorResponse {
+                    client_request_id,
+                    ..err
+                }
+                .into()),
+            };
+        }
+
+        // The body of an error response is parsed leniently, so that the
+        // status is reported even if the body is malformed.
+        let status = response.status();
+        let retry_after = Self::retry_after(response.headers());
+        match error_body::from_response::<ErrorResponse>(response).await {
+            ErrorBody::Parsed(err) => Err(ErrorResponse {
+                retry_after,
+                client_request_id,
+                ..err
+            }
+            .into()),
+            ErrorBody::Message(message) => Err(Error::Http {
+                status,
+                message,
+                retry_after,
+                client_request_id,
+            }),
+        }
+    }
+
+    /// Returns the delay of the `Retry-After` header in `headers`, if it is
+    /// given in seconds, as the API does.
+    fn retry_after(
+        headers: &reqwest::header::HeaderMap,
+    ) -> Option<std::time::Duration> {
+        let seconds = headers
+            .get(reqwest::header::RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+        Some(std::time::Duration::from_secs(seconds))
+    }
+
+    /// Deserializes the JSON body of `response`. The body is parsed with
+    /// `simd-json` if the `simd-json` feature is enabled.
+    async fn json<T: serde::de::DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T> {
+        let body = response.bytes().await?;
+
+        #[cfg(feature = "simd-json")]
+        {
+            // Reuses the buffer of `body`, which is not shared.
+            let mut body = Vec::from(body);
+            simd_json::serde::from_slice(&mut body)
+                .map_err(|err| Error::Json(serde::de::Error::custom(err)))
+        }
+
+        #[cfg(not(feature = "simd-json"))]
+        serde_json::from_slice(&body).map_err(Error::Json)
+    }
+
+    /// Returns [`Client::build_query_params`], with an empty `features` if
+    /// requested by [`Client::with_empty_features`].
+    fn query_params(
+        &self,
+        options: &AnalyzeImageOptions,
+    ) -> Vec<(&'static str, String)> {
+        let mut query_params =
+            Self::build_query_params(self.api_version, options);
+
+        if self.send_empty_features
+            && options.features.is_some_and(|features| features.is_empty())
+        {
+            query_params.push(("features", String::new()));
+            query_params[1..].sort_by_key(|&(name, _)| name);
         }
-        let err = response.json::<ErrorResponse>().await?;
-        Err(Error::API(err))
+
+        query_params
     }
 
     // POST {Endpoint}/imageanalysis:analyze?features={features}&model-name={model-name}&language={language}&smartcrops-aspect-ratios={smartcrops-aspect-ratios}&gender-neutral-caption={gender-neutral-caption}&api-version=2023-04-01-preview
+    /// Returns the query parameters for `options`: `api-version` first, then
+    /// the set parameters sorted by name, so that equal options always yield
+    /// the same query string, e.g. for caching responses keyed on it.
     fn build_query_params<'a>(
+        api_version: &str,
         options: &AnalyzeImageOptions,
     ) -> Vec<(&'a str, String)> {
         let mut query_params: Vec<(&str, String)> = Vec::new();
 
-        query_params.push(("api-version", DEFAULT_API_VERSION.to_string()));
+        query_params.push(("api-version", api_version.to_string()));
 
         if let Some(features) = options.features {
             if !features.is_empty() {
@@ -372,15 +1855,38 @@ impl Client {
             }
         }
 
+        query_params[1..].sort_by_key(|&(name, _)| name);
+
         query_params
     }
 
-    fn create_http_client(key: Secret) -> Result<reqwest::Client> {
+    fn validate_endpoint(
+        endpoint: &str,
+    ) -> std::result::Result<(), ValidationError> {
+        let has_scheme =
+            endpoint.starts_with("https://") || endpoint.starts_with("http://");
+        let has_host = reqwest::Url::parse(endpoint)
+            .is_ok_and(|url| url.host_str().is_some_and(|h| !h.is_empty()));
+
+        if has_scheme && has_host {
+            return Ok(());
+        }
+        Err(ValidationError::InvalidEndpoint(endpoint.to_string()))
+    }
+
+    fn create_http_client(
+        key: Secret,
+        http_version: HttpVersion,
+    ) -> Result<reqwest::Client> {
         let headers = Self::create_headers(&key)?;
+        let builder = reqwest::Client::builder().default_headers(headers);
 
-        Ok(reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?)
+        // Large results, e.g. OCR of dense documents, are requested
+        // compressed and decompressed by reqwest.
+        #[cfg(all(feature = "decompression", not(target_arch = "wasm32")))]
+        let builder = builder.gzip(true).brotli(true);
+
+        Ok(http_version.configure(builder).build()?)
     }
 
     fn create_headers(
@@ -389,29 +1895,99 @@ impl Client {
         let mut headers = reqwest::header::HeaderMap::new();
 
         let mut header_key =
-            reqwest::header::HeaderValue::from_str(key.value())?;
+            key.expose_for(reqwest::header::HeaderValue::from_str)?;
         header_key.set_sensitive(true);
 
         headers.insert("Ocp-Apim-Subscription-Key", header_key);
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static(
-                "application/octet-stream",
-            ),
-        );
 
         Ok(headers)
     }
 }
 
+/// Returns the sorted paths of the files in `dir` with an extension in
+/// [`IMAGE_EXTENSIONS`], ignoring case.
+#[cfg(feature = "fs")]
+async fn image_files(
+    dir: &std::path::Path,
+) -> std::io::Result<Vec<std::path::PathBuf>> {
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    let mut paths = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_image = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                IMAGE_EXTENSIONS
+                    .contains(&extension.to_ascii_lowercase().as_str())
+            });
+
+        // Follows symbolic links, unlike `DirEntry::file_type`.
+        let is_file = is_image
+            && tokio::fs::metadata(&path)
+                .await
+                .is_ok_and(|metadata| metadata.is_file());
+        if is_file {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
 enum ImageInput<'a> {
     Url(&'a str),
     Data(&'a [u8]),
+    Bytes(bytes::Bytes),
+    /// A memory-mapped file.
+    #[cfg(feature = "mmap")]
+    Mapped(bytes::Bytes),
+    /// A streaming body and its length in bytes.
+    #[cfg(feature = "fs")]
+    Stream(reqwest::Body, u64),
+}
+
+impl<'a> ImageInput<'a> {
+    /// Returns a copy of the input to send it again, or `None` if its body is
+    /// streamed and cannot be replayed.
+    fn try_clone(&self) -> Option<Self> {
+        match self {
+            ImageInput::Url(image_url) => Some(ImageInput::Url(image_url)),
+            ImageInput::Data(image_data) => Some(ImageInput::Data(image_data)),
+            ImageInput::Bytes(image_data) => {
+                Some(ImageInput::Bytes(image_data.clone()))
+            }
+            #[cfg(feature = "mmap")]
+            ImageInput::Mapped(image_data) => {
+                Some(ImageInput::Mapped(image_data.clone()))
+            }
+            #[cfg(feature = "fs")]
+            ImageInput::Stream(..) => None,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "image")]
+    use crate::common::images;
+    use crate::testing::mock;
+    use crate::v40::testing::{mock_client, options, PEOPLE, TAGS};
+
+    const MOCK_ENDPOINT: &str = "https://mock.cognitiveservices.azure.com/";
+
+    const INVALID_IMAGE_URL: &str = r#"{"error": {"code": "InvalidRequest",
+        "message": "Image URL is badly formatted or not accessible.",
+        "innererror": {"code": "InvalidImageUrl",
+        "message": "Image URL is badly formatted or not accessible."}}}"#;
+
+    const NOT_SUPPORTED_LANGUAGE: &str = r#"{"error": {"code":
+        "InvalidRequest", "message": "The input language is not supported.",
+        "innererror": {"code": "NotSupportedLanguage",
+        "message": "The input language is not supported."}}}"#;
 
     fn header_value_to_str(value: &reqwest::header::HeaderValue) -> &str {
         value.to_str().unwrap_or("")
@@ -426,15 +2002,10 @@ mod tests {
             header_value_to_str(
                 headers.get("Ocp-Apim-Subscription-Key").unwrap()
             ),
-            key.value()
+            "dummy_key"
         );
 
-        assert_eq!(
-            header_value_to_str(
-                headers.get(reqwest::header::CONTENT_TYPE).unwrap()
-            ),
-            "application/octet-stream"
-        );
+        assert!(headers.get(reqwest::header::CONTENT_TYPE).is_none());
     }
 
     #[test]
@@ -449,12 +2020,96 @@ mod tests {
 
     #[test]
     fn test_create_http_client() {
-        let result =
-            Client::create_http_client(Secret::new("dummy_key".into()));
+        let result = Client::create_http_client(
+            Secret::new("dummy_key".into()),
+            HttpVersion::Auto,
+        );
 
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_parameters_smartcrops_aspect_ratios() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let features = vec![VisualFeature::SmartCrops];
+
+        for ratios in ["0.75", "1.0,1.5", "1.8", " 1.2 , 0.9"] {
+            let options = AnalyzeImageOptions {
+                features: Some(&features),
+                smartcrops_aspect_ratios: Some(ratios),
+                ..Default::default()
+            };
+            assert!(client.validate_parameters(&options).is_ok());
+        }
+
+        for ratios in ["0.5", "1.0,2.0", "wide", "1.0,"] {
+            let options = AnalyzeImageOptions {
+                features: Some(&features),
+                smartcrops_aspect_ratios: Some(ratios),
+                ..Default::default()
+            };
+            assert!(matches!(
+                client.validate_parameters(&options),
+                Err(ValidationError::InvalidSmartCropsAspectRatio(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_smart_crops_ratios() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let features = vec![VisualFeature::SmartCrops];
+        let ratios = SmartCropsAspectRatios::new(&[0.75, 1.0, 1.8]).unwrap();
+
+        let options = AnalyzeImageOptions {
+            features: Some(&features),
+            ..Default::default()
+        }
+        .smart_crops_ratios(&ratios);
+
+        assert_eq!(options.smartcrops_aspect_ratios, Some("0.75,1,1.8"));
+        assert!(client.validate_parameters(&options).is_ok());
+        assert_eq!(SmartCropsAspectRatios::new(&[]).unwrap().as_str(), "");
+
+        let err = SmartCropsAspectRatios::new(&[1.0, 2.0, 0.5]).unwrap_err();
+        assert!(matches!(
+            &err,
+            ValidationError::InvalidSmartCropsAspectRatio(ratio) if ratio == "2"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Invalid smart crops aspect ratio `2`: must be between 0.75 and \
+             1.8 (inclusive)."
+        );
+        assert!(SmartCropsAspectRatios::new(&[f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn test_validate_parameters_conflicting_model_and_features() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let features = vec![VisualFeature::Tags];
+
+        let options = AnalyzeImageOptions {
+            features: Some(&features),
+            model_name: Some("my-custom-model"),
+            ..Default::default()
+        };
+        assert!(client.validate_parameters(&options).is_ok());
+
+        let client = client.with_strict_model_features(true);
+        assert!(matches!(
+            client.validate_parameters(&options),
+            Err(ValidationError::ConflictingModelAndFeatures)
+        ));
+
+        let options = AnalyzeImageOptions {
+            features: Some(&[]),
+            model_name: Some("my-custom-model"),
+            ..Default::default()
+        };
+        assert!(client.validate_parameters(&options).is_ok());
+    }
+
     #[test]
     fn test_build_query_params_edge_case() {
         let options = AnalyzeImageOptions {
@@ -464,11 +2119,36 @@ mod tests {
             model_name: Some(""),
             smartcrops_aspect_ratios: Some(""),
         };
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert_eq!(query_params.len(), 1);
     }
 
+    #[test]
+    fn test_query_params_with_empty_features() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = AnalyzeImageOptions {
+            features: Some(&[]),
+            language: Some("en"),
+            ..Default::default()
+        };
+
+        let query_params = client.query_params(&options);
+        assert!(!query_params.iter().any(|&(name, _)| name == "features"));
+
+        let query_params =
+            client.with_empty_features(true).query_params(&options);
+        assert_eq!(
+            query_params,
+            [
+                ("api-version", DEFAULT_API_VERSION.to_string()),
+                ("features", String::new()),
+                ("language", "en".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_error_invalid_header_value() {
         let invalid_key = "mock\n_invalid_key";
@@ -493,7 +2173,8 @@ mod tests {
     fn test_build_query_params_no_options() {
         let options = AnalyzeImageOptions::default();
 
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert_eq!(query_params.len(), 1);
         assert_eq!(
@@ -502,15 +2183,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_query_params_ga_api_version() {
+        let client = Client::v40_ga("dummy_key".into(), MOCK_ENDPOINT).unwrap();
+        let options = AnalyzeImageOptions::default();
+
+        let query_params =
+            Client::build_query_params(client.api_version, &options);
+
+        assert_eq!(
+            query_params,
+            vec![("api-version", "2024-02-01".to_string())]
+        );
+    }
+
     #[test]
     fn test_build_query_params_with_features() {
-        let features = vec![VisualFeature::Tags, VisualFeature::Objects];
-        let options = AnalyzeImageOptions {
-            features: Some(&features),
-            ..Default::default()
-        };
+        let options = options(&[VisualFeature::Tags, VisualFeature::Objects]);
 
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert!(
             query_params.contains(&("features", "tags,objects".to_string()))
@@ -524,7 +2216,8 @@ mod tests {
             ..Default::default()
         };
 
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert!(query_params
             .contains(&("gender-neutral-caption", "true".to_string())));
@@ -537,7 +2230,8 @@ mod tests {
             ..Default::default()
         };
 
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert!(query_params.contains(&("language", "fr".to_string())));
     }
@@ -549,7 +2243,8 @@ mod tests {
             ..Default::default()
         };
 
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert!(query_params
             .contains(&("smartcrops-aspect-ratios", "1.0,1.5".to_string())));
@@ -567,7 +2262,8 @@ mod tests {
             smartcrops_aspect_ratios: Some("1.0"),
         };
 
-        let query_params = Client::build_query_params(&options);
+        let query_params =
+            Client::build_query_params(DEFAULT_API_VERSION, &options);
 
         assert!(query_params
             .contains(&("api-version", DEFAULT_API_VERSION.to_string())));
@@ -579,4 +2275,1316 @@ mod tests {
         assert!(query_params
             .contains(&("smartcrops-aspect-ratios", "1.0".to_string())));
     }
+
+    #[tokio::test]
+    async fn test_analyze_image_bytes() {
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+        let client = mock_client(&endpoint);
+
+        let image_data = bytes::Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        let options = options(&[VisualFeature::People]);
+
+        let analysis = client
+            .analyze_image_bytes(image_data.clone(), options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.body, image_data);
+        assert!(analysis.people_result.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key() {
+        let (endpoint, server) =
+            mock::serve(vec![(200, TAGS), (200, TAGS)]).await;
+        let client = Client::new("old_key".into(), &endpoint).unwrap();
+        let rotated = client.rotate_key("new_key".into()).unwrap();
+
+        let options = options(&[VisualFeature::Tags]);
+
+        rotated
+            .analyze_image_url("https://example.com/image.jpg", options.clone())
+            .await
+            .unwrap();
+        client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert_eq!(
+            requests[0].header("ocp-apim-subscription-key"),
+            Some("new_key")
+        );
+        assert_eq!(
+            requests[1].header("ocp-apim-subscription-key"),
+            Some("old_key")
+        );
+    }
+
+    #[test]
+    fn test_rotate_key_invalid_key() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        assert!(matches!(
+            client.rotate_key("invalid\nkey".into()),
+            Err(Error::Validation(ValidationError::InvalidHeaderValue(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_full_headers() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            200,
+            &[("x-custom-header", "custom-value")],
+            TAGS,
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let (analysis, headers) = client
+            .analyze_image_url_full("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert!(analysis.tags_result.is_some());
+        assert_eq!(headers.get("x-custom-header").unwrap(), "custom-value");
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_image_file_async() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let path =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
+        let options = options(&[VisualFeature::Tags]);
+
+        client
+            .analyze_image_file_async(path, options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(request.body, std::fs::read(path).unwrap());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[tokio::test]
+    async fn test_analyze_image_mmap() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let path =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/people.jpg");
+        let options = options(&[VisualFeature::Tags]);
+
+        client.analyze_image_mmap(path, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(request.body, std::fs::read(path).unwrap());
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_image_file_async_missing_file() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = options(&[VisualFeature::Tags]);
+
+        let result = client
+            .analyze_image_file_async("does/not/exist.jpg", options)
+            .await;
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_image_file_async_too_large() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = options(&[VisualFeature::Tags]);
+
+        // Sparse, so no disk space is used.
+        let path = std::env::temp_dir().join(format!(
+            "az_analyze_image_v40_too_large_{}",
+            std::process::id()
+        ));
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_len(MAX_IMAGE_SIZE as u64 + 1).unwrap();
+
+        let result = client.analyze_image_file_async(&path, options).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::FileTooLarge(size)))
+                if size == MAX_IMAGE_SIZE as u64 + 1
+        ));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_directory() {
+        let (endpoint, server) =
+            mock::serve(vec![(200, TAGS), (200, TAGS)]).await;
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::Tags]);
+
+        let dir = std::env::temp_dir().join(format!(
+            "az_analyze_image_v40_directory_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("nested.jpg")).unwrap();
+        std::fs::write(dir.join("b.PNG"), b"png").unwrap();
+        std::fs::write(dir.join("a.jpg"), b"jpeg").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"not an image").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("missing.jpg"), dir.join("c.jpg"))
+            .unwrap();
+
+        let results = client.analyze_directory(&dir, options, 2).await;
+        let requests = server.await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let results = results.unwrap();
+        let paths: Vec<_> = results.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, [&dir.join("a.jpg"), &dir.join("b.PNG")]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        let mut bodies: Vec<_> = requests.iter().map(|r| &r.body[..]).collect();
+        bodies.sort();
+        assert_eq!(bodies, [&b"jpeg"[..], &b"png"[..]]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_analyze_directory_missing_dir() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let dir = std::path::Path::new("does/not/exist");
+
+        let result = client
+            .analyze_directory(dir, AnalyzeImageOptions::default(), 0)
+            .await;
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_stream_is_bounded_by_its_length() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::Tags]);
+
+        // E.g. a file that grew after its size was checked.
+        let body = reqwest::Body::wrap_stream(
+            tokio_util::io::ReaderStream::new(&[1u8; 64][..]),
+        );
+        client
+            .analyze_image_(ImageInput::Stream(body, 16), options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-length"), Some("16"));
+        assert_eq!(request.body, [1u8; 16]);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_timed() {
+        let delay = std::time::Duration::from_millis(20);
+        let (endpoint, server) =
+            mock::serve_once_delayed(200, TAGS, delay).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let (_, elapsed) = client
+            .analyze_image_url_timed("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert!(elapsed >= delay);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_dense_captions_gender_neutral() {
+        let dense_captions =
+            include_str!("../../tests/samples/v40/dense_captions.json");
+        let flags = [Some(true), Some(false), None];
+        let (endpoint, server) =
+            mock::serve(flags.iter().map(|_| (200, dense_captions)).collect())
+                .await;
+        let client = mock_client(&endpoint);
+
+        let features = vec![VisualFeature::DenseCaptions];
+        for gender_neutral_caption in flags {
+            let options = AnalyzeImageOptions {
+                features: Some(&features),
+                gender_neutral_caption,
+                ..Default::default()
+            };
+
+            let result = client
+                .analyze_image_url("https://example.com/image.jpg", options)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                result.dense_captions_result.unwrap().values[0].text,
+                "a man pointing at a screen"
+            );
+        }
+        let requests = server.await.unwrap();
+
+        assert!(requests[0].head.contains("gender-neutral-caption=true"));
+        assert!(requests[1].head.contains("gender-neutral-caption=false"));
+        assert!(!requests[2].head.contains("gender-neutral-caption"));
+        assert!(requests
+            .iter()
+            .all(|request| request.head.contains("features=denseCaptions")));
+    }
+
+    #[tokio::test]
+    async fn test_with_failover_server_error() {
+        let (primary, primary_server) = mock::serve_once(
+            503,
+            r#"{"error": {"code": "ServiceUnavailable", "message": ""}}"#,
+        )
+        .await;
+        let (secondary, secondary_server) = mock::serve_once(200, PEOPLE).await;
+
+        let client =
+            Client::with_failover("dummy_key".into(), &[&primary, &secondary])
+                .unwrap();
+        let options = options(&[VisualFeature::People]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        let primary_request = primary_server.await.unwrap();
+        let secondary_request = secondary_server.await.unwrap();
+
+        assert!(result.unwrap().people_result.is_some());
+        assert_eq!(primary_request.body, secondary_request.body);
+        assert_eq!(
+            secondary_request.header("ocp-apim-subscription-key"),
+            Some("dummy_key")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_failover_connection_failure() {
+        // Nothing listens on the primary endpoint once the listener is dropped.
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let (secondary, server) = mock::serve_once(200, PEOPLE).await;
+
+        let client =
+            Client::with_failover("dummy_key".into(), &[&primary, &secondary])
+                .unwrap();
+        let options = options(&[VisualFeature::People]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(result.unwrap().people_result.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_with_failover_client_error_is_returned() {
+        let (primary, server) = mock::serve_once(
+            400,
+            include_str!("../../tests/samples/v40/error_image_too_large.json"),
+        )
+        .await;
+
+        // The secondary endpoint is never contacted.
+        let client = Client::with_failover(
+            "dummy_key".into(),
+            &[&primary, "http://127.0.0.1:1/"],
+        )
+        .unwrap();
+        let options = options(&[VisualFeature::People]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(result, Err(Error::API(_))));
+    }
+
+    #[test]
+    fn test_with_failover_invalid_endpoints() {
+        assert!(matches!(
+            Client::with_failover("dummy_key".into(), &[]),
+            Err(Error::Validation(ValidationError::NoEndpoints))
+        ));
+        assert!(matches!(
+            Client::with_failover(
+                "dummy_key".into(),
+                &["https://example.com/", "example.com/"]
+            ),
+            Err(Error::Validation(ValidationError::InvalidEndpoint(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_with_cache() {
+        // The server stops listening after one request, so a second request
+        // would fail to connect.
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+
+        let client = mock_client(&endpoint).with_cache(8);
+        let options = options(&[VisualFeature::People]);
+
+        let first = client
+            .analyze_image_url("https://example.com/image.jpg", options.clone())
+            .await
+            .unwrap();
+        server.await.unwrap();
+        let second = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&first).unwrap(),
+            serde_json::to_value(&second).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_cache_bypass_and_different_requests() {
+        let people = PEOPLE;
+        let (endpoint, server) =
+            mock::serve(vec![(200, people), (200, people), (200, people)])
+                .await;
+
+        let client = mock_client(&endpoint).with_cache(8);
+        let options = options(&[VisualFeature::People]);
+
+        for (image_url, bypass_cache) in [
+            ("https://example.com/image.jpg", false),
+            ("https://example.com/image.jpg", true),
+            ("https://example.com/other.jpg", false),
+        ] {
+            client
+                .clone()
+                .with_cache_bypass(bypass_cache)
+                .analyze_image_url(image_url, options.clone())
+                .await
+                .unwrap();
+        }
+        let requests = server.await.unwrap();
+
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_traced() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let features = [VisualFeature::Tags, VisualFeature::Caption];
+        let options = options(&features);
+
+        let traced = client
+            .analyze_image_url_traced("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert_eq!(traced.requested_features, features);
+        assert_eq!(traced.model_name, None);
+        assert!(traced.result.tags_result.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generator() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint)
+            .with_request_id_generator(|| "request-1".to_string());
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let traced = client
+            .analyze_image_url_traced("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header(CLIENT_REQUEST_ID), Some("request-1"));
+        assert_eq!(traced.client_request_id.as_deref(), Some("request-1"));
+    }
+
+    #[tokio::test]
+    async fn test_error_carries_client_request_id() {
+        let (endpoint, server) = mock::serve_once(
+            400,
+            include_str!("../../tests/samples/v40/error_image_too_large.json"),
+        )
+        .await;
+        let client = mock_client(&endpoint)
+            .with_request_id_generator(|| "request-1".to_string());
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let err = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap_err();
+        server.await.unwrap();
+
+        assert!(matches!(err, Error::API(_)));
+        assert_eq!(err.client_request_id(), Some("request-1"));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[tokio::test]
+    async fn test_default_uuid_request_ids() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let traced = client
+            .analyze_image_url_traced("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        let request_id = traced.client_request_id.unwrap();
+        assert!(uuid::Uuid::parse_str(&request_id).is_ok());
+        assert_eq!(
+            request.header(CLIENT_REQUEST_ID),
+            Some(request_id.as_str())
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_image_autoresize_too_small() {
+        let client = mock_client(MOCK_ENDPOINT);
+        let options = options(&[VisualFeature::Tags]);
+
+        let result = client
+            .analyze_image_autoresize(&images::png(49, 49), options)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::ImageTooSmall {
+                width: 49,
+                height: 49
+            }))
+        ));
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_image_autoresize_small_enough() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::Tags]);
+
+        let image_data = images::png(51, 51);
+        client
+            .analyze_image_autoresize(&image_data, options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.body, image_data);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_sas_url() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        const SAS_URL: &str = "https://account.blob.core.windows.net/images/caf%C3%A9%20photo.jpg?sv=2022-11-02&ss=b&srt=o&sp=r&se=2030-01-01T00:00:00Z&sig=abc%2Bdef%2F%3D";
+        let options = options(&[VisualFeature::Tags]);
+
+        client.analyze_image_url(SAS_URL, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        let body: ImageUrl = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body.url, SAS_URL);
+    }
+
+    #[tokio::test]
+    async fn test_content_type_url_request() {
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::People]);
+
+        client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-type"), Some("application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_content_type_data_request() {
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+        // A default Content-Type of a custom HTTP client must not leak into
+        // the request alongside the per-request one.
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("text/plain"),
+        );
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        let client = Client::with_http_client(http, &endpoint).unwrap();
+
+        let options = options(&[VisualFeature::People]);
+
+        client
+            .analyze_image(&[0xFF, 0xD8, 0xFF, 0xE0], options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(request.head.matches("content-type").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_without_content_type() {
+        let (endpoint, server) =
+            mock::serve(vec![(200, PEOPLE), (200, PEOPLE)]).await;
+        let client =
+            Client::with_http_client(reqwest::Client::new(), &endpoint)
+                .unwrap()
+                .with_content_type(false);
+
+        let options = options(&[VisualFeature::People]);
+
+        client
+            .analyze_image_url("https://example.com/image.jpg", options.clone())
+            .await
+            .unwrap();
+        client
+            .analyze_image(&[0xFF, 0xD8, 0xFF, 0xE0], options)
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert!(requests
+            .iter()
+            .all(|request| request.header("content-type").is_none()));
+        assert_eq!(
+            requests[0].body,
+            br#"{"url":"https://example.com/image.jpg"}"#
+        );
+    }
+
+    #[test]
+    fn test_create_headers_no_content_type() {
+        let headers =
+            Client::create_headers(&Secret::new("dummy_key".into())).unwrap();
+
+        assert!(headers.get(reqwest::header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_error_is_feature_unsupported() {
+        let error = |code: &str| {
+            Error::API(
+                serde_json::from_str(&format!(
+                    r#"{{"error": {{"code": "{}", "message": "message"}}}}"#,
+                    code
+                ))
+                .unwrap(),
+            )
+        };
+
+        assert!(error("OperationBlocked").is_feature_unsupported());
+        assert!(error("NotSupported").is_feature_unsupported());
+        assert!(!error("InvalidRequest").is_feature_unsupported());
+        assert!(!Error::Validation(ValidationError::NoFeaturesOrModelName)
+            .is_feature_unsupported());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_url_or_bytes() {
+        let (endpoint, server) =
+            mock::serve(vec![(400, INVALID_IMAGE_URL), (200, PEOPLE)]).await;
+
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::People]);
+
+        let result = client
+            .analyze_url_or_bytes(
+                "https://example.com/private.jpg",
+                async { Ok(b"image data".to_vec()) },
+                options,
+            )
+            .await;
+        let requests = server.await.unwrap();
+
+        assert!(result.unwrap().people_result.is_some());
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].header("content-type"),
+            Some("application/json")
+        );
+        assert_eq!(
+            requests[1].header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(requests[1].body, b"image data");
+    }
+
+    #[tokio::test]
+    async fn test_caption_url_with_fallback() {
+        let (endpoint, server) = mock::serve(vec![
+            (400, NOT_SUPPORTED_LANGUAGE),
+            (200, include_str!("../../tests/samples/v40/captions.json")),
+        ])
+        .await;
+
+        let client = mock_client(&endpoint);
+
+        let (caption, language) = client
+            .caption_url_with_fallback("https://example.com/image.jpg", "xx")
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert!(!caption.text.is_empty());
+        assert_eq!(language, "en");
+        assert!(requests[0].head.contains("language=xx"));
+        assert!(requests[1].head.contains("language=en"));
+    }
+
+    #[tokio::test]
+    async fn test_caption_url_with_fallback_supported_language() {
+        let (endpoint, server) = mock::serve_once(
+            200,
+            include_str!("../../tests/samples/v40/captions.json"),
+        )
+        .await;
+
+        let client = mock_client(&endpoint);
+
+        let (_, language) = client
+            .caption_url_with_fallback("https://example.com/image.jpg", "fr")
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert_eq!(language, "fr");
+    }
+
+    #[tokio::test]
+    async fn test_analyze_url_or_bytes_other_error() {
+        let (endpoint, server) = mock::serve_once(
+            400,
+            include_str!("../../tests/samples/v40/error_image_too_large.json"),
+        )
+        .await;
+
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::People]);
+
+        let result = client
+            .analyze_url_or_bytes(
+                "https://example.com/image.jpg",
+                async { panic!("unexpected fetch") },
+                options,
+            )
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(result, Err(err) if !err.is_invalid_image_url()));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_url_or_bytes_fetch_error() {
+        let (endpoint, server) = mock::serve_once(400, INVALID_IMAGE_URL).await;
+
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::People]);
+
+        let result = client
+            .analyze_url_or_bytes(
+                "https://example.com/private.jpg",
+                async { Err(std::io::ErrorKind::PermissionDenied.into()) },
+                options,
+            )
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::Io(err))
+                if err.kind() == std::io::ErrorKind::PermissionDenied
+        ));
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_client_is_send_sync() {
+        assert_send_sync::<Client>();
+    }
+
+    #[test]
+    fn test_analyze_futures_are_send() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        assert_send(&client.analyze_image_url("", Default::default()));
+        assert_send(&client.analyze_image(&[], Default::default()));
+        assert_send(
+            &client
+                .analyze_image_bytes(bytes::Bytes::new(), Default::default()),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_in_spawned_task() {
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+        let client = mock_client(&endpoint);
+
+        let task = tokio::spawn(async move {
+            let options = options(&[VisualFeature::People]);
+
+            client
+                .analyze_image_url("https://example.com/image.jpg", options)
+                .await
+        });
+
+        assert!(task.await.unwrap().is_ok());
+        server.await.unwrap();
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_analyze_image_compression() {
+        use std::io::Read;
+
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+        let client = mock_client(&endpoint).with_compression(64);
+
+        let image_data = [0xAB; 1024];
+        let options = options(&[VisualFeature::People]);
+
+        client.analyze_image(&image_data, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-encoding"), Some("gzip"));
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+
+        let mut body = Vec::new();
+        flate2::read::GzDecoder::new(request.body.as_slice())
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, image_data);
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_analyze_image_compression_below_threshold() {
+        let (endpoint, server) = mock::serve_once(200, PEOPLE).await;
+        let client = mock_client(&endpoint).with_compression(4096);
+
+        let image_data = [0xAB; 1024];
+        let options = options(&[VisualFeature::People]);
+
+        client.analyze_image(&image_data, options).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(request.header("content-encoding"), None);
+        assert_eq!(request.body, image_data);
+    }
+
+    #[cfg(all(feature = "compression", feature = "decompression"))]
+    #[tokio::test]
+    async fn test_analyze_image_url_gzip_response() {
+        let json = include_str!("../../tests/samples/v40/read.json");
+        let (endpoint, server) = mock::serve_once_bytes(
+            200,
+            &[("Content-Encoding", "gzip")],
+            crate::common::compression::gzip(json.as_bytes()),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Read]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        let expected: ImageAnalysisResult = serde_json::from_str(json).unwrap();
+        assert!(request.header("accept-encoding").unwrap().contains("gzip"));
+        assert_eq!(
+            result.read_result.unwrap().to_text("\n", "\n\n"),
+            expected.read_result.unwrap().to_text("\n", "\n\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_success_status_with_error_body() {
+        let (endpoint, server) = mock::serve_once(
+            200,
+            include_str!("../../tests/samples/v40/error_image_too_large.json"),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::API(err)) if err.code() == "InvalidRequest"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_malformed_error_body() {
+        let (endpoint, server) = mock::serve(vec![
+            (502, "<html>Bad Gateway</html>"),
+            (500, r#"{"error": {"code": "InternalServerError", "mess"#),
+            (401, r#"{"error": {"message": "Access denied due to invalid key."}}"#),
+        ])
+        .await;
+        let client = mock_client(&endpoint);
+        let options = options(&[VisualFeature::Tags]);
+
+        let mut errors = Vec::new();
+        for _ in 0..3 {
+            let result = client
+                .analyze_image_url(
+                    "https://example.com/image.jpg",
+                    options.clone(),
+                )
+                .await;
+            errors.push(result.unwrap_err());
+        }
+        server.await.unwrap();
+
+        assert!(matches!(
+            &errors[0],
+            Error::Http { status, message, .. }
+                if status.as_u16() == 502 && message == "<html>Bad Gateway</html>"
+        ));
+        assert!(matches!(
+            &errors[1],
+            Error::Http { status, message, .. }
+                if status.as_u16() == 500 && message.starts_with(r#"{"error""#)
+        ));
+        assert!(matches!(
+            &errors[2],
+            Error::Http { status, message, .. }
+                if status.as_u16() == 401
+                    && message == "Access denied due to invalid key."
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "HTTP error 502 Bad Gateway: <html>Bad Gateway</html>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_retry_after() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            429,
+            &[("Retry-After", "2")],
+            r#"{"error": {"code": "429", "message": "Rate limit exceeded."}}"#,
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let err = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await
+            .unwrap_err();
+        server.await.unwrap();
+
+        assert!(matches!(err, Error::API(_)));
+        assert_eq!(err.retry_after(), Some(std::time::Duration::from_secs(2)));
+    }
+
+    #[cfg(feature = "decompression")]
+    #[tokio::test]
+    async fn test_analyze_image_url_invalid_gzip_response() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            200,
+            &[("Content-Encoding", "gzip")],
+            "{}",
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Read]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(result, Err(Error::Reqwest(_))));
+    }
+
+    #[cfg(all(feature = "compression", feature = "decompression"))]
+    #[tokio::test]
+    async fn test_analyze_image_url_gzip_response_invalid_json() {
+        let (endpoint, server) = mock::serve_once_bytes(
+            200,
+            &[("Content-Encoding", "gzip")],
+            crate::common::compression::gzip(b"not json"),
+        )
+        .await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Read]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(result, Err(Error::Json(_))));
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_gif_frames() {
+        use crate::common::frames::tests::gif;
+
+        let (endpoint, server) =
+            mock::serve(vec![(200, PEOPLE), (200, PEOPLE)]).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::People]);
+
+        let gif_bytes = gif(&[[255, 0, 0, 255], [0, 0, 255, 255]]);
+        let results = client
+            .analyze_gif_frames(&gif_bytes, options, 1, 2)
+            .await
+            .unwrap();
+        let requests = server.await.unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_ok()));
+        for request in requests {
+            assert_eq!(
+                request.header("content-type"),
+                Some("application/octet-stream")
+            );
+            assert!(request.body.starts_with(b"\x89PNG"));
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_gif_frames_invalid_gif() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = client.analyze_gif_frames(&[0; 16], options, 1, 1).await;
+
+        assert!(matches!(result, Err(Error::Image(_))));
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_analyze_gif_frames_zero_stride() {
+        use crate::common::frames::tests::gif;
+
+        let client = mock_client(MOCK_ENDPOINT);
+        let gif_bytes = gif(&[[255, 0, 0, 255]]);
+
+        let result = client
+            .analyze_gif_frames(
+                &gif_bytes,
+                AnalyzeImageOptions::default(),
+                0,
+                1,
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::ZeroFrameStride))
+        ));
+    }
+
+    #[cfg(feature = "data-uri")]
+    #[tokio::test]
+    async fn test_analyze_data_uri() {
+        use crate::common::data_uri::tests::PNG_DATA_URI;
+
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client = mock_client(&endpoint);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let result = client.analyze_data_uri(PNG_DATA_URI, options).await;
+        let request = server.await.unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(
+            request.body,
+            crate::common::data_uri::decode(PNG_DATA_URI).unwrap()
+        );
+    }
+
+    #[cfg(feature = "data-uri")]
+    #[tokio::test]
+    async fn test_analyze_data_uri_malformed() {
+        let client = mock_client(MOCK_ENDPOINT);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let result = client
+            .analyze_data_uri("data:image/png;base64,not base64!", options)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidDataUri(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_with_http1_only() {
+        let (endpoint, server) = mock::serve_once(200, TAGS).await;
+        let client =
+            Client::with_http1_only("dummy_key".into(), &endpoint).unwrap();
+        assert_eq!(client.http_version, HttpVersion::Http1Only);
+
+        let options = options(&[VisualFeature::Tags]);
+
+        let result = client
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        let request = server.await.unwrap();
+
+        assert!(result.is_ok());
+        assert!(request.head.lines().next().unwrap().ends_with("HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_with_http2_prior_knowledge() {
+        let client = Client::with_http2_prior_knowledge(
+            "dummy_key".into(),
+            MOCK_ENDPOINT,
+        )
+        .unwrap();
+        assert_eq!(client.http_version, HttpVersion::Http2PriorKnowledge);
+
+        let rotated = client.rotate_key("new_key".into()).unwrap();
+        assert_eq!(rotated.http_version, HttpVersion::Http2PriorKnowledge);
+
+        assert_eq!(
+            Client::new("dummy_key".into(), MOCK_ENDPOINT)
+                .unwrap()
+                .http_version,
+            HttpVersion::Auto
+        );
+    }
+
+    #[test]
+    fn test_new_rejects_endpoint_without_scheme() {
+        let result = Client::new(
+            "dummy_key".into(),
+            "myresource.cognitiveservices.azure.com/",
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidEndpoint(_)))
+        ));
+    }
+
+    #[test]
+    fn test_new_accepts_https_endpoint() {
+        let result = Client::new(
+            "dummy_key".into(),
+            "https://myresource.cognitiveservices.azure.com/",
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_new_rejects_endpoint_without_host() {
+        let result = Client::new("dummy_key".into(), "https:///");
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidEndpoint(_)))
+        ));
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_key() {
+        let result = Client::new("dummy\nkey".into(), MOCK_ENDPOINT);
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation(ValidationError::InvalidHeaderValue(_)))
+        ));
+    }
+
+    #[test]
+    fn test_build_query_params_with_language_override() {
+        let options = AnalyzeImageOptions {
+            language: Some("en"),
+            ..Default::default()
+        };
+
+        let query_params = Client::build_query_params(
+            DEFAULT_API_VERSION,
+            &options.with_language("de"),
+        );
+
+        assert!(query_params.contains(&("language", "de".to_string())));
+        assert!(!query_params.contains(&("language", "en".to_string())));
+        assert_eq!(options.language, Some("en"));
+    }
+
+    #[test]
+    fn test_build_query_params_all_features() {
+        let query_params = Client::build_query_params(
+            DEFAULT_API_VERSION,
+            &AnalyzeImageOptions::all_features(),
+        );
+
+        let (_, features) = query_params
+            .iter()
+            .find(|(name, _)| *name == "features")
+            .unwrap();
+        let features: Vec<&str> = features.split(',').collect();
+
+        assert_eq!(features.len(), VisualFeature::ALL.len());
+        for feature in VisualFeature::ALL {
+            assert!(features.contains(&format!("{}", feature).as_str()));
+        }
+    }
+
+    fn query_param_names<'a>(
+        query_params: &[(&'a str, String)],
+    ) -> Vec<&'a str> {
+        query_params.iter().map(|&(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_build_query_params_order() {
+        let features = vec![VisualFeature::Tags];
+        let options = AnalyzeImageOptions {
+            features: Some(&features),
+            gender_neutral_caption: Some(true),
+            language: Some("en"),
+            smartcrops_aspect_ratios: Some("1.0"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            query_param_names(&Client::build_query_params(
+                DEFAULT_API_VERSION,
+                &options
+            )),
+            [
+                "api-version",
+                "features",
+                "gender-neutral-caption",
+                "language",
+                "smartcrops-aspect-ratios",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_query_params_order_subsets() {
+        let options = AnalyzeImageOptions {
+            model_name: Some("model"),
+            language: Some("en"),
+            ..Default::default()
+        };
+        assert_eq!(
+            query_param_names(&Client::build_query_params(
+                DEFAULT_API_VERSION,
+                &options
+            )),
+            ["api-version", "language", "model-name"]
+        );
+
+        let options = AnalyzeImageOptions {
+            smartcrops_aspect_ratios: Some("1.0"),
+            gender_neutral_caption: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            query_param_names(&Client::build_query_params(
+                DEFAULT_API_VERSION,
+                &options
+            )),
+            [
+                "api-version",
+                "gender-neutral-caption",
+                "smartcrops-aspect-ratios"
+            ]
+        );
+
+        assert_eq!(
+            query_param_names(&Client::build_query_params(
+                DEFAULT_API_VERSION,
+                &AnalyzeImageOptions::default()
+            )),
+            ["api-version"]
+        );
+    }
 }
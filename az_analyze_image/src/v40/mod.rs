@@ -9,9 +9,21 @@ SPDX-License-Identifier: Apache-2.0 OR MIT
 //! This module provides types that map to the official [API definitions](https://learn.microsoft.com/en-us/rest/api/computervision/image-analysis/analyze-image?view=rest-computervision-v4.0-preview%20(2023-04-01)&tabs=HTTP#definitions)
 //! and a [`client::Client`].
 
+#[cfg(feature = "analyzer")]
+pub mod analyzer;
 mod api;
 pub mod client;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "retry")]
+pub mod resilient;
+#[cfg(test)]
+pub(crate) mod testing;
 
+pub use super::common::confidence::{format_confidence, ConfidenceExt};
+pub use super::common::error::{ApiError, ParseFeatureError};
+pub use super::common::rect::{NormRect, PixelRect, Rect};
+pub use super::common::tags::{CommonTag, TagAggregation};
 pub use api::*;
 
 use super::common::*;
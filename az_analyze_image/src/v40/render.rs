@@ -0,0 +1,128 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Drawing of analysis results onto images.
+//!
+//! Requires the `render` feature.
+
+use super::{DetectedPerson, Number};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+
+/// Draws the bounding box of each detected person with confidence greater
+/// than `min_confidence` onto `img`.
+///
+/// Boxes are drawn `thickness` pixels thick, growing outwards, and cycle
+/// through `colors`. Nothing is drawn if `colors` is empty.
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::render::draw_people;
+/// use az_analyze_image::v40::{BoundingBox, DetectedPerson};
+/// use image::{Rgb, RgbImage};
+///
+/// let mut img = RgbImage::new(100, 100);
+/// let people = vec![DetectedPerson {
+///     bounding_box: BoundingBox { x: 10, y: 10, w: 30, h: 60 },
+///     confidence: 0.9,
+/// }];
+///
+/// draw_people(&mut img, &people, 0.75, 2, &[Rgb([1, 255, 79])]);
+///
+/// assert_eq!(img.get_pixel(10, 10), &Rgb([1, 255, 79]));
+/// ```
+pub fn draw_people(
+    img: &mut RgbImage,
+    people: &[DetectedPerson],
+    min_confidence: Number,
+    thickness: u32,
+    colors: &[Rgb<u8>],
+) {
+    if colors.is_empty() {
+        return;
+    }
+
+    for (i, person) in people.iter().enumerate() {
+        let bounding_box = &person.bounding_box;
+
+        if person.confidence <= min_confidence
+            || bounding_box.w == 0
+            || bounding_box.h == 0
+        {
+            continue;
+        }
+
+        let rect = Rect::at(bounding_box.x as i32, bounding_box.y as i32)
+            .of_size(bounding_box.w, bounding_box.h);
+        let color = colors[i % colors.len()];
+
+        draw_thick_rect(img, rect, color, thickness);
+    }
+}
+
+fn draw_thick_rect(
+    img: &mut RgbImage,
+    rect: Rect,
+    color: Rgb<u8>,
+    thickness: u32,
+) {
+    for i in 0..thickness {
+        let new_rect = Rect::at(rect.left() - i as i32, rect.top() - i as i32)
+            .of_size(rect.width() + i * 2, rect.height() + i * 2);
+
+        draw_hollow_rect_mut(img, new_rect, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v40::BoundingBox;
+
+    const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+    const GREEN: Rgb<u8> = Rgb([1, 255, 79]);
+
+    fn person(x: u32, y: u32, confidence: Number) -> DetectedPerson {
+        DetectedPerson {
+            bounding_box: BoundingBox { h: 20, w: 20, x, y },
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_draw_people_above_threshold_only() {
+        let mut img = RgbImage::new(100, 100);
+        let people = vec![person(10, 10, 0.9), person(60, 60, 0.5)];
+
+        draw_people(&mut img, &people, 0.75, 1, &[GREEN]);
+
+        assert_eq!(img.get_pixel(10, 10), &GREEN);
+        assert_eq!(img.get_pixel(29, 29), &GREEN);
+        assert_eq!(img.get_pixel(20, 20), &BLACK);
+        assert_eq!(img.get_pixel(60, 60), &BLACK);
+        assert_eq!(img.get_pixel(79, 79), &BLACK);
+    }
+
+    #[test]
+    fn test_draw_people_thickness() {
+        let mut img = RgbImage::new(100, 100);
+
+        draw_people(&mut img, &[person(10, 10, 0.9)], 0.75, 3, &[GREEN]);
+
+        assert_eq!(img.get_pixel(8, 8), &GREEN);
+        assert_eq!(img.get_pixel(7, 7), &BLACK);
+    }
+
+    #[test]
+    fn test_draw_people_no_colors() {
+        let mut img = RgbImage::new(100, 100);
+
+        draw_people(&mut img, &[person(10, 10, 0.9)], 0.75, 1, &[]);
+
+        assert!(img.pixels().all(|pixel| pixel == &BLACK));
+    }
+}
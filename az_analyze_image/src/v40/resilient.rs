@@ -0,0 +1,410 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! A [`Client`] wrapper that applies a timeout and retries transient failures.
+//!
+//! Requires the `retry` feature.
+
+use super::client::{AnalyzeImageOptions, Client, Error, Result};
+use super::{ApiError, ImageAnalysisResult};
+use std::time::Duration;
+
+/// Default maximum number of attempts per request, including the first.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Default upper bound of the delay between retries.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// A thin layer over [`Client`] that retries requests failing for transient
+/// reasons, such as rate limiting (`429`) or service unavailability (`503`),
+/// with exponential backoff, optionally bounding each attempt by a timeout.
+///
+/// A request is retried if it fails with an [`Error::API`] for which
+/// [`ApiError::is_retryable`] is `true` or that requests a retry with a
+/// `Retry-After` header, an [`Error::Http`] with a `429` or `5xx` status,
+/// whatever its body, an [`Error::Reqwest`] caused by a
+/// timeout, a connection failure or a transport error while sending the
+/// request, e.g. a connection reset or broken pipe, or an [`Error::Timeout`].
+/// Analyze requests are idempotent, so retrying them is safe. Other errors,
+/// including responses that cannot be decoded, are returned immediately.
+///
+/// Before a retry, the client waits for the delay of the `Retry-After` header
+/// of the failed response, see [`Error::retry_after`], or else for the
+/// current backoff, randomized between half and all of it so that clients
+/// failing together do not retry in lockstep. Either delay is capped at the
+/// maximum backoff, see [`ResilientClient::with_backoff`].
+///
+/// Dropping the future returned by [`ResilientClient::analyze_image_url`]
+/// cancels the request, including any pending retry, without side effects.
+///
+/// # Example
+///
+/// ```
+/// use az_analyze_image::v40::client::Client;
+/// use az_analyze_image::v40::resilient::ResilientClient;
+/// use std::time::Duration;
+///
+/// let client = Client::new(
+///     "key".into(),
+///     "https://myresource.cognitiveservices.azure.com/",
+/// )
+/// .unwrap();
+///
+/// let resilient = ResilientClient::new(client)
+///     .with_max_attempts(5)
+///     .with_timeout(Duration::from_secs(30));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResilientClient {
+    client: Client,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    timeout: Option<Duration>,
+}
+
+impl ResilientClient {
+    /// Wrap `client`, making up to [`DEFAULT_MAX_ATTEMPTS`] attempts per
+    /// request with backoff from [`DEFAULT_INITIAL_BACKOFF`] up to
+    /// [`DEFAULT_MAX_BACKOFF`], and no timeout beyond that of `client`.
+    pub fn new(client: Client) -> Self {
+        ResilientClient {
+            client,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            timeout: None,
+        }
+    }
+
+    /// Make up to `max_attempts` attempts per request, including the first.
+    ///
+    /// A value of 0 is treated as 1.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Wait up to `initial` before the first retry, doubling the delay for
+    /// each further retry up to `max`.
+    ///
+    /// `max` also caps the delay requested by a `Retry-After` header, so that
+    /// a misbehaving server cannot stall a request indefinitely.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Abandon an attempt that takes longer than `timeout`, failing it with
+    /// [`Error::Timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the wrapped client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Analyze the image at `image_url` with [`Client::analyze_image_url`],
+    /// applying the timeout and retry policy.
+    ///
+    /// Returns the error of the last attempt if every attempt fails.
+    ///
+    /// # Parameters
+    /// - `image_url`: Publicly reachable URL of an image.
+    /// - `options`: Optional parameters to be passed to the Analyze Image API.
+    pub async fn analyze_image_url(
+        &self,
+        image_url: &str,
+        options: AnalyzeImageOptions<'_>,
+    ) -> Result<ImageAnalysisResult> {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            let result = self
+                .attempt(
+                    self.client.analyze_image_url(image_url, options.clone()),
+                )
+                .await;
+
+            match result {
+                Err(err)
+                    if attempt < self.max_attempts && is_transient(&err) =>
+                {
+                    let delay = err.retry_after().map_or_else(
+                        || jitter(backoff),
+                        |delay| delay.min(self.max_backoff),
+                    );
+                    tokio::time::sleep(delay).await;
+                    backoff = self.next_backoff(backoff);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the backoff following `backoff`, saturating at the maximum.
+    fn next_backoff(&self, backoff: Duration) -> Duration {
+        backoff.saturating_mul(2).min(self.max_backoff)
+    }
+
+    async fn attempt<T>(
+        &self,
+        request: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, request)
+                .await
+                .map_err(|_| Error::Timeout(timeout))?,
+            None => request.await,
+        }
+    }
+}
+
+/// Returns a random delay between half of `backoff` and `backoff`.
+fn jitter(backoff: Duration) -> Duration {
+    backoff.mul_f64(0.5 + fastrand::f64() / 2.0)
+}
+
+/// Returns `true` if the request that failed with `err` may succeed if retried.
+fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::API(err) => err.is_retryable() || err.retry_after.is_some(),
+        Error::Http { status, .. } => {
+            status.is_server_error()
+                || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        }
+        Error::Reqwest(err) => {
+            err.is_timeout() || err.is_connect() || err.is_request()
+        }
+        Error::Timeout(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mock;
+    use crate::v40::testing::{options, PEOPLE};
+    use crate::v40::VisualFeature;
+
+    const TOO_MANY_REQUESTS: &str =
+        r#"{"error": {"code": "429", "message": "Rate limit exceeded."}}"#;
+    const SERVICE_UNAVAILABLE: &str = r#"{"error": {"code":
+        "ServiceUnavailable", "message": "Service unavailable."}}"#;
+
+    fn resilient(endpoint: &str, max_attempts: u32) -> ResilientClient {
+        let client = Client::new("dummy_key".into(), endpoint).unwrap();
+
+        ResilientClient::new(client)
+            .with_max_attempts(max_attempts)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(2))
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_retries_transient_errors() {
+        let (endpoint, server) = mock::serve(vec![
+            (429, TOO_MANY_REQUESTS),
+            (503, SERVICE_UNAVAILABLE),
+            (200, PEOPLE),
+        ])
+        .await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = resilient(&endpoint, 3)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        let requests = server.await.unwrap();
+
+        assert!(result.unwrap().people_result.is_some());
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_retries_gateway_errors() {
+        let (endpoint, server) =
+            mock::serve(vec![(502, "<html>Bad Gateway</html>"), (200, PEOPLE)])
+                .await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = resilient(&endpoint, 2)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(result.unwrap().people_result.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_honours_retry_after() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            503,
+            &[("Retry-After", "1")],
+            "Service Unavailable",
+        )
+        .await;
+
+        let options = options(&[VisualFeature::People]);
+
+        // The retry fails to connect, as the server has stopped listening.
+        let start = tokio::time::Instant::now();
+        let result = resilient(&endpoint, 2)
+            .with_backoff(Duration::from_millis(1), Duration::from_secs(2))
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert!(matches!(result, Err(Error::Reqwest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_caps_retry_after() {
+        let (endpoint, server) = mock::serve_once_with_headers(
+            503,
+            &[("Retry-After", "60")],
+            "Service Unavailable",
+        )
+        .await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let start = tokio::time::Instant::now();
+        let result = resilient(&endpoint, 2)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(matches!(result, Err(Error::Reqwest(_))));
+    }
+
+    #[test]
+    fn test_next_backoff_saturates() {
+        let client = Client::new("dummy_key".into(), "http://localhost/");
+        let resilient = ResilientClient::new(client.unwrap())
+            .with_backoff(Duration::from_secs(1), Duration::MAX);
+
+        assert_eq!(
+            resilient.next_backoff(Duration::from_secs(1)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(resilient.next_backoff(Duration::MAX), Duration::MAX);
+
+        let resilient = resilient
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(3));
+        assert_eq!(
+            resilient.next_backoff(Duration::from_secs(2)),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn test_jitter() {
+        let backoff = Duration::from_millis(100);
+
+        for _ in 0..100 {
+            let delay = jitter(backoff);
+            assert!(delay >= backoff / 2 && delay <= backoff);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_retries_closed_connection() {
+        let (endpoint, server) =
+            mock::serve_once_after_close(200, PEOPLE).await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = resilient(&endpoint, 2)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(result.unwrap().people_result.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_does_not_retry_decode_errors() {
+        let (endpoint, server) = mock::serve_once(200, "not json").await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = resilient(&endpoint, 2)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        // A retry would fail to connect, as the server has stopped listening.
+        assert!(matches!(result, Err(Error::Json(_))));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_gives_up_after_max_attempts() {
+        let (endpoint, server) = mock::serve(vec![
+            (429, TOO_MANY_REQUESTS),
+            (503, SERVICE_UNAVAILABLE),
+        ])
+        .await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = resilient(&endpoint, 2)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::API(err)) if err.code() == "ServiceUnavailable"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_does_not_retry_permanent_errors() {
+        let (endpoint, server) = mock::serve_once(
+            400,
+            include_str!("../../tests/samples/v40/error_image_too_large.json"),
+        )
+        .await;
+
+        let options = options(&[VisualFeature::People]);
+
+        let result = resilient(&endpoint, 3)
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(result, Err(Error::API(_))));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_image_url_timeout() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+        let options = options(&[VisualFeature::People]);
+
+        // The listener never responds.
+        let result = resilient(&endpoint, 2)
+            .with_timeout(Duration::from_millis(50))
+            .analyze_image_url("https://example.com/image.jpg", options)
+            .await;
+
+        assert!(matches!(result, Err(Error::Timeout(_))));
+    }
+}
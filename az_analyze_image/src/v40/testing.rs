@@ -0,0 +1,28 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Shared scaffolding of the unit tests of the v4.0 client.
+
+use super::client::{AnalyzeImageOptions, Client};
+use super::VisualFeature;
+
+/// Response body of a `people` analysis.
+pub const PEOPLE: &str = include_str!("../../tests/samples/v40/people.json");
+
+/// Response body of a `tags` analysis.
+pub const TAGS: &str = include_str!("../../tests/samples/v40/tags.json");
+
+/// Returns a client of `endpoint`, e.g. that of a mock server.
+pub fn mock_client(endpoint: &str) -> Client {
+    Client::new("dummy_key".into(), endpoint).unwrap()
+}
+
+/// Returns options requesting `features`.
+pub fn options(features: &[VisualFeature]) -> AnalyzeImageOptions<'_> {
+    AnalyzeImageOptions {
+        features: Some(features),
+        ..Default::default()
+    }
+}
@@ -3,6 +3,16 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0 OR MIT
 */
 
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::bool_comparison,
+    clippy::from_over_into,
+    clippy::get_first,
+    clippy::op_ref,
+    clippy::redundant_static_lifetimes,
+    clippy::upper_case_acronyms
+)]
+
 mod v32;
 mod v40;
 
@@ -3,9 +3,21 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0 OR MIT
 */
 
+#[cfg(feature = "test-util")]
+pub use az_analyze_image::testing::mock;
+
 pub const MSG_NO_ENV: &'static str =
     "tests require that CV_KEY and CV_ENDPOINT are set";
 
+/// Returns the path of the image fixture `name`, resolved relative to the crate
+/// root so that tests do not depend on the working directory.
+pub fn fixture_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("images")
+        .join(name)
+}
+
 pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
     (a - b).abs() < epsilon
 }
@@ -40,3 +52,12 @@ impl Into<&'static str> for URL {
         }
     }
 }
+
+#[test]
+fn test_fixture_path() {
+    let path = fixture_path("people.jpg");
+
+    assert!(path.is_absolute());
+    assert!(path.is_file(), "missing fixture {}", path.display());
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+}
@@ -55,6 +55,43 @@ async fn test_api_error_invalid_image_size() {
     }
 }
 
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn test_api_error_invalid_image_size_mock() {
+    let (endpoint, server) = mock::serve_once(
+        400,
+        include_str!("../samples/v32/error_invalid_image_size.json"),
+    )
+    .await;
+    let client =
+        Client::with_http_client(reqwest::Client::new(), &endpoint).unwrap();
+
+    let visual_features = vec![VisualFeatureTypes::Faces];
+
+    let options = AnalyzeImageOptions {
+        visual_features: Some(&visual_features),
+        ..Default::default()
+    };
+
+    match client
+        .analyze_image_url(URL::GreaterThan4MB.into(), options)
+        .await
+    {
+        Err(Error::API(err)) => {
+            assert!(
+                err.error.innererror.code
+                    == ComputerVisionInnerErrorCodeValue::InvalidImageSize
+            );
+        }
+        other => {
+            panic!("Expected InvalidImageSize, but got other {:?}", other);
+        }
+    }
+
+    let request = server.await.unwrap();
+    assert!(request.head.starts_with("POST /vision/v3.2/analyze?"));
+}
+
 #[tokio::test]
 async fn test_api_error_unsupported_celebrities_feature() {
     let client = get_client();
@@ -740,3 +740,15 @@ async fn test_analyze_image_tags() {
         assert_eq!(tag.hint, expected_tag.hint);
     }
 }
+
+#[test]
+fn test_visual_feature_types_wildcard_match() {
+    let describe = |feature: &VisualFeatureTypes| match feature {
+        VisualFeatureTypes::Faces => "faces",
+        VisualFeatureTypes::Tags => "tags",
+        _ => "other",
+    };
+
+    assert_eq!(describe(&VisualFeatureTypes::Faces), "faces");
+    assert_eq!(describe(&VisualFeatureTypes::Color), "other");
+}
@@ -32,7 +32,7 @@ async fn test_validation_error_no_features_or_model_name() {
     let client = get_client();
 
     let f =
-        std::fs::read("./tests/images/people.jpg").expect("Error reading file");
+        std::fs::read(fixture_path("people.jpg")).expect("Error reading file");
 
     let options = AnalyzeImageOptions::default();
 
@@ -78,3 +78,43 @@ async fn test_api_error_image_too_large() {
         }
     }
 }
+
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn test_api_error_image_too_large_mock() {
+    let (endpoint, server) = mock::serve_once(
+        400,
+        include_str!("../samples/v40/error_image_too_large.json"),
+    )
+    .await;
+    let client =
+        Client::with_http_client(reqwest::Client::new(), &endpoint).unwrap();
+
+    let features = vec![VisualFeature::People];
+    let options = AnalyzeImageOptions {
+        features: Some(&features),
+        ..Default::default()
+    };
+
+    match client
+        .analyze_image_url(URL::GreaterThan20MB.into(), options)
+        .await
+    {
+        Err(Error::API(err)) => {
+            assert!(err.error.code == "InvalidRequest");
+            assert!(
+                err.error.message
+                    == "The image size is not allowed to be zero or larger \
+                 than 20971520 bytes."
+            )
+        }
+        other => {
+            panic!("Expected Error::API, but got other {:?}", other);
+        }
+    }
+
+    let request = server.await.unwrap();
+    assert!(request
+        .head
+        .starts_with("POST /computervision/imageanalysis:analyze?"));
+}
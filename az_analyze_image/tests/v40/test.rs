@@ -407,3 +407,15 @@ async fn test_analyze_image_tags() {
 
     validate_tags(analysis.tags_result.expect("no tags result"));
 }
+
+#[test]
+fn test_visual_feature_wildcard_match() {
+    let describe = |feature: &VisualFeature| match feature {
+        VisualFeature::Caption => "caption",
+        VisualFeature::People => "people",
+        _ => "other",
+    };
+
+    assert_eq!(describe(&VisualFeature::Caption), "caption");
+    assert_eq!(describe(&VisualFeature::Tags), "other");
+}